@@ -939,6 +939,7 @@ bar() -> ?FOO.
                             },
                             eqwalizer_config: EqwalizerConfig {
                                 enable_all: false,
+                                path: None,
                             },
                         },
                         ProjectId(
@@ -971,6 +972,7 @@ bar() -> ?FOO.
                             },
                             eqwalizer_config: EqwalizerConfig {
                                 enable_all: false,
+                                path: None,
                             },
                         },
                     },
@@ -1083,6 +1085,7 @@ foo() -> ?BAR.
                             },
                             eqwalizer_config: EqwalizerConfig {
                                 enable_all: false,
+                                path: None,
                             },
                         },
                         ProjectId(
@@ -1105,6 +1108,7 @@ foo() -> ?BAR.
                             },
                             eqwalizer_config: EqwalizerConfig {
                                 enable_all: false,
+                                path: None,
                             },
                         },
                     },
@@ -100,10 +100,21 @@ pub struct EqwalizeAll {
         guard(format_guard, "Please use json")
     )]
     pub format: Option<String>,
+    /// Group diagnostics by code, most frequent first
+    #[bpaf(
+        long("group-by"),
+        argument("GROUP_BY"),
+        complete(group_by_completer),
+        fallback(None),
+        guard(group_by_guard, "Please use code")
+    )]
+    pub group_by: Option<String>,
     /// Run with rebar
     pub rebar: bool,
     /// Also eqwalize opted-in generated modules from project
     pub include_generated: bool,
+    /// List the modules that could not be eqwalized, and why
+    pub show_skipped: bool,
 }
 
 #[derive(Clone, Debug, Bpaf)]
@@ -163,6 +174,55 @@ pub struct EqwalizeStats {
     pub include_generated: bool,
 }
 
+#[derive(Clone, Debug, Bpaf)]
+pub struct EqwalizeBaseline {
+    /// Path to directory with project (defaults to `.`)
+    #[bpaf(argument("PROJECT"), fallback(PathBuf::from(".")))]
+    pub project: PathBuf,
+    /// Rebar3 profile to pickup (default is test)
+    #[bpaf(long("as"), argument("PROFILE"), fallback("test".to_string()))]
+    pub profile: String,
+    /// Run with rebar
+    pub rebar: bool,
+    /// Also eqwalize opted-in generated modules from project
+    pub include_generated: bool,
+}
+
+#[derive(Clone, Debug, Bpaf)]
+pub struct AstDiff {
+    /// Path to directory with project (defaults to `.`)
+    #[bpaf(argument("PROJECT"), fallback(PathBuf::from(".")))]
+    pub project: PathBuf,
+    /// Rebar3 profile to pickup (default is test)
+    #[bpaf(long("as"), argument("PROFILE"), fallback("test".to_string()))]
+    pub profile: String,
+    /// Run with rebar
+    pub rebar: bool,
+    /// Module to compare the ASTs of
+    #[bpaf(argument("MODULE"), complete(module_completer))]
+    pub module: String,
+    /// Number of differences tolerated before failing (default 0)
+    #[bpaf(argument("MAX_DIFFS"), fallback(0))]
+    pub max_diffs: usize,
+}
+
+#[derive(Clone, Debug, Bpaf)]
+pub struct EqwalizeChanged {
+    /// Path to directory with project (defaults to `.`)
+    #[bpaf(argument("PROJECT"), fallback(PathBuf::from(".")))]
+    pub project: PathBuf,
+    /// Rebar3 profile to pickup (default is test)
+    #[bpaf(long("as"), argument("PROFILE"), fallback("test".to_string()))]
+    pub profile: String,
+    /// Run with rebar
+    pub rebar: bool,
+    /// Also eqwalize opted-in generated modules from project
+    pub include_generated: bool,
+    /// git diff range, like main...HEAD
+    #[bpaf(positional::< String > ("RANGE"))]
+    pub range: String,
+}
+
 #[derive(Clone, Debug, Bpaf)]
 pub struct BuildInfo {
     /// Path to directory with project (defaults to `.`)
@@ -236,6 +296,8 @@ pub struct Lint {
     /// Filter out all reported diagnostics after this line. Valid only for single file
     #[bpaf(argument("LINE_TO"))]
     pub line_to: Option<u32>,
+    /// Report the unused macros, records, record fields, functions and types of the project
+    pub unused: bool,
     /// Rest of args are space separated list of apps to ignore
     #[bpaf(positional("IGNORED_APPS"))]
     pub ignore_apps: Vec<String>,
@@ -258,6 +320,9 @@ pub enum Command {
     EqwalizeTarget(EqwalizeTarget),
     EqwalizeApp(EqwalizeApp),
     EqwalizeStats(EqwalizeStats),
+    EqwalizeChanged(EqwalizeChanged),
+    EqwalizeBaseline(EqwalizeBaseline),
+    AstDiff(AstDiff),
     BuildInfo(BuildInfo),
     GenerateCompletions(GenerateCompletions),
     RunServer(RunServer),
@@ -297,6 +362,10 @@ pub fn command() -> impl Parser<Command> {
         .help("Eqwalize specified module");
 
     let eqwalize_all = eqwalize_all()
+        .guard(
+            |args| args.format.is_none() || args.group_by.is_none(),
+            "--format and --group-by cannot be used together",
+        )
         .map(Command::EqwalizeAll)
         .to_options()
         .command("eqwalize-all")
@@ -326,6 +395,24 @@ pub fn command() -> impl Parser<Command> {
         .command("eqwalize-stats")
         .help("Return statistics about code quality for eqWAlizer");
 
+    let eqwalize_changed = eqwalize_changed()
+        .map(Command::EqwalizeChanged)
+        .to_options()
+        .command("eqwalize-changed")
+        .help("Eqwalize opted-in modules affected by the changes in a git diff range");
+
+    let eqwalize_baseline = eqwalize_baseline()
+        .map(Command::EqwalizeBaseline)
+        .to_options()
+        .command("eqwalize-baseline")
+        .help("Add eqwalizer:ignore pragmas for all current eqWAlizer errors in a project");
+
+    let ast_diff = ast_diff()
+        .map(Command::AstDiff)
+        .to_options()
+        .command("ast-diff")
+        .help("Compare the raw and ELP-converted eqWAlizer ASTs of a module");
+
     let build_info = build_info()
         .map(Command::BuildInfo)
         .to_options()
@@ -376,6 +463,9 @@ pub fn command() -> impl Parser<Command> {
         version,
         shell,
         eqwalize_stats,
+        eqwalize_changed,
+        eqwalize_baseline,
+        ast_diff,
     ])
     .fallback(Help())
 }
@@ -435,6 +525,18 @@ fn format_guard(format: &Option<String>) -> bool {
     }
 }
 
+fn group_by_completer(_: &Option<String>) -> Vec<(String, Option<String>)> {
+    vec![("code".to_string(), None)]
+}
+
+fn group_by_guard(group_by: &Option<String>) -> bool {
+    match group_by {
+        None => true,
+        Some(g) if g == "code" => true,
+        _ => false,
+    }
+}
+
 fn shell_completer(shell: &String) -> Vec<(String, Option<String>)> {
     let completions = match shell.to_lowercase().chars().next() {
         Some('b') => vec!["bash"],
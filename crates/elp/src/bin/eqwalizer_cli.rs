@@ -7,9 +7,13 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fs;
 use std::io;
 use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
 
 use anyhow::anyhow;
 use anyhow::bail;
@@ -19,6 +23,7 @@ use elp::build;
 use elp::build::load;
 use elp::build::types::LoadResult;
 use elp::cli::Cli;
+use elp_ide::elp_ide_db::elp_base_db::AbsPathBuf;
 use elp_ide::elp_ide_db::elp_base_db::FileId;
 use elp_ide::elp_ide_db::elp_base_db::FileSource;
 use elp_ide::elp_ide_db::elp_base_db::IncludeOtp;
@@ -26,6 +31,7 @@ use elp_ide::elp_ide_db::elp_base_db::ModuleName;
 use elp_ide::elp_ide_db::elp_base_db::VfsPath;
 use elp_ide::elp_ide_db::EqwalizerDiagnostics;
 use elp_ide::elp_ide_db::EqwalizerStats;
+use elp_ide::elp_ide_db::LineIndex;
 use elp_ide::erlang_service;
 use elp_ide::Analysis;
 use elp_project_model::AppName;
@@ -33,19 +39,25 @@ use elp_project_model::AppType;
 use elp_project_model::DiscoverConfig;
 use elp_project_model::ProjectBuildData;
 use fxhash::FxHashMap;
+use fxhash::FxHashSet;
 use indicatif::ParallelProgressIterator;
+use indicatif::ProgressBar;
 use itertools::Itertools;
 use rayon::prelude::*;
 
+use crate::args::AstDiff;
 use crate::args::Eqwalize;
 use crate::args::EqwalizeAll;
 use crate::args::EqwalizeApp;
+use crate::args::EqwalizeBaseline;
+use crate::args::EqwalizeChanged;
 use crate::args::EqwalizePassthrough;
 use crate::args::EqwalizeStats;
 use crate::args::EqwalizeTarget;
 use crate::erlang_service_cli;
 use crate::reporting;
 use crate::reporting::Reporter;
+use crate::reporting::SkipReason;
 
 /// Max parallel eqWAlizer tasks.
 ///
@@ -63,6 +75,9 @@ struct EqwalizerInternalArgs<'a> {
     loaded: &'a LoadResult,
     file_ids: Vec<FileId>,
     reporter: &'a mut dyn reporting::Reporter,
+    /// If set, the modules not eqWAlized are summarized at the end,
+    /// starting with the ones the caller already knows about.
+    skipped: Option<Vec<(String, SkipReason)>>,
 }
 
 pub fn eqwalize_module(args: &Eqwalize, cli: &mut dyn Cli) -> Result<()> {
@@ -83,6 +98,7 @@ pub fn do_eqwalize_module(args: &Eqwalize, loaded: &LoadResult, cli: &mut dyn Cl
         loaded: &loaded,
         file_ids: vec![file_id],
         reporter,
+        skipped: None,
     })
 }
 
@@ -95,8 +111,68 @@ pub fn eqwalize_all(args: &EqwalizeAll, cli: &mut dyn Cli) -> Result<()> {
 
 pub fn do_eqwalize_all(args: &EqwalizeAll, loaded: &LoadResult, cli: &mut dyn Cli) -> Result<()> {
     let analysis = &loaded.analysis();
+    let file_ids = gather_file_ids(analysis, loaded, args.include_generated, cli)?;
+
+    let mut json_reporter;
+    let mut pretty_reporter;
+    let mut grouped_reporter;
+
+    let reporter: &mut dyn Reporter = match (&args.format, &args.group_by) {
+        (Some(_), _) => {
+            json_reporter = reporting::JsonReporter::new(analysis, &loaded, cli);
+            &mut json_reporter
+        }
+        (None, Some(_)) => {
+            grouped_reporter = reporting::GroupedReporter::new(analysis, &loaded, cli);
+            &mut grouped_reporter
+        }
+        (None, None) => {
+            pretty_reporter = reporting::PrettyReporter::new(analysis, &loaded, cli);
+            &mut pretty_reporter
+        }
+    };
+
+    let skipped = if args.show_skipped {
+        Some(generated_modules(analysis, loaded, args.include_generated)?)
+    } else {
+        None
+    };
+
+    advise_on_suite_modules_that_should_not_be_opted_in(&loaded, analysis, reporter)?;
+    eqwalize(EqwalizerInternalArgs {
+        analysis,
+        loaded: &loaded,
+        file_ids,
+        reporter,
+        skipped,
+    })
+}
+
+/// The opted-in generated modules left out by `gather_file_ids`.
+fn generated_modules(
+    analysis: &Analysis,
+    loaded: &LoadResult,
+    include_generated: bool,
+) -> Result<Vec<(String, SkipReason)>> {
+    let module_index = analysis.module_index(loaded.project_id)?;
+    Ok(module_index
+        .iter_own()
+        .filter(|(_name, _source, file_id)| {
+            should_eqwalize(analysis, *file_id, true)
+                && !should_eqwalize(analysis, *file_id, include_generated)
+        })
+        .map(|(name, _source, _file_id)| (name.as_str().to_string(), SkipReason::Generated))
+        .collect())
+}
+
+/// All the modules of the project that should be eqWAlized.
+fn gather_file_ids(
+    analysis: &Analysis,
+    loaded: &LoadResult,
+    include_generated: bool,
+    cli: &dyn Cli,
+) -> Result<Vec<FileId>> {
     let module_index = analysis.module_index(loaded.project_id)?;
-    let include_generated = args.include_generated;
     let pb = cli.progress(module_index.len_own() as u64, "Gathering modules");
     let file_ids: Vec<FileId> = module_index
         .iter_own()
@@ -112,28 +188,169 @@ pub fn do_eqwalize_all(args: &EqwalizeAll, loaded: &LoadResult, cli: &mut dyn Cl
         .flatten()
         .collect();
     pb.finish();
+    Ok(file_ids)
+}
 
-    let mut json_reporter;
-    let mut pretty_reporter;
+pub fn eqwalize_baseline(args: &EqwalizeBaseline, cli: &mut dyn Cli) -> Result<()> {
+    let config = DiscoverConfig::new(args.rebar, &args.profile);
+    let loaded = load::load_project_at(cli, &args.project, config, IncludeOtp::Yes)?;
+    build::compile_deps(&loaded, cli)?;
+    do_eqwalize_baseline(args, &loaded, cli)
+}
 
-    let reporter: &mut dyn Reporter = match args.format {
-        None => {
-            pretty_reporter = reporting::PrettyReporter::new(analysis, &loaded, cli);
-            &mut pretty_reporter
+pub fn do_eqwalize_baseline(
+    args: &EqwalizeBaseline,
+    loaded: &LoadResult,
+    cli: &mut dyn Cli,
+) -> Result<()> {
+    let analysis = &loaded.analysis();
+    let file_ids = gather_file_ids(analysis, loaded, args.include_generated, cli)?;
+    // eqWAlizer gives up on the whole run at the first module it has no
+    // AST for, so leave those out and report them.
+    let module_index = analysis.module_index(loaded.project_id)?;
+    let mut unparsed = Vec::new();
+    let mut parsed = Vec::new();
+    for file_id in file_ids {
+        if analysis
+            .module_ast(file_id, erlang_service::Format::OffsetEtf)?
+            .is_ok()
+        {
+            parsed.push(file_id);
+        } else if let Some(module) = module_index.module_for_file(file_id) {
+            unparsed.push(module.as_str().to_string());
         }
-        Some(_) => {
-            json_reporter = reporting::JsonReporter::new(analysis, &loaded, cli);
-            &mut json_reporter
+    }
+    unparsed.sort();
+    for module in &unparsed {
+        writeln!(cli, "Skipped module {}, which could not be parsed", module)?;
+    }
+    let file_ids = parsed;
+    if file_ids.is_empty() {
+        bail!("No files to eqWAlize detected")
+    }
+
+    let pb = cli.progress(file_ids.len() as u64, "EqWAlizing");
+    let output = eqwalize_in_parallel(loaded, file_ids, &pb);
+    pb.finish();
+
+    let diagnostics_by_module = match output {
+        EqwalizerDiagnostics::Diagnostics(diagnostics_by_module) => diagnostics_by_module,
+        EqwalizerDiagnostics::NoAst { module } => {
+            // Only left for modules needed by the others, e.g. headers
+            bail!(
+                "Could not type-check because module {} could not be parsed",
+                module
+            )
+        }
+        EqwalizerDiagnostics::Error(error) => {
+            bail!("Could not eqwalize: {}", error)
         }
     };
 
-    advise_on_suite_modules_that_should_not_be_opted_in(&loaded, analysis, reporter)?;
-    eqwalize(EqwalizerInternalArgs {
-        analysis,
-        loaded: &loaded,
-        file_ids,
-        reporter,
-    })
+    // Diagnostics can be in a header rather than in the module they are
+    // reported for, and several diagnostics on a line share a pragma.
+    let mut codes_by_file: FxHashMap<FileId, BTreeMap<u32, BTreeSet<String>>> =
+        FxHashMap::default();
+    for (module, diagnostics) in diagnostics_by_module {
+        let module_file_id = module_index
+            .file_for_module(module.as_str())
+            .with_context(|| format!("module {} not found", module))?;
+        for diagnostic in &diagnostics {
+            let file_id = diagnostic_file_id(loaded, &diagnostic.uri).unwrap_or(module_file_id);
+            let line_index = analysis.line_index(file_id)?;
+            let line = line_index.line_col(diagnostic.range.start()).line;
+            codes_by_file
+                .entry(file_id)
+                .or_default()
+                .entry(line)
+                .or_default()
+                .insert(diagnostic.code.clone());
+        }
+    }
+
+    let mut ignored = 0;
+    let mut files = 0;
+    for (file_id, codes_by_line) in codes_by_file
+        .into_iter()
+        .sorted_by_key(|(file_id, _)| loaded.vfs.file_path(*file_id).to_string())
+    {
+        let line_index = analysis.line_index(file_id)?;
+        let text = analysis.file_text(file_id)?;
+        let (new_text, count) = insert_ignore_pragmas(&text, &line_index, &codes_by_line);
+        if count == 0 {
+            continue;
+        }
+        let vfs_path = loaded.vfs.file_path(file_id);
+        let path = vfs_path
+            .as_path()
+            .with_context(|| format!("no path for {}", vfs_path))?;
+        fs::write(path, new_text).with_context(|| format!("writing {}", vfs_path))?;
+        ignored += count;
+        files += 1;
+    }
+    writeln!(
+        cli,
+        "Added {} eqwalizer:ignore pragmas in {} files",
+        ignored, files
+    )?;
+    Ok(())
+}
+
+/// The file of the project `uri` names, if any. eqWAlizer usually sets
+/// it to the documentation of the diagnostic instead.
+fn diagnostic_file_id(loaded: &LoadResult, uri: &str) -> Option<FileId> {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let path = AbsPathBuf::try_from(PathBuf::from(path)).ok()?;
+    loaded.vfs.file_id(&VfsPath::from(path))
+}
+
+const IGNORE_PRAGMA: &str = "% eqwalizer:ignore";
+
+/// Whether `line` is an `eqwalizer:ignore` comment, with any number of
+/// `%`, e.g. `%% eqwalizer:ignore`.
+fn is_ignore_pragma(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with('%')
+        && line
+            .trim_start_matches('%')
+            .trim_start()
+            .starts_with("eqwalizer:ignore")
+}
+
+/// Insert a `% eqwalizer:ignore` comment before each of the given lines,
+/// with the same indentation, unless the previous line already is one.
+/// Returns the new text and the number of comments inserted.
+fn insert_ignore_pragmas(
+    text: &str,
+    line_index: &LineIndex,
+    codes_by_line: &BTreeMap<u32, BTreeSet<String>>,
+) -> (String, usize) {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut result = text.to_string();
+    let mut count = 0;
+    // Going backwards keeps the offsets of the lines still to be done valid.
+    for (&line, codes) in codes_by_line.iter().rev() {
+        let already_ignored = line
+            .checked_sub(1)
+            .and_then(|prev| lines.get(prev as usize))
+            .map_or(false, |prev| is_ignore_pragma(prev));
+        if already_ignored {
+            continue;
+        }
+        let line_text = lines.get(line as usize).copied().unwrap_or_default();
+        let indent: String = line_text
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        let start = match line_index.line_at(line as usize) {
+            Some(start) => u32::from(start) as usize,
+            None => continue,
+        };
+        let pragma = format!("{}{} {}\n", indent, IGNORE_PRAGMA, codes.iter().join(", "));
+        result.insert_str(start, &pragma);
+        count += 1;
+    }
+    (result, count)
 }
 
 pub fn eqwalize_app(args: &EqwalizeApp, cli: &mut dyn Cli) -> Result<()> {
@@ -165,6 +382,7 @@ pub fn do_eqwalize_app(args: &EqwalizeApp, loaded: &LoadResult, cli: &mut dyn Cl
         loaded: &loaded,
         file_ids,
         reporter: &mut reporter,
+        skipped: None,
     })
 }
 
@@ -226,9 +444,104 @@ elp eqwalize-target erl/chatd #same as //erl/chatd/... but enables shell complet
         loaded: &loaded,
         file_ids,
         reporter: &mut reporter,
+        skipped: None,
     })
 }
 
+pub fn eqwalize_changed(args: &EqwalizeChanged, cli: &mut dyn Cli) -> Result<()> {
+    let config = DiscoverConfig::new(args.rebar, &args.profile);
+    let loaded = load::load_project_at(cli, &args.project, config, IncludeOtp::Yes)?;
+    build::compile_deps(&loaded, cli)?;
+    do_eqwalize_changed(args, &loaded, cli)
+}
+
+pub fn do_eqwalize_changed(
+    args: &EqwalizeChanged,
+    loaded: &LoadResult,
+    cli: &mut dyn Cli,
+) -> Result<()> {
+    let analysis = &loaded.analysis();
+    let changed = git_changed_files(&loaded.project.root(), &args.range)?;
+    let file_ids: Vec<FileId> = affected_modules(analysis, loaded, &changed)?
+        .into_iter()
+        .filter(|&file_id| should_eqwalize(analysis, file_id, args.include_generated))
+        .collect();
+    if file_ids.is_empty() {
+        writeln!(
+            cli,
+            "No opted-in modules affected by changes in {}",
+            args.range
+        )?;
+        return Ok(());
+    }
+    let mut reporter = reporting::PrettyReporter::new(analysis, &loaded, cli);
+    eqwalize(EqwalizerInternalArgs {
+        analysis,
+        loaded: &loaded,
+        file_ids,
+        reporter: &mut reporter,
+        skipped: None,
+    })
+}
+
+/// The `.erl` and `.hrl` files changed in the given git diff range, still
+/// present in the project.
+fn git_changed_files(root: &AbsPathBuf, range: &str) -> Result<Vec<AbsPathBuf>> {
+    let output = Command::new("git")
+        .current_dir(root)
+        .args(["diff", "--name-only", "--relative", range])
+        .output()
+        .context("Failed to run git diff")?;
+    if !output.status.success() {
+        bail!(
+            "git diff {} failed: {}",
+            range,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let files = String::from_utf8(output.stdout)?
+        .lines()
+        .filter(|path| path.ends_with(".erl") || path.ends_with(".hrl"))
+        .map(|path| root.join(path))
+        .collect();
+    Ok(files)
+}
+
+/// The modules of the project affected by changes to the given files:
+/// changed modules themselves, and all modules including a changed
+/// header, directly or through other headers.
+fn affected_modules(
+    analysis: &Analysis,
+    loaded: &LoadResult,
+    changed: &[AbsPathBuf],
+) -> Result<Vec<FileId>> {
+    let changed: FxHashSet<FileId> = changed
+        .iter()
+        .filter_map(|path| loaded.vfs.file_id(&VfsPath::from(path.clone())))
+        .collect();
+    let module_index = analysis.module_index(loaded.project_id)?;
+    let mut affected = Vec::new();
+    for (_name, _source, file_id) in module_index.iter_own() {
+        if changed.contains(&file_id) {
+            affected.push(file_id);
+            continue;
+        }
+        let mut seen = FxHashSet::default();
+        let mut to_visit = vec![file_id];
+        while let Some(current) = to_visit.pop() {
+            for included in analysis.included_file_ids(current)? {
+                if seen.insert(included) {
+                    to_visit.push(included);
+                }
+            }
+        }
+        if !seen.is_disjoint(&changed) {
+            affected.push(file_id);
+        }
+    }
+    Ok(affected)
+}
+
 pub fn eqwalize_passthrough(args: &EqwalizePassthrough, cli: &mut dyn Cli) -> Result<()> {
     let config = DiscoverConfig::new(!args.buck, &args.profile);
     let loaded = load::load_project_at(cli, &args.project, config, IncludeOtp::No)?;
@@ -255,11 +568,14 @@ pub fn eqwalize_passthrough(args: &EqwalizePassthrough, cli: &mut dyn Cli) -> Re
         bail!("Aborting because there was an error parsing");
     }
 
-    let status = loaded.analysis().eqwalizer().passthrough(
-        args.args.as_ref(),
-        loaded.project.build_info_file().unwrap().as_ref(),
-        ast_dir.as_ref(),
-    )?;
+    let status = loaded
+        .analysis()
+        .project_eqwalizer(loaded.project_id)
+        .passthrough(
+            args.args.as_ref(),
+            loaded.project.build_info_file().unwrap().as_ref(),
+            ast_dir.as_ref(),
+        )?;
 
     let code = status
         .code()
@@ -297,12 +613,36 @@ pub fn eqwalize_stats(args: &EqwalizeStats, cli: &mut dyn Cli) -> Result<()> {
     Ok(())
 }
 
+pub fn ast_diff(args: &AstDiff, cli: &mut dyn Cli) -> Result<()> {
+    let config = DiscoverConfig::new(args.rebar, &args.profile);
+    let loaded = load::load_project_at(cli, &args.project, config, IncludeOtp::Yes)?;
+    build::compile_deps(&loaded, cli)?;
+    let analysis = &loaded.analysis();
+    analysis
+        .module_file_id(loaded.project_id, &args.module)?
+        .with_context(|| format!("Module {} not found", &args.module))?;
+    let diff = analysis
+        .eqwalizer_ast_diff(loaded.project_id, &args.module)?
+        .map_err(|err| anyhow!("Could not compare ASTs of {}: {}", &args.module, err))?;
+    cli.write(serde_json::to_string(&diff)?.as_bytes())?;
+    if diff.len() > args.max_diffs {
+        bail!(
+            "{} AST differences for module {}, more than the {} allowed",
+            diff.len(),
+            &args.module,
+            args.max_diffs
+        )
+    }
+    Ok(())
+}
+
 fn eqwalize(
     EqwalizerInternalArgs {
         analysis,
         loaded,
         file_ids,
         reporter,
+        mut skipped,
     }: EqwalizerInternalArgs,
 ) -> Result<()> {
     if file_ids.is_empty() {
@@ -310,36 +650,27 @@ fn eqwalize(
     }
 
     pre_parse_for_speed(reporter, analysis.clone(), &file_ids);
+    if let Some(skipped) = &mut skipped {
+        // The ASTs are cached by the pre-parse, so this is cheap.
+        let module_index = analysis.module_index(loaded.project_id)?;
+        for file_id in &file_ids {
+            if !analysis
+                .module_ast(*file_id, erlang_service::Format::OffsetEtf)?
+                .is_ok()
+            {
+                if let Some(module) = module_index.module_for_file(*file_id) {
+                    skipped.push((module.as_str().to_string(), SkipReason::ParseError));
+                }
+            }
+        }
+    }
 
     let files_count = file_ids.len();
     let pb = reporter.progress(files_count as u64, "EqWAlizing");
-    let output = loaded.with_eqwalizer_progress_bar(pb.clone(), move |analysis| {
-        let chunk_size = (files_count + MAX_EQWALIZER_TASKS - 1) / MAX_EQWALIZER_TASKS;
-        let pool = rayon::ThreadPoolBuilder::new()
-            .stack_size(THREAD_STACK_SIZE)
-            .build()
-            .unwrap();
-        let project_id = loaded.project_id;
-        pool.install(|| {
-            file_ids
-                .chunks(chunk_size)
-                .par_bridge()
-                .map_with(analysis, move |analysis, file_ids| {
-                    analysis
-                        .eqwalizer_diagnostics(project_id, file_ids.to_vec())
-                        .expect("cancelled")
-                })
-                .fold(EqwalizerDiagnostics::default, |acc, output| {
-                    acc.combine(&*output)
-                })
-                .reduce(EqwalizerDiagnostics::default, |acc, other| {
-                    acc.combine(&other)
-                })
-        })
-    });
+    let output = eqwalize_in_parallel(loaded, file_ids, &pb);
     let eqwalized = pb.position();
     pb.finish();
-    match output {
+    let result = match output {
         EqwalizerDiagnostics::Diagnostics(diagnostics_by_module) => {
             for (module, diagnostics) in diagnostics_by_module
                 .into_iter()
@@ -351,7 +682,7 @@ fn eqwalize(
                     .with_context(|| format!("module {} not found", module))?;
                 reporter.write_eqwalizer_diagnostics(file_id, &diagnostics)?;
             }
-            if analysis.eqwalizer().shell {
+            if analysis.project_eqwalizer(loaded.project_id).shell {
                 reporter.write_stats(eqwalized, files_count as u64)?;
             }
             reporter.write_error_count()?;
@@ -368,18 +699,62 @@ fn eqwalize(
                 // The cached parse errors must be non-empty otherwise we wouldn't have `NoAst`
                 assert!(!parse_diagnostics.is_empty());
                 reporter.write_parse_diagnostics(&parse_diagnostics)?;
+                if let Some(skipped) = &mut skipped {
+                    skipped.push((module, SkipReason::ParseError));
+                }
                 Ok(())
             } else {
-                bail!(
+                let err = anyhow!(
                     "Could not type-check because module {} was not found",
                     module
-                )
+                );
+                if let Some(skipped) = &mut skipped {
+                    skipped.push((module, SkipReason::NotFound));
+                }
+                Err(err)
             }
         }
-        EqwalizerDiagnostics::Error(error) => {
-            bail!("Could not eqwalize: {}", error)
-        }
+        EqwalizerDiagnostics::Error(error) => Err(anyhow!("Could not eqwalize: {}", error)),
+    };
+    if let Some(mut skipped) = skipped {
+        skipped.sort();
+        skipped.dedup();
+        reporter.write_skipped_modules(&skipped)?;
     }
+    result
+}
+
+fn eqwalize_in_parallel(
+    loaded: &LoadResult,
+    file_ids: Vec<FileId>,
+    pb: &ProgressBar,
+) -> EqwalizerDiagnostics {
+    let files_count = file_ids.len();
+    loaded.with_eqwalizer_progress_bar(pb.clone(), move |analysis| {
+        let chunk_size = (files_count + MAX_EQWALIZER_TASKS - 1) / MAX_EQWALIZER_TASKS;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .stack_size(THREAD_STACK_SIZE)
+            .build()
+            .unwrap();
+        let project_id = loaded.project_id;
+        pool.install(|| {
+            file_ids
+                .chunks(chunk_size)
+                .par_bridge()
+                .map_with(analysis, move |analysis, file_ids| {
+                    analysis
+                        .eqwalizer_diagnostics(project_id, file_ids.to_vec())
+                        .expect("cancelled")
+                })
+                .fold(EqwalizerDiagnostics::default, |acc, output| {
+                    acc.combine(&*output)
+                })
+                .reduce(EqwalizerDiagnostics::default, |acc, other| {
+                    acc.combine(&other)
+                })
+                .dedup()
+        })
+    })
 }
 
 fn pre_parse_for_speed(reporter: &dyn Reporter, analysis: Analysis, file_ids: &[FileId]) {
@@ -455,3 +830,84 @@ fn ensure_empty_directory_exists(path: impl AsRef<Path>) -> io::Result<()> {
     fs::remove_dir_all(path)?;
     fs::create_dir(path)
 }
+
+#[cfg(test)]
+mod tests {
+    use elp::cli::Fake;
+    use expect_test::expect;
+
+    use super::*;
+
+    fn codes(lines: &[(u32, &[&str])]) -> BTreeMap<u32, BTreeSet<String>> {
+        lines
+            .iter()
+            .map(|(line, codes)| (*line, codes.iter().map(|code| code.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn insert_ignore_pragmas_before_lines() {
+        let text = r#"-module(a).
+foo() ->
+    bar(1),
+    %% eqwalizer:ignore incompatible_types
+    baz(2),
+    qux(3).
+"#;
+        let line_index = LineIndex::new(text);
+        let codes_by_line = codes(&[
+            (1, &["redundant_fixme"]),
+            (2, &["incompatible_types", "expected_fun_type"]),
+            (4, &["incompatible_types"]),
+            (5, &["incompatible_types"]),
+        ]);
+        let (new_text, count) = insert_ignore_pragmas(text, &line_index, &codes_by_line);
+        assert_eq!(count, 3);
+        expect![[r#"
+            -module(a).
+            % eqwalizer:ignore redundant_fixme
+            foo() ->
+                % eqwalizer:ignore expected_fun_type, incompatible_types
+                bar(1),
+                %% eqwalizer:ignore incompatible_types
+                baz(2),
+                % eqwalizer:ignore incompatible_types
+                qux(3).
+        "#]]
+        .assert_eq(&new_text);
+    }
+
+    #[test]
+    fn ignore_pragma_with_any_number_of_percents() {
+        assert!(is_ignore_pragma("% eqwalizer:ignore"));
+        assert!(is_ignore_pragma(
+            "    %% eqwalizer:ignore incompatible_types\n"
+        ));
+        assert!(is_ignore_pragma("%%%eqwalizer:ignore"));
+        assert!(!is_ignore_pragma("% eqwalizer:fixme"));
+        assert!(!is_ignore_pragma("eqwalizer:ignore"));
+    }
+
+    #[test]
+    fn modules_including_a_changed_header_are_affected() {
+        let cli = Fake::default();
+        let config = DiscoverConfig::new(true, "test");
+        let project = Path::new("../../test_projects/standard");
+        let loaded = load::load_project_at(&cli, project, config, IncludeOtp::Yes).unwrap();
+        let analysis = loaded.analysis();
+        let root = loaded.project.root();
+        let changed = vec![
+            root.join("app_a/include/app_a.hrl"),
+            root.join("app_b/src/app_b.erl"),
+        ];
+
+        let module_index = analysis.module_index(loaded.project_id).unwrap();
+        let mut affected: Vec<&str> = affected_modules(&analysis, &loaded, &changed)
+            .unwrap()
+            .into_iter()
+            .map(|file_id| module_index.module_for_file(file_id).unwrap().as_str())
+            .collect();
+        affected.sort();
+        assert_eq!(affected, vec!["app_a_SUITE", "app_a_mod2", "app_b"]);
+    }
+}
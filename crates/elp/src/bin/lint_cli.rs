@@ -24,6 +24,7 @@ use elp::convert;
 use elp::document::Document;
 use elp::otp_file_to_ignore;
 use elp_ide::diagnostics;
+use elp_ide::diagnostics::DiagnosticCode;
 use elp_ide::diagnostics::DiagnosticsConfig;
 use elp_ide::diff::diff_from_textedit;
 use elp_ide::diff::DiffRange;
@@ -60,9 +61,55 @@ pub fn lint_all(args: &Lint, cli: &mut dyn Cli) -> Result<()> {
         fs::create_dir_all(to)?
     };
 
+    if args.unused {
+        return do_unused(cli, &loaded, args);
+    }
     do_codemod(cli, &mut loaded, args)
 }
 
+fn do_unused(cli: &mut dyn Cli, loaded: &LoadResult, args: &Lint) -> Result<()> {
+    let analysis = loaded.analysis();
+    let ignored_apps: FxHashSet<Option<AppName>> = args
+        .ignore_apps
+        .iter()
+        .map(|name| Some(AppName(name.to_string())))
+        .collect();
+    let unused: Vec<(FileId, diagnostics::Diagnostic)> = analysis
+        .project_unused_definitions(loaded.project_id)?
+        .into_iter()
+        .filter(|(file_id, _)| {
+            !otp_file_to_ignore(&analysis, *file_id)
+                && !ignored_apps.contains(&analysis.file_app_name(*file_id).ok().flatten())
+        })
+        .collect();
+
+    for (file_id, diag) in &unused {
+        let vfs_path = loaded.vfs.file_path(*file_id);
+        let project_data = analysis
+            .project_data(*file_id)?
+            .ok_or_else(|| anyhow::anyhow!("could not find project data"))?;
+        let relative_path = reporting::get_relative_path(&project_data.root_dir, &vfs_path);
+        if args.is_format_json() {
+            print_diagnostic_json(diag, &analysis, *file_id, relative_path, cli)?;
+        } else {
+            let line_index = analysis.line_index(*file_id)?;
+            writeln!(
+                cli,
+                "{}:{}",
+                relative_path.display(),
+                diag.print(&line_index)
+            )?;
+        }
+    }
+    if args.is_format_normal() {
+        match unused.len() {
+            0 => writeln!(cli, "No unused definitions found")?,
+            n => writeln!(cli, "{} unused definitions found", n)?,
+        }
+    }
+    Ok(())
+}
+
 /// Changed lines, from and to
 type ChangeRange = (u32, u32);
 
@@ -166,9 +213,14 @@ pub fn do_codemod(cli: &mut dyn Cli, loaded: &mut LoadResult, args: &Lint) -> Re
             line_to,
             ignore_apps,
             format: _,
+            unused: _,
         } => {
             let mut cfg = DiagnosticsConfig::default();
             cfg.disable_experimental = args.experimental_diags;
+            // Asking for a specific diagnostic also turns it on if it is opt-in
+            if let Some(code) = DiagnosticCode::maybe_from_string(diagnostic_filter) {
+                cfg = cfg.enable(code);
+            }
             // Declare outside the block so it has the right lifetime for filter_diagnostics
             let res;
             let mut diags = {
@@ -71,6 +71,9 @@ fn try_main(cli: &mut dyn Cli, args: Args) -> Result<()> {
         args::Command::EqwalizeApp(args) => eqwalizer_cli::eqwalize_app(&args, cli)?,
         args::Command::EqwalizeStats(args) => eqwalizer_cli::eqwalize_stats(&args, cli)?,
         args::Command::EqwalizeTarget(args) => eqwalizer_cli::eqwalize_target(&args, cli)?,
+        args::Command::EqwalizeChanged(args) => eqwalizer_cli::eqwalize_changed(&args, cli)?,
+        args::Command::EqwalizeBaseline(args) => eqwalizer_cli::eqwalize_baseline(&args, cli)?,
+        args::Command::AstDiff(args) => eqwalizer_cli::ast_diff(&args, cli)?,
         args::Command::EqwalizePassthrough(args) => {
             eqwalizer_cli::eqwalize_passthrough(&args, cli)?
         }
@@ -139,7 +142,12 @@ mod tests {
     use std::str;
 
     use bpaf::Args;
+    use elp::build;
+    use elp::build::load;
     use elp::cli::Fake;
+    use elp_ide::elp_ide_db::elp_base_db::FileId;
+    use elp_ide::elp_ide_db::elp_base_db::IncludeOtp;
+    use elp_project_model::DiscoverConfig;
     use expect_test::expect_file;
     use expect_test::ExpectFile;
     use tempfile::Builder;
@@ -290,6 +298,146 @@ mod tests {
         );
     }
 
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn eqwalize_all_diagnostics_match_snapshot_grouped(buck: bool) {
+        simple_snapshot(
+            args_vec!["eqwalize-all", "--group-by", "code"],
+            "standard",
+            expect_file!("../resources/test/standard/eqwalize_all_diagnostics_grouped.pretty"),
+            buck,
+            None,
+        );
+    }
+
+    #[test]
+    fn eqwalize_all_rejects_format_with_group_by() {
+        let err = args::args()
+            .run_inner(Args::from(&[
+                "eqwalize-all",
+                "--format",
+                "json",
+                "--group-by",
+                "code",
+            ]))
+            .unwrap_err();
+        let stderr = err.unwrap_stderr();
+        assert!(
+            stderr.contains("--format and --group-by cannot be used together"),
+            "unexpected error: {}",
+            stderr
+        );
+    }
+
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn eqwalize_all_show_skipped_match_snapshot_pretty(buck: bool) {
+        simple_snapshot(
+            args_vec!["eqwalize-all", "--show-skipped"],
+            "standard",
+            expect_file!("../resources/test/standard/eqwalize_all_diagnostics_skipped.pretty"),
+            buck,
+            None,
+        );
+    }
+
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn eqwalize_changed_without_changes(buck: bool) {
+        if !buck || cfg!(feature = "buck") {
+            let (mut args, _path) = add_project(
+                args_vec!["eqwalize-changed", "HEAD..HEAD"],
+                "standard",
+                None,
+            );
+            if !buck {
+                args.push("--rebar".into());
+            }
+            let (stdout, stderr, code) = elp(args);
+            assert_eq!(code, 0, "stdout:\n{}\nstderr:\n{}", stdout, stderr);
+            assert_eq!(
+                stdout,
+                "No opted-in modules affected by changes in HEAD..HEAD\n"
+            );
+        }
+    }
+
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn ast_diff_of_module_without_differences(buck: bool) {
+        if !buck || cfg!(feature = "buck") {
+            let (mut args, _path) = add_project(
+                args_vec!["ast-diff", "--module", "app_a_no_errors"],
+                "standard",
+                None,
+            );
+            if !buck {
+                args.push("--rebar".into());
+            }
+            let (stdout, stderr, code) = elp(args);
+            assert_eq!(code, 0, "stdout:\n{}\nstderr:\n{}", stdout, stderr);
+            assert_eq!(stdout, r#"{"forms":[],"stub":[]}"#);
+        }
+    }
+
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn ast_diff_of_unknown_module(buck: bool) {
+        if !buck || cfg!(feature = "buck") {
+            let (mut args, _path) = add_project(
+                args_vec!["ast-diff", "--module", "no_such_module"],
+                "standard",
+                None,
+            );
+            if !buck {
+                args.push("--rebar".into());
+            }
+            let (_stdout, stderr, code) = elp(args);
+            assert_eq!(code, 101);
+            assert!(
+                stderr.contains("Module no_such_module not found"),
+                "unexpected stderr:\n{}",
+                stderr
+            );
+        }
+    }
+
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn eqwalize_all_deterministic_matches_default(buck: bool) {
+        if !buck || cfg!(feature = "buck") {
+            let diagnostics = |deterministic: bool| {
+                let cli = Fake::default();
+                let config = DiscoverConfig::new(!buck, "test");
+                let project = Path::new("../../test_projects/standard");
+                let mut loaded =
+                    load::load_project_at(&cli, project, config, IncludeOtp::Yes).unwrap();
+                build::compile_deps(&loaded, &cli).unwrap();
+                let db = loaded.analysis_host.raw_database_mut();
+                db.in_shell();
+                db.set_eqwalizer_deterministic(deterministic);
+                let analysis = loaded.analysis();
+                let module_index = analysis.module_index(loaded.project_id).unwrap();
+                let mut modules: Vec<(&str, FileId)> = module_index
+                    .iter_own()
+                    .filter(|(_name, _source, file_id)| {
+                        analysis.is_eqwalizer_enabled(*file_id, false).unwrap()
+                    })
+                    .map(|(name, _source, file_id)| (name.as_str(), file_id))
+                    .collect();
+                modules.sort();
+                let file_ids = modules
+                    .into_iter()
+                    .map(|(_name, file_id)| file_id)
+                    .collect();
+                analysis
+                    .eqwalizer_diagnostics(loaded.project_id, file_ids)
+                    .unwrap()
+            };
+            assert_eq!(diagnostics(false), diagnostics(true));
+        }
+    }
+
     #[test_case(false ; "rebar")]
     #[test_case(true  ; "buck")]
     fn eqwalize_all_diagnostics_match_snapshot_jsonl_gen(buck: bool) {
@@ -543,6 +691,19 @@ mod tests {
         .expect("bad test");
     }
 
+    #[test_case(false ; "rebar")]
+    #[test_case(true  ; "buck")]
+    fn lint_unused(buck: bool) {
+        simple_snapshot(
+            // app_a does not parse
+            args_vec!["lint", "--unused", "app_a"],
+            "linter",
+            expect_file!("../resources/test/linter/parse_elp_lint_unused.stdout"),
+            buck,
+            None,
+        );
+    }
+
     #[test_case(false ; "rebar")]
     #[test_case(true  ; "buck")]
     fn lint_json_output(buck: bool) {
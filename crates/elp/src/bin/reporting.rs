@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
@@ -29,6 +30,7 @@ use elp_ide::elp_ide_db::elp_base_db::AbsPath;
 use elp_ide::elp_ide_db::elp_base_db::FileId;
 use elp_ide::elp_ide_db::elp_base_db::VfsPath;
 use elp_ide::elp_ide_db::EqwalizerDiagnostic;
+use elp_ide::elp_ide_db::EqwalizerSeverity;
 use elp_ide::Analysis;
 use elp_ide::TextRange;
 use indicatif::ProgressBar;
@@ -44,10 +46,29 @@ pub trait Reporter {
     fn write_file_advice(&mut self, file_id: FileId, description: String) -> Result<()>;
     fn write_error_count(&mut self) -> Result<()>;
     fn write_stats(&mut self, count: u64, total: u64) -> Result<()>;
+    fn write_skipped_modules(&mut self, skipped: &[(String, SkipReason)]) -> Result<()>;
 
     fn progress(&self, len: u64, prefix: &'static str) -> ProgressBar;
 }
 
+/// Why a module was not eqWAlized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SkipReason {
+    ParseError,
+    NotFound,
+    Generated,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::ParseError => "parse error",
+            SkipReason::NotFound => "not found",
+            SkipReason::Generated => "generated, use --include-generated",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ParseDiagnostic {
     pub file_id: FileId,
@@ -71,6 +92,16 @@ pub struct JsonReporter<'a> {
     cli: &'a mut dyn Cli,
 }
 
+/// Collects the eqWAlizer diagnostics, and writes them grouped by code,
+/// most frequent first, once all modules are checked.
+pub struct GroupedReporter<'a> {
+    analysis: &'a Analysis,
+    loaded: &'a LoadResult,
+    cli: &'a mut dyn Cli,
+    /// For each code, the `path:line:col: message` of its diagnostics.
+    by_code: BTreeMap<String, Vec<String>>,
+}
+
 impl<'a> PrettyReporter<'a> {
     pub fn new(analysis: &'a Analysis, loaded: &'a LoadResult, cli: &'a mut dyn Cli) -> Self {
         Self {
@@ -120,9 +151,13 @@ impl<'a> Reporter for PrettyReporter<'a> {
                     Label::secondary(reporting_id, range).with_message(format!("\n\n{}", s));
                 labels.push(explanation_label);
             };
-            let d: ReportingDiagnostic<usize> = ReportingDiagnostic::error()
-                .with_message(&diagnostic.code)
-                .with_labels(labels);
+            let d: ReportingDiagnostic<usize> = match diagnostic.severity {
+                EqwalizerSeverity::Error => ReportingDiagnostic::error(),
+                EqwalizerSeverity::Warning => ReportingDiagnostic::warning(),
+                EqwalizerSeverity::Info => ReportingDiagnostic::note(),
+            }
+            .with_message(&diagnostic.code)
+            .with_labels(labels);
 
             term::emit(&mut self.cli, &REPORTING_CONFIG, &reporting_files, &d).unwrap();
         }
@@ -193,6 +228,10 @@ impl<'a> Reporter for PrettyReporter<'a> {
         Ok(())
     }
 
+    fn write_skipped_modules(&mut self, skipped: &[(String, SkipReason)]) -> Result<()> {
+        write_skipped_modules(self.cli, skipped)
+    }
+
     fn progress(&self, len: u64, prefix: &'static str) -> ProgressBar {
         self.cli.progress(len, prefix)
     }
@@ -284,11 +323,139 @@ impl<'a> Reporter for JsonReporter<'a> {
         Ok(())
     }
 
+    fn write_skipped_modules(&mut self, skipped: &[(String, SkipReason)]) -> Result<()> {
+        for (module, reason) in skipped {
+            let skipped = serde_json::json!({"module": module, "skipped": reason.as_str()});
+            writeln!(self.cli, "{}", skipped)?;
+        }
+        Ok(())
+    }
+
     fn progress(&self, len: u64, prefix: &'static str) -> ProgressBar {
         self.cli.progress(len, prefix)
     }
 }
 
+impl<'a> GroupedReporter<'a> {
+    pub fn new(analysis: &'a Analysis, loaded: &'a LoadResult, cli: &'a mut dyn Cli) -> Self {
+        Self {
+            analysis,
+            loaded,
+            cli,
+            by_code: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'a> Reporter for GroupedReporter<'a> {
+    fn write_eqwalizer_diagnostics(
+        &mut self,
+        file_id: FileId,
+        diagnostics: &[EqwalizerDiagnostic],
+    ) -> Result<()> {
+        let line_index = self.analysis.line_index(file_id)?;
+        let file_path = &self.loaded.vfs.file_path(file_id);
+        let root_path = &self
+            .analysis
+            .project_data(file_id)?
+            .with_context(|| "could not find project data")?
+            .root_dir;
+        let relative_path = get_relative_path(root_path, file_path);
+        for diagnostic in diagnostics {
+            let line_col = line_index.line_col(diagnostic.range.start());
+            let location = format!(
+                "{}:{}:{}: {}",
+                relative_path.display(),
+                line_col.line + 1,
+                line_col.col_utf16 + 1,
+                diagnostic.message
+            );
+            self.by_code
+                .entry(diagnostic.code.clone())
+                .or_default()
+                .push(location);
+        }
+        Ok(())
+    }
+
+    fn write_parse_diagnostics(&mut self, diagnostics: &[ParseDiagnostic]) -> Result<()> {
+        writeln!(self.cli, "{}", format_raw_parse_error(diagnostics))?;
+        Ok(())
+    }
+
+    fn write_file_advice(&mut self, file_id: FileId, description: String) -> Result<()> {
+        let file_path = &self.loaded.vfs.file_path(file_id);
+        let root_path = &self
+            .analysis
+            .project_data(file_id)?
+            .with_context(|| "could not find project data")?
+            .root_dir;
+        let relative_path = get_relative_path(root_path, file_path);
+        writeln!(
+            self.cli,
+            "{}: advice: {}",
+            relative_path.display(),
+            description
+        )?;
+        Ok(())
+    }
+
+    fn write_error_count(&mut self) -> Result<()> {
+        let mut groups: Vec<(&String, &Vec<String>)> = self.by_code.iter().collect();
+        // Stable, so codes with the same count stay in alphabetical order
+        groups.sort_by(|(_, a), (_, b)| b.len().cmp(&a.len()));
+        let mut error_count = 0;
+        for (code, locations) in groups {
+            self.cli.set_color(&CYAN_COLOR_SPEC)?;
+            write!(self.cli, "{} ({})", code, locations.len())?;
+            self.cli.reset()?;
+            writeln!(self.cli)?;
+            for location in locations {
+                writeln!(self.cli, "    {}", location)?;
+            }
+            error_count += locations.len();
+        }
+        if error_count == 0 {
+            self.cli.set_color(&GREEN_COLOR_SPEC)?;
+            write!(self.cli, "NO ERRORS")?;
+        } else {
+            self.cli.set_color(&CYAN_COLOR_SPEC)?;
+            let noun = if error_count == 1 { "ERROR" } else { "ERRORS" };
+            write!(self.cli, "{} {}", error_count, noun)?;
+        }
+        self.cli.reset()?;
+        writeln!(self.cli)?;
+        Ok(())
+    }
+
+    fn write_stats(&mut self, _count: u64, _total: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_skipped_modules(&mut self, skipped: &[(String, SkipReason)]) -> Result<()> {
+        write_skipped_modules(self.cli, skipped)
+    }
+
+    fn progress(&self, len: u64, prefix: &'static str) -> ProgressBar {
+        self.cli.progress(len, prefix)
+    }
+}
+
+/// A section listing the modules not eqWAlized, after the diagnostics.
+fn write_skipped_modules(cli: &mut dyn Cli, skipped: &[(String, SkipReason)]) -> Result<()> {
+    if skipped.is_empty() {
+        return Ok(());
+    }
+    cli.set_color(&YELLOW_COLOR_SPEC)?;
+    write!(cli, "{} module(s) not eqWAlized", skipped.len())?;
+    cli.reset()?;
+    writeln!(cli)?;
+    for (module, reason) in skipped {
+        writeln!(cli, "    {}: {}", module, reason.as_str())?;
+    }
+    Ok(())
+}
+
 pub fn format_raw_parse_error(errs: &[ParseDiagnostic]) -> String {
     errs.iter()
         .map(|err| {
@@ -190,7 +190,9 @@ impl ShellCommand {
                         profile,
                         rebar,
                         format: None,
+                        group_by: None,
                         include_generated,
+                        show_skipped: false,
                     })));
                 }
                 "exit" | "quit" => return Ok(Some(ShellCommand::Quit)),
@@ -141,6 +141,7 @@ fn load_database(
 
     let project_id = ProjectId(0);
     db.ensure_erlang_service(project_id)?;
+    db.ensure_eqwalizer(project_id);
     let changes = vfs.take_changes();
     for file in changes {
         if file.exists() {
@@ -37,6 +37,8 @@ config_data! {
       diagnostics_enableExperimental: bool = json! { false },
       /// List of ELP diagnostics to disable.
       diagnostics_disabled: FxHashSet<String> = json! { [] },
+      /// List of opt-in ELP diagnostics to enable.
+      diagnostics_enabled: FxHashSet<String> = json! { [] },
       /// Whether to show function parameter name inlay hints at the call
       /// site.
       inlayHints_parameterHints_enable: bool = json! { false },
@@ -175,8 +177,8 @@ impl Config {
     }
 
     pub fn diagnostics(&self) -> DiagnosticsConfig {
-        // Look up disabled diagnostics using both label and code.
-        DiagnosticsConfig::new(
+        // Look up disabled and enabled diagnostics using both label and code.
+        let mut config = DiagnosticsConfig::new(
             !self.data.diagnostics_enableExperimental,
             self.data
                 .diagnostics_disabled
@@ -184,7 +186,16 @@ impl Config {
                 .filter_map(DiagnosticCode::maybe_from_string)
                 .collect(),
             vec![],
-        )
+        );
+        for code in self
+            .data
+            .diagnostics_enabled
+            .iter()
+            .filter_map(DiagnosticCode::maybe_from_string)
+        {
+            config = config.enable(code);
+        }
+        config
     }
 
     pub fn code_action_group(&self) -> bool {
@@ -420,7 +431,7 @@ mod tests {
 
         let s = remove_ws(&schema);
 
-        expect![[r#""elp.ai.enable":{"default":false,"markdownDescription":"EnablesupportforAI-basedcompletions.","type":"boolean"},"elp.diagnostics.disabled":{"default":[],"items":{"type":"string"},"markdownDescription":"ListofELPdiagnosticstodisable.","type":"array","uniqueItems":true},"elp.diagnostics.enableExperimental":{"default":false,"markdownDescription":"WhethertoshowexperimentalELPdiagnosticsthatmight\nhavemorefalsepositivesthanusual.","type":"boolean"},"elp.inlayHints.parameterHints.enable":{"default":false,"markdownDescription":"Whethertoshowfunctionparameternameinlayhintsatthecall\nsite.","type":"boolean"},"elp.lens.debug.enable":{"default":false,"markdownDescription":"Whethertoshowthe`Debug`lenses.Onlyapplieswhen\n`#elp.lens.enable#`isset.","type":"boolean"},"elp.lens.enable":{"default":false,"markdownDescription":"WhethertoshowCodeLensesinErlangfiles.","type":"boolean"},"elp.lens.run.enable":{"default":false,"markdownDescription":"Whethertoshowthe`Run`lenses.Onlyapplieswhen\n`#elp.lens.enable#`isset.","type":"boolean"},"elp.log":{"default":"error","markdownDescription":"ConfigureLSP-basedloggingusingenv_loggersyntax.","type":"string"},"elp.signatureHelp.enable":{"default":false,"markdownDescription":"WhethertoshowSignatureHelp.","type":"boolean"},"#]]
+        expect![[r#""elp.ai.enable":{"default":false,"markdownDescription":"EnablesupportforAI-basedcompletions.","type":"boolean"},"elp.diagnostics.disabled":{"default":[],"items":{"type":"string"},"markdownDescription":"ListofELPdiagnosticstodisable.","type":"array","uniqueItems":true},"elp.diagnostics.enableExperimental":{"default":false,"markdownDescription":"WhethertoshowexperimentalELPdiagnosticsthatmight\nhavemorefalsepositivesthanusual.","type":"boolean"},"elp.diagnostics.enabled":{"default":[],"items":{"type":"string"},"markdownDescription":"Listofopt-inELPdiagnosticstoenable.","type":"array","uniqueItems":true},"elp.inlayHints.parameterHints.enable":{"default":false,"markdownDescription":"Whethertoshowfunctionparameternameinlayhintsatthecall\nsite.","type":"boolean"},"elp.lens.debug.enable":{"default":false,"markdownDescription":"Whethertoshowthe`Debug`lenses.Onlyapplieswhen\n`#elp.lens.enable#`isset.","type":"boolean"},"elp.lens.enable":{"default":false,"markdownDescription":"WhethertoshowCodeLensesinErlangfiles.","type":"boolean"},"elp.lens.run.enable":{"default":false,"markdownDescription":"Whethertoshowthe`Run`lenses.Onlyapplieswhen\n`#elp.lens.enable#`isset.","type":"boolean"},"elp.log":{"default":"error","markdownDescription":"ConfigureLSP-basedloggingusingenv_loggersyntax.","type":"string"},"elp.signatureHelp.enable":{"default":false,"markdownDescription":"WhethertoshowSignatureHelp.","type":"boolean"},"#]]
         .assert_eq(s.as_str());
 
         expect![[r#"
@@ -443,6 +454,15 @@ mod tests {
               "markdownDescription": "Whether to show experimental ELP diagnostics that might\nhave more false positives than usual.",
               "type": "boolean"
             },
+            "elp.diagnostics.enabled": {
+              "default": [],
+              "items": {
+                "type": "string"
+              },
+              "markdownDescription": "List of opt-in ELP diagnostics to enable.",
+              "type": "array",
+              "uniqueItems": true
+            },
             "elp.inlayHints.parameterHints.enable": {
               "default": false,
               "markdownDescription": "Whether to show function parameter name inlay hints at the call\nsite.",
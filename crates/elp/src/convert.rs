@@ -22,6 +22,7 @@ use elp_ide::elp_ide_db::elp_base_db::AbsPath;
 use elp_ide::elp_ide_db::elp_base_db::AbsPathBuf;
 use elp_ide::elp_ide_db::elp_base_db::VfsPath;
 use elp_ide::elp_ide_db::EqwalizerDiagnostic;
+use elp_ide::elp_ide_db::EqwalizerSeverity;
 use elp_ide::elp_ide_db::LineIndex;
 use elp_ide::TextRange;
 use elp_ide::TextSize;
@@ -76,7 +77,7 @@ pub fn ide_to_lsp_diagnostic(
         message: d.message.clone(),
         related_information: from_related(line_index, url, &d.related_info),
         tags: None,
-        data: None,
+        data: d.metadata.clone(),
     }
 }
 
@@ -102,7 +103,11 @@ pub fn eqwalizer_to_lsp_diagnostic(
 ) -> lsp_types::Diagnostic {
     let range = range(line_index, d.range);
     let severity = if eqwalizer_enabled {
-        lsp_types::DiagnosticSeverity::ERROR
+        match d.severity {
+            EqwalizerSeverity::Error => lsp_types::DiagnosticSeverity::ERROR,
+            EqwalizerSeverity::Warning => lsp_types::DiagnosticSeverity::WARNING,
+            EqwalizerSeverity::Info => lsp_types::DiagnosticSeverity::INFORMATION,
+        }
     } else {
         lsp_types::DiagnosticSeverity::INFORMATION
     };
@@ -140,7 +145,11 @@ pub fn eqwalizer_to_arc_diagnostic(
     let line_num = pos.line + 1;
     let character = Some(pos.character + 1);
     let severity = if eqwalizer_enabled {
-        arc_types::Severity::Error
+        match d.severity {
+            EqwalizerSeverity::Error => arc_types::Severity::Error,
+            EqwalizerSeverity::Warning => arc_types::Severity::Warning,
+            EqwalizerSeverity::Info => arc_types::Severity::Advice,
+        }
     } else {
         // We use Severity::Disabled so that we have the ability in our arc linter to choose
         // to display lints for *new* files with errors that are not opted in (T118466310).
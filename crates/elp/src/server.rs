@@ -872,6 +872,7 @@ impl Server {
         for (project_id, _) in projects.iter().enumerate() {
             let project_id = ProjectId(project_id as u32);
             raw_db.ensure_erlang_service(project_id)?;
+            raw_db.ensure_eqwalizer(project_id);
         }
         if let Some(otp_project_id) = project_apps.otp_project_id {
             raw_db.ensure_erlang_service(otp_project_id)?;
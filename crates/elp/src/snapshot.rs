@@ -21,6 +21,7 @@ use elp_ide::elp_ide_db::elp_base_db::ProjectId;
 use elp_ide::elp_ide_db::elp_base_db::Vfs;
 use elp_ide::elp_ide_db::elp_base_db::VfsPath;
 use elp_ide::elp_ide_db::EqwalizerDiagnostics;
+use elp_ide::elp_ide_db::EqwalizerError;
 use elp_ide::Analysis;
 use elp_log::timeit_with_telemetry;
 use elp_project_model::Project;
@@ -192,6 +193,7 @@ impl Snapshot {
                     .collect(),
             ),
             EqwalizerDiagnostics::NoAst { .. } => Some(vec![]),
+            EqwalizerDiagnostics::Error(EqwalizerError::Cancelled) => Some(vec![]),
             EqwalizerDiagnostics::Error(err) => {
                 log::error!("EqWAlizer failed for {:?}: {}", file_id, err);
                 return Some(vec![]);
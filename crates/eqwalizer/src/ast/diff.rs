@@ -0,0 +1,299 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Compare the raw forms of a module, as produced by the Erlang service,
+//! with the forms ELP converts them to, to find forms lost or added by
+//! the conversion.
+//!
+//! Both sides are normalised to a key per form, e.g. `function foo/1`,
+//! `spec foo/1` or `export`. Forms the conversion drops on purpose,
+//! such as unknown attributes, are not keyed.
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use eetf::Term;
+use elp_base_db::ProjectId;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::Error;
+use crate::get_ast_bytes;
+use crate::ipc::EqWAlizerASTFormat;
+use crate::EqwalizerDiagnosticsDatabase;
+
+/// A form key appearing a different number of times on each side.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FormDiff {
+    pub key: String,
+    pub raw: usize,
+    pub converted: usize,
+}
+
+/// The differences for the forms and for the stub of a module.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ModuleDiff {
+    pub forms: Vec<FormDiff>,
+    pub stub: Vec<FormDiff>,
+}
+
+impl ModuleDiff {
+    pub fn len(&self) -> usize {
+        self.forms.len() + self.stub.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Compare the raw and converted forms and stub of a module, fetched
+/// with the same dispatch eqWAlizer requests go through.
+pub fn diff_module(
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    module: &str,
+) -> Result<ModuleDiff, Error> {
+    let keys = |raw, converted| -> Result<Vec<FormDiff>, Error> {
+        let raw = raw_form_keys(&get_ast_bytes(db, project_id, module, raw)?)?;
+        let converted = converted_form_keys(&get_ast_bytes(db, project_id, module, converted)?)?;
+        Ok(diff_form_keys(&raw, &converted))
+    };
+    Ok(ModuleDiff {
+        forms: keys(
+            EqWAlizerASTFormat::RawForms,
+            EqWAlizerASTFormat::ConvertedForms,
+        )?,
+        stub: keys(
+            EqWAlizerASTFormat::RawStub,
+            EqWAlizerASTFormat::ConvertedStub,
+        )?,
+    })
+}
+
+/// Keys of the forms in raw forms bytes, `{ok, Forms, Warnings}` as ETF.
+pub fn raw_form_keys(bytes: &[u8]) -> Result<Vec<String>, Error> {
+    let term = Term::decode(Cursor::new(bytes))?;
+    let forms = match &term {
+        Term::Tuple(res) => match &res.elements[..] {
+            [Term::Atom(ok), Term::List(forms), _] if ok.name == "ok" => forms,
+            _ => return Err(Error::DecodeError("expected {ok, Forms, _}".to_string())),
+        },
+        _ => return Err(Error::DecodeError("expected {ok, Forms, _}".to_string())),
+    };
+    Ok(forms.elements.iter().filter_map(raw_form_key).collect())
+}
+
+fn raw_form_key(form: &Term) -> Option<String> {
+    let elements = match form {
+        Term::Tuple(tuple) => &tuple.elements[..],
+        _ => return None,
+    };
+    match elements {
+        [Term::Atom(fun), _, Term::Atom(name), Term::FixInteger(arity), _]
+            if fun.name == "function" =>
+        {
+            Some(format!("function {}/{}", name.name, arity.value))
+        }
+        [Term::Atom(attr), _, Term::Atom(kind), value] if attr.name == "attribute" => {
+            match (kind.name.as_str(), value) {
+                ("type" | "opaque", Term::Tuple(decl)) => match &decl.elements[..] {
+                    [Term::Atom(name), _, Term::List(params)] => Some(format!(
+                        "{} {}/{}",
+                        kind.name,
+                        name.name,
+                        params.elements.len()
+                    )),
+                    _ => None,
+                },
+                ("spec" | "callback", Term::Tuple(spec)) => match &spec.elements[..] {
+                    [Term::Tuple(id), _] => match &id.elements[..] {
+                        [Term::Atom(name), Term::FixInteger(arity)] => {
+                            Some(format!("{} {}/{}", kind.name, name.name, arity.value))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                },
+                ("record", Term::Tuple(rec)) => match &rec.elements[..] {
+                    [Term::Atom(name), _] => Some(format!("record {}", name.name)),
+                    _ => None,
+                },
+                ("behaviour" | "behavior", Term::Atom(name)) => {
+                    Some(format!("behaviour {}", name.name))
+                }
+                ("export" | "import" | "export_type", _) => Some(kind.name.clone()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Keys of the forms in converted forms bytes, as JSON.
+pub fn converted_form_keys(bytes: &[u8]) -> Result<Vec<String>, Error> {
+    let forms: Vec<Value> =
+        serde_json::from_slice(bytes).map_err(|err| Error::DecodeError(err.to_string()))?;
+    Ok(forms.iter().filter_map(converted_form_key).collect())
+}
+
+fn converted_form_key(form: &Value) -> Option<String> {
+    let (variant, content) = form.as_object()?.iter().next()?;
+    let field = |name: &str| content.get(name).and_then(Value::as_str);
+    match variant.as_str() {
+        "FunDecl" => Some(format!("function {}", field("id")?)),
+        "ExternalTypeDecl" => Some(format!("type {}", field("id")?)),
+        "ExternalOpaqueDecl" => Some(format!("opaque {}", field("id")?)),
+        "ExternalFunSpec" => Some(format!("spec {}", field("id")?)),
+        "ExternalCallback" => Some(format!("callback {}", field("id")?)),
+        "ExternalRecDecl" => Some(format!("record {}", field("name")?)),
+        "Behaviour" => Some(format!("behaviour {}", field("name")?)),
+        "Export" => Some("export".to_string()),
+        "Import" => Some("import".to_string()),
+        "ExportType" => Some("export_type".to_string()),
+        _ => None,
+    }
+}
+
+/// The keys whose number of occurrences differ, in key order.
+pub fn diff_form_keys(raw: &[String], converted: &[String]) -> Vec<FormDiff> {
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for key in raw {
+        counts.entry(key.as_str()).or_default().0 += 1;
+    }
+    for key in converted {
+        counts.entry(key.as_str()).or_default().1 += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, (raw, converted))| raw != converted)
+        .map(|(key, (raw, converted))| FormDiff {
+            key: key.to_string(),
+            raw,
+            converted,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use eetf::Atom;
+    use eetf::FixInteger;
+    use eetf::List;
+    use eetf::Tuple;
+
+    use super::*;
+
+    fn atom(name: &str) -> Term {
+        Atom::from(name).into()
+    }
+
+    fn int(value: i32) -> Term {
+        FixInteger::from(value).into()
+    }
+
+    fn tuple(elements: Vec<Term>) -> Term {
+        Tuple::from(elements).into()
+    }
+
+    fn list(elements: Vec<Term>) -> Term {
+        List::from(elements).into()
+    }
+
+    fn encode(term: Term) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        term.encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn raw_keys_skip_unkeyed_forms() {
+        let forms = list(vec![
+            tuple(vec![atom("attribute"), int(1), atom("module"), atom("a")]),
+            tuple(vec![
+                atom("attribute"),
+                int(2),
+                atom("export"),
+                list(vec![]),
+            ]),
+            tuple(vec![
+                atom("attribute"),
+                int(3),
+                atom("spec"),
+                tuple(vec![tuple(vec![atom("foo"), int(0)]), list(vec![])]),
+            ]),
+            tuple(vec![
+                atom("function"),
+                int(4),
+                atom("foo"),
+                int(0),
+                list(vec![]),
+            ]),
+            tuple(vec![atom("attribute"), int(5), atom("custom"), atom("x")]),
+        ]);
+        let bytes = encode(tuple(vec![atom("ok"), forms, list(vec![])]));
+
+        assert_eq!(
+            raw_form_keys(&bytes),
+            Ok(keys(&["export", "spec foo/0", "function foo/0"]))
+        );
+    }
+
+    #[test]
+    fn raw_keys_of_a_failed_parse() {
+        let bytes = encode(tuple(vec![atom("error"), list(vec![]), list(vec![])]));
+
+        assert_eq!(
+            raw_form_keys(&bytes),
+            Err(Error::DecodeError("expected {ok, Forms, _}".to_string()))
+        );
+    }
+
+    #[test]
+    fn converted_keys_skip_unkeyed_forms() {
+        let bytes = br#"[
+            {"FunDecl": {"id": "foo/0"}},
+            {"ExternalFunSpec": {"id": "foo/0"}},
+            {"Export": {}},
+            {"Compile": {}}
+        ]"#;
+
+        assert_eq!(
+            converted_form_keys(bytes),
+            Ok(keys(&["function foo/0", "spec foo/0", "export"]))
+        );
+    }
+
+    #[test]
+    fn diff_keys_by_count() {
+        let raw = keys(&["function foo/0", "spec foo/0", "export"]);
+        let converted = keys(&["export", "function foo/0", "export"]);
+
+        assert_eq!(
+            diff_form_keys(&raw, &converted),
+            vec![
+                FormDiff {
+                    key: "export".to_string(),
+                    raw: 1,
+                    converted: 2,
+                },
+                FormDiff {
+                    key: "spec foo/0".to_string(),
+                    raw: 1,
+                    converted: 0,
+                },
+            ]
+        );
+        assert_eq!(diff_form_keys(&raw, &raw), vec![]);
+    }
+}
@@ -30,6 +30,7 @@ pub mod contractivity;
 pub mod convert;
 pub mod convert_types;
 pub mod db;
+pub mod diff;
 pub mod expand;
 pub mod expr;
 pub mod ext_types;
@@ -10,6 +10,7 @@
 use std::io::BufRead;
 use std::io::BufReader;
 use std::io::BufWriter;
+use std::io::ErrorKind;
 use std::io::Write;
 use std::process::ChildStdin;
 use std::process::ChildStdout;
@@ -17,6 +18,7 @@ use std::process::Command;
 use std::process::Stdio;
 use std::time::Duration;
 
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 use fxhash::FxHashMap;
@@ -26,7 +28,10 @@ use stdx::JodChild;
 use timeout_readwrite::TimeoutReader;
 use timeout_readwrite::TimeoutWriter;
 
+use crate::session::Session;
+use crate::session::SessionEntry;
 use crate::EqwalizerDiagnostic;
+use crate::EqwalizerError;
 
 #[derive(Deserialize, Debug)]
 pub enum EqWAlizerASTFormat {
@@ -62,6 +67,39 @@ pub enum MsgFromEqWAlizer {
     Done {
         diagnostics: FxHashMap<String, Vec<EqwalizerDiagnostic>>,
     },
+    /// A message this version of ELP does not understand, e.g. sent by a
+    /// newer eqWAlizer.
+    #[serde(skip)]
+    Unknown {
+        tag: String,
+    },
+}
+
+/// The tags of the variants of `MsgFromEqWAlizer`, but `Unknown`.
+const KNOWN_TAGS: &[&str] = &[
+    "EnteringModule",
+    "GetAstBytes",
+    "EqwalizingStart",
+    "EqwalizingDone",
+    "Dependencies",
+    "Done",
+];
+
+impl MsgFromEqWAlizer {
+    /// Whether eqWAlizer waits for a reply to this message. Nothing is
+    /// known about `Unknown` messages, and answering a notification
+    /// would put a stray reply in front of the next request.
+    pub fn is_request(&self) -> bool {
+        match self {
+            MsgFromEqWAlizer::GetAstBytes { .. } => true,
+            MsgFromEqWAlizer::EnteringModule { .. }
+            | MsgFromEqWAlizer::EqwalizingStart { .. }
+            | MsgFromEqWAlizer::EqwalizingDone { .. }
+            | MsgFromEqWAlizer::Dependencies { .. }
+            | MsgFromEqWAlizer::Done { .. }
+            | MsgFromEqWAlizer::Unknown { .. } => false,
+        }
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -73,17 +111,50 @@ pub enum MsgToEqWAlizer {
     CannotCompleteRequest,
 }
 
+/// Gets a copy of all the traffic over an `IpcHandle`, e.g. to record it.
+pub trait IpcObserver: Send {
+    fn on_receive(&mut self, line: &str);
+    fn on_send(&mut self, line: &str);
+    fn on_send_bytes(&mut self, bytes: &[u8]);
+}
+
 pub struct IpcHandle {
-    writer: BufWriter<TimeoutWriter<ChildStdin>>,
-    reader: BufReader<TimeoutReader<ChildStdout>>,
-    _child_for_drop: JodChild,
+    transport: Transport,
+    observers: Vec<Box<dyn IpcObserver>>,
+    /// Whether to compute the stubs of the `Dependencies` eqWAlizer
+    /// announces ahead of its requests for them.
+    prefetch_dependencies: bool,
+    /// The size of the ASTs sent to eqWAlizer so far.
+    ast_bytes_sent: u64,
+}
+
+enum Transport {
+    Process {
+        writer: BufWriter<TimeoutWriter<ChildStdin>>,
+        reader: BufReader<TimeoutReader<ChildStdout>>,
+        /// How long to wait for each read from eqWAlizer.
+        read_timeout: Duration,
+        /// Killed and reaped when dropped, after the pipes above.
+        child: JodChild,
+    },
+    Replay(Session),
 }
 
 const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
-const READ_TIMEOUT: Duration = Duration::from_secs(240);
+/// How long to wait for each message from eqWAlizer, unless configured
+/// otherwise. Each message restarts the wait, so that long typechecks
+/// which keep sending messages are not stopped.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(240);
+
+// Don't exceed pipe buffer size on Mac or Linux
+// https://unix.stackexchange.com/a/11954/147568
+const CHUNK_SIZE: usize = 65_536;
 
 impl IpcHandle {
-    pub fn from_command(cmd: &mut Command) -> Result<Self> {
+    /// Start eqWAlizer with `cmd`. If it sends nothing for
+    /// `read_timeout`, the process is killed and receiving fails with
+    /// [`EqwalizerError::Timeout`].
+    pub fn from_command(cmd: &mut Command, read_timeout: Duration) -> Result<Self> {
         cmd.stdin(Stdio::piped())
             .stdout(Stdio::piped())
             // for debugging purposes
@@ -99,58 +170,192 @@ impl IpcHandle {
             .take()
             .context("failed to get stdout for eqwalizer process")?;
 
-        let _child_for_drop = JodChild(child);
+        let child = JodChild(child);
         let writer = BufWriter::new(TimeoutWriter::new(stdin, WRITE_TIMEOUT));
-        let reader = BufReader::new(TimeoutReader::new(stdout, READ_TIMEOUT));
+        let reader = BufReader::new(TimeoutReader::new(stdout, read_timeout));
 
         Ok(Self {
-            writer,
-            reader,
-            _child_for_drop,
+            transport: Transport::Process {
+                writer,
+                reader,
+                read_timeout,
+                child,
+            },
+            observers: Vec::new(),
+            prefetch_dependencies: true,
+            ast_bytes_sent: 0,
         })
     }
 
+    /// A handle playing the eqWAlizer side of a recorded session. Sending
+    /// anything other than what was recorded, in the same order, fails.
+    pub fn replay(session: Session) -> Self {
+        Self {
+            transport: Transport::Replay(session),
+            observers: Vec::new(),
+            prefetch_dependencies: true,
+            ast_bytes_sent: 0,
+        }
+    }
+
+    pub fn add_observer(&mut self, observer: Box<dyn IpcObserver>) {
+        self.observers.push(observer);
+    }
+
+    pub fn prefetch_dependencies(&self) -> bool {
+        self.prefetch_dependencies
+    }
+
+    pub fn set_prefetch_dependencies(&mut self, prefetch: bool) {
+        self.prefetch_dependencies = prefetch;
+    }
+
+    /// Count `len` more bytes of AST sent over this handle, returning the
+    /// total so far.
+    pub fn add_ast_bytes_sent(&mut self, len: usize) -> u64 {
+        self.ast_bytes_sent += len as u64;
+        self.ast_bytes_sent
+    }
+
     pub fn receive(&mut self) -> Result<MsgFromEqWAlizer> {
-        let buf = self.receive_line().context("receiving message")?;
-        let deserialized =
-            serde_json::from_str(&buf).expect("failed to parse stdout from eqwalizer");
-        Ok(deserialized)
+        let buf = self.receive_line("message")?;
+        match serde_json::from_str(&buf) {
+            Ok(deserialized) => Ok(deserialized),
+            Err(err) => {
+                let tag = match serde_json::from_str::<serde_json::Value>(&buf)
+                    .ok()
+                    .and_then(|value| Some(value.get("tag")?.as_str()?.to_string()))
+                {
+                    Some(tag) => tag,
+                    None => {
+                        return Err(EqwalizerError::Protocol(format!(
+                            "cannot parse message from eqWAlizer: {}: {:?}",
+                            err, buf
+                        ))
+                        .into());
+                    }
+                };
+                // A message we know that does not decode, e.g. with an
+                // invalid range, would otherwise be answered as unknown.
+                if KNOWN_TAGS.contains(&tag.as_str()) {
+                    return Err(anyhow::Error::new(err)
+                        .context(format!("decoding {} message from eqWAlizer", tag)));
+                }
+                tracing::debug!("cannot parse eqWAlizer message {}: {}", tag, err);
+                Ok(MsgFromEqWAlizer::Unknown { tag })
+            }
+        }
     }
 
     pub fn receive_newline(&mut self) -> Result<()> {
-        let _ = self.receive_line().context("receiving newline")?;
+        let _ = self.receive_line("newline")?;
         Ok(())
     }
 
     pub fn send(&mut self, msg: &MsgToEqWAlizer) -> Result<()> {
         let msg = serde_json::to_string(msg).expect("failed to serialize msg to eqwalizer");
-        writeln!(self.writer, "{}", msg).with_context(|| format!("writing message: {:?}", msg))?;
-        self.writer
-            .flush()
-            .with_context(|| format!("flushing message: {:?}", msg))?;
+        for observer in &mut self.observers {
+            observer.on_send(&msg);
+        }
+        match &mut self.transport {
+            Transport::Process { writer, .. } => {
+                writeln!(writer, "{}", msg)
+                    .with_context(|| format!("writing message: {:?}", msg))?;
+                writer
+                    .flush()
+                    .with_context(|| format!("flushing message: {:?}", msg))?;
+            }
+            Transport::Replay(session) => match session.entries.pop_front() {
+                Some(SessionEntry::Sent(expected)) if expected == msg => {}
+                entry => bail!("replay: sent message {}, recorded {:?}", msg, entry),
+            },
+        }
         Ok(())
     }
 
+    /// Send AST bytes. They are written in chunks straight from `msg`,
+    /// usually the buffer cached by the AST queries, without a copy.
     pub fn send_bytes(&mut self, msg: &[u8]) -> Result<()> {
-        // Don't exceed pipe buffer size on Mac or Linux
-        // https://unix.stackexchange.com/a/11954/147568
-        let chunk_size = 65_536;
-        for (idx, chunk) in msg.chunks(chunk_size).enumerate() {
-            self.writer
+        for observer in &mut self.observers {
+            observer.on_send_bytes(msg);
+        }
+        let writer = match &mut self.transport {
+            Transport::Process { writer, .. } => writer,
+            Transport::Replay(session) => {
+                return match session.entries.pop_front() {
+                    Some(SessionEntry::SentBytes(expected)) if expected == msg => Ok(()),
+                    Some(SessionEntry::SentBytes(expected)) => bail!(
+                        "replay: sent {} bytes differing from the {} recorded",
+                        msg.len(),
+                        expected.len()
+                    ),
+                    entry => bail!("replay: sent {} bytes, recorded {:?}", msg.len(), entry),
+                };
+            }
+        };
+        for (idx, chunk) in msg.chunks(CHUNK_SIZE).enumerate() {
+            writer
                 .write_all(chunk)
                 .with_context(|| format!("writing bytes chunk {} of size {}", idx, chunk.len()))?;
         }
-        self.writer
+        writer
             .flush()
             .with_context(|| format!("flushing bytes of size {}", msg.len()))?;
         Ok(())
     }
 
-    fn receive_line(&mut self) -> Result<String> {
-        let mut buf = String::new();
-        self.reader
-            .read_line(&mut buf)
-            .context("failed read_line from eqwalizer stdout")?;
+    fn receive_line(&mut self, what: &str) -> Result<String> {
+        let buf = match &mut self.transport {
+            Transport::Process {
+                reader,
+                read_timeout,
+                child,
+                ..
+            } => {
+                let mut buf = String::new();
+                match reader.read_line(&mut buf) {
+                    Ok(0) => {
+                        return Err(EqwalizerError::Killed(format!(
+                            "eqWAlizer exited while receiving {}",
+                            what
+                        ))
+                        .into());
+                    }
+                    Ok(_) => buf,
+                    Err(err) if err.kind() == ErrorKind::TimedOut => {
+                        // The handle can be shared with the database, so
+                        // don't wait for it to be dropped to stop the
+                        // process. Waiting reaps it.
+                        let _ = child.0.kill();
+                        let _ = child.0.wait();
+                        return Err(EqwalizerError::Timeout {
+                            after: *read_timeout,
+                        }
+                        .into());
+                    }
+                    Err(err) => {
+                        return Err(anyhow::Error::new(err)
+                            .context("failed read_line from eqwalizer stdout")
+                            .context(format!("receiving {}", what)));
+                    }
+                }
+            }
+            Transport::Replay(session) => match session.entries.pop_front() {
+                Some(SessionEntry::Received(line)) => line,
+                // A recording stops where eqWAlizer crashed
+                None => {
+                    return Err(EqwalizerError::Killed(format!(
+                        "replay: session ended while receiving {}",
+                        what
+                    ))
+                    .into());
+                }
+                entry => bail!("replay: expected to receive, recorded {:?}", entry),
+            },
+        };
+        for observer in &mut self.observers {
+            observer.on_receive(&buf);
+        }
         Ok(buf)
     }
 }
@@ -7,30 +7,38 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::env;
+use std::ffi::OsStr;
 use std::ffi::OsString;
 use std::fmt;
 use std::fs;
+use std::io;
 use std::io::Write;
 use std::marker::PhantomData;
+use std::mem;
 use std::ops::Deref;
 use std::ops::DerefMut;
-use std::os::unix::prelude::PermissionsExt;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
 use std::process::ExitStatus;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
 use anyhow::Context;
 use anyhow::Result;
 use ast::form::ExternalForm;
 use ast::Error;
+use elp_base_db::FileId;
 use elp_base_db::ModuleName;
 use elp_base_db::ProjectId;
 use elp_syntax::TextRange;
 use fxhash::FxHashMap;
+use fxhash::FxHashSet;
 use parking_lot::Mutex;
 use serde::Deserialize;
 use serde::Serialize;
@@ -42,6 +50,10 @@ use ipc::IpcHandle;
 use ipc::MsgFromEqWAlizer;
 use ipc::MsgToEqWAlizer;
 
+pub mod session;
+use session::Recorder;
+use session::Session;
+
 use crate::ipc::EqWAlizerASTFormat;
 
 pub mod ast;
@@ -52,15 +64,125 @@ pub struct Eqwalizer {
     cmd: OsString,
     args: Vec<OsString>,
     pub shell: bool,
+    /// Ask eqWAlizer to include explanations in its diagnostics. These
+    /// cost extra computation, so they are off by default.
+    pub verbose: bool,
+    /// For tests: do not pre-fetch the stubs of the `Dependencies`
+    /// eqWAlizer announces, so that runs are reproducible.
+    pub deterministic: bool,
+    /// How long to wait for each message from eqWAlizer before killing
+    /// it, [`ipc::READ_TIMEOUT`] if not set.
+    pub timeout: Option<Duration>,
+    /// The jar run with `java`, if the executable is one.
+    jar: Option<PathBuf>,
+    /// Why the executable could not be set up, reported by every request.
+    setup_error: Option<Arc<String>>,
     // Used only for the Drop implementation
     _file: Option<Arc<TempPath>>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// Serializes to JSON, with the modules sorted, so that the results of a
+/// run can be snapshotted and compared across eqWAlizer versions.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "tag", content = "content")]
 pub enum EqwalizerDiagnostics {
-    Diagnostics(FxHashMap<String, Vec<EqwalizerDiagnostic>>),
-    NoAst { module: String },
-    Error(String),
+    Diagnostics(
+        #[serde(serialize_with = "serialize_sorted")] FxHashMap<String, Vec<EqwalizerDiagnostic>>,
+    ),
+    NoAst {
+        module: String,
+    },
+    Error(EqwalizerError),
+}
+
+/// Why a typecheck failed, so that callers can e.g. tell a missing
+/// eqWAlizer, worth telling the user about, from a broken session, worth
+/// retrying. Displays as the message of the failure alone.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "kind", content = "message")]
+pub enum EqwalizerError {
+    /// The executable is missing or could not be set up or started.
+    Unavailable(String),
+    /// ELP and eqWAlizer are out of sync, or the pipe between them broke.
+    Protocol(String),
+    /// The process stopped before the end of the session, e.g. killed
+    /// after a timeout.
+    Killed(String),
+    /// ELP could not produce what eqWAlizer asked for, e.g. the AST of a
+    /// module that does not parse.
+    Internal(String),
+    /// The AST sent during one typecheck went over `limit` bytes, when
+    /// sending the AST of `module`.
+    AstLimitExceeded { module: String, limit: u64 },
+    /// eqWAlizer sent nothing for `after`, so its process was killed.
+    Timeout { after: Duration },
+    /// The run was cancelled by the caller.
+    Cancelled,
+}
+
+impl fmt::Display for EqwalizerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EqwalizerError::Unavailable(msg)
+            | EqwalizerError::Protocol(msg)
+            | EqwalizerError::Killed(msg)
+            | EqwalizerError::Internal(msg) => write!(f, "{}", msg),
+            EqwalizerError::AstLimitExceeded { module, limit } => write!(
+                f,
+                "AST sent exceeded the limit of {} bytes, at module {}",
+                limit, module
+            ),
+            EqwalizerError::Timeout { after } => write!(
+                f,
+                "timed out after {}s without a message from eqWAlizer",
+                after.as_secs()
+            ),
+            EqwalizerError::Cancelled => write!(f, "eqWAlizer run cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for EqwalizerError {}
+
+impl From<Error> for EqwalizerError {
+    fn from(err: Error) -> Self {
+        EqwalizerError::Internal(err.to_string())
+    }
+}
+
+impl From<anyhow::Error> for EqwalizerError {
+    /// Classify by the first typed error in the chain, keeping the
+    /// message of the outermost context.
+    fn from(err: anyhow::Error) -> Self {
+        let message = format!("{}", err);
+        for cause in err.chain() {
+            if let Some(err) = cause.downcast_ref::<EqwalizerError>() {
+                return match err {
+                    EqwalizerError::Cancelled => EqwalizerError::Cancelled,
+                    EqwalizerError::Unavailable(_) => EqwalizerError::Unavailable(message),
+                    EqwalizerError::Protocol(_) => EqwalizerError::Protocol(message),
+                    EqwalizerError::Killed(_) => EqwalizerError::Killed(message),
+                    EqwalizerError::Internal(_) => EqwalizerError::Internal(message),
+                    // These carry their details instead of a message.
+                    EqwalizerError::AstLimitExceeded { .. } | EqwalizerError::Timeout { .. } => {
+                        err.clone()
+                    }
+                };
+            }
+            if cause.downcast_ref::<Error>().is_some() {
+                return EqwalizerError::Internal(message);
+            }
+            if let Some(err) = cause.downcast_ref::<io::Error>() {
+                return match err.kind() {
+                    io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => {
+                        EqwalizerError::Unavailable(message)
+                    }
+                    _ => EqwalizerError::Protocol(message),
+                };
+            }
+        }
+        EqwalizerError::Protocol(message)
+    }
 }
 
 impl Default for EqwalizerDiagnostics {
@@ -69,21 +191,81 @@ impl Default for EqwalizerDiagnostics {
     }
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct EqwalizerDiagnostic {
-    #[serde(deserialize_with = "deserialize_text_range")]
+    #[serde(
+        serialize_with = "serialize_text_range",
+        deserialize_with = "deserialize_text_range"
+    )]
     pub range: TextRange,
     pub message: String,
     pub uri: String,
     pub code: String,
-    #[serde(rename(deserialize = "expressionOrNull"))]
+    // Serialized as eqWAlizer sends them, so that snapshots reload.
+    #[serde(rename = "expressionOrNull")]
     pub expression: Option<String>,
-    #[serde(rename(deserialize = "explanationOrNull"))]
+    #[serde(rename = "explanationOrNull")]
     pub explanation: Option<String>,
+    /// Not sent by older eqWAlizer versions, whose diagnostics are all
+    /// errors.
+    #[serde(default)]
+    pub severity: EqwalizerSeverity,
+}
+
+/// The same code can be reported at different severities, depending on
+/// the configuration of eqWAlizer.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum EqwalizerSeverity {
+    #[serde(alias = "Error")]
+    Error,
+    #[serde(alias = "Warning")]
+    Warning,
+    #[serde(alias = "Info")]
+    Info,
+}
+
+impl Default for EqwalizerSeverity {
+    fn default() -> Self {
+        EqwalizerSeverity::Error
+    }
+}
+
+type DedupKey = (
+    String,
+    TextRange,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+);
+
+impl EqwalizerDiagnostic {
+    /// Where the diagnostic is, its code and its text, see
+    /// [`EqwalizerDiagnostics::dedup`].
+    fn dedup_key(&self) -> DedupKey {
+        (
+            self.uri.clone(),
+            self.range,
+            self.code.clone(),
+            self.message.clone(),
+            self.expression.clone(),
+            self.explanation.clone(),
+        )
+    }
+
+    /// A hash of the code, message and expression of the diagnostic, but
+    /// not of its range, so that it stays the same when the code moves
+    /// around, e.g. to reference the diagnostic from a baseline file.
+    pub fn fingerprint(&self) -> u64 {
+        fxhash::hash64(&(&self.code, &self.message, &self.expression))
+    }
 }
 
 impl EqwalizerDiagnostics {
+    /// Add the diagnostics of `other`. Diagnostics reported by several
+    /// modules are kept, [`dedup`](Self::dedup) the result once done.
     pub fn combine(mut self, other: &Self) -> Self {
         match &mut self {
             EqwalizerDiagnostics::NoAst { .. } => self,
@@ -102,13 +284,114 @@ impl EqwalizerDiagnostics {
             },
         }
     }
+
+    /// Drop the diagnostics reported more than once, e.g. by every module
+    /// including the header they are in, keeping them under the first
+    /// module by name. Diagnostics are the same when they are in the same
+    /// file, at the same range, with the same code and text, so two
+    /// differing only in their `expression` or `explanation` are both
+    /// kept.
+    pub fn dedup(mut self) -> Self {
+        if let EqwalizerDiagnostics::Diagnostics(diags) = &mut self {
+            let mut modules: Vec<String> = diags.keys().cloned().collect();
+            modules.sort();
+            let mut seen = FxHashSet::default();
+            for module in modules {
+                if let Some(module_diags) = diags.get_mut(&module) {
+                    module_diags.retain(|diag| seen.insert(diag.dedup_key()));
+                }
+            }
+        }
+        self
+    }
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Default)]
 pub struct EqwalizerStats {
-    ignores: u32,
-    fixmes: u32,
-    nowarn: u32,
+    pub ignores: u32,
+    pub fixmes: u32,
+    pub nowarn: u32,
+    /// Where each of the suppressions counted above is, in the order
+    /// they appear in the AST.
+    pub suppressions: Vec<EqwalizerSuppression>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum FixmeKind {
+    Fixme,
+    Ignore,
+    Nowarn,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct EqwalizerSuppression {
+    pub kind: FixmeKind,
+    /// The module whose AST the suppression is in, so that it can be
+    /// located once the stats of several modules are combined.
+    pub module: String,
+    /// The `eqwalizer:fixme` or `eqwalizer:ignore` comment, or the
+    /// `-eqwalizer({nowarn_function, ...})` attribute. Only ASTs not
+    /// converted by ELP have no ranges.
+    #[serde(serialize_with = "serialize_opt_text_range")]
+    pub range: Option<TextRange>,
+}
+
+impl EqwalizerStats {
+    /// Count the fixmes, ignores and nowarn functions of the converted
+    /// AST of `module`.
+    pub fn from_ast(module: &ModuleName, ast: &ast::AST) -> Self {
+        let mut stats = EqwalizerStats::default();
+        let module = module.as_str();
+        for form in ast {
+            match form {
+                ExternalForm::ElpMetadata(meta) => {
+                    for fixme in &meta.fixmes {
+                        let kind = if fixme.is_ignore {
+                            FixmeKind::Ignore
+                        } else {
+                            FixmeKind::Fixme
+                        };
+                        stats.add(kind, module, Some(text_range(&fixme.comment)));
+                    }
+                }
+                ExternalForm::EqwalizerNowarnFunction(nowarn) => {
+                    let range = match &nowarn.location {
+                        ast::Pos::TextRange(range) => Some(text_range(range)),
+                        ast::Pos::LineAndColumn(_) => None,
+                    };
+                    stats.add(FixmeKind::Nowarn, module, range)
+                }
+                _ => (),
+            }
+        }
+        stats
+    }
+
+    fn add(&mut self, kind: FixmeKind, module: &str, range: Option<TextRange>) {
+        match kind {
+            FixmeKind::Fixme => self.fixmes += 1,
+            FixmeKind::Ignore => self.ignores += 1,
+            FixmeKind::Nowarn => self.nowarn += 1,
+        }
+        self.suppressions.push(EqwalizerSuppression {
+            kind,
+            module: module.to_string(),
+            range,
+        });
+    }
+
+    pub fn combine(mut self, other: &Self) -> Self {
+        self.ignores += other.ignores;
+        self.fixmes += other.fixmes;
+        self.nowarn += other.nowarn;
+        self.suppressions.extend(other.suppressions.iter().cloned());
+        self
+    }
+}
+
+fn text_range(range: &ast::TextRange) -> TextRange {
+    TextRange::new(range.start_byte.into(), range.end_byte.into())
 }
 
 pub trait DbApi {
@@ -126,6 +409,17 @@ pub trait EqwalizerDiagnosticsDatabase: ast::db::EqwalizerASTDatabase + DbApi {
         module: String,
     ) -> (Arc<EqwalizerDiagnostics>, Instant);
 
+    /// The diagnostics of `module_diagnostics` intersecting `range`, e.g.
+    /// the function at the cursor.
+    fn module_diagnostics_in_range(
+        &self,
+        project_id: ProjectId,
+        module: String,
+        range: TextRange,
+    ) -> Arc<EqwalizerDiagnostics>;
+
+    /// Only depends on the converted AST of the module, so that edits to
+    /// other modules don't invalidate it.
     fn compute_eqwalizer_stats(
         &self,
         project_id: ProjectId,
@@ -133,6 +427,44 @@ pub trait EqwalizerDiagnosticsDatabase: ast::db::EqwalizerASTDatabase + DbApi {
     ) -> Option<Arc<EqwalizerStats>>;
 }
 
+fn serialize_sorted<S>(
+    diagnostics: &FxHashMap<String, Vec<EqwalizerDiagnostic>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let sorted: BTreeMap<&String, &Vec<EqwalizerDiagnostic>> = diagnostics.iter().collect();
+    sorted.serialize(serializer)
+}
+
+fn serialize_text_range<S>(range: &TextRange, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    #[derive(Serialize)]
+    struct RawTextRange {
+        start: u32,
+        end: u32,
+    }
+
+    RawTextRange {
+        start: range.start().into(),
+        end: range.end().into(),
+    }
+    .serialize(serializer)
+}
+
+fn serialize_opt_text_range<S>(range: &Option<TextRange>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match range {
+        Some(range) => serialize_text_range(range, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
 fn deserialize_text_range<'de, D>(deserializer: D) -> Result<TextRange, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -143,65 +475,312 @@ where
         end: u32,
     }
 
+    // The length of the file is not known here, so a range past its end
+    // is left to the consumers of the diagnostic.
     let range = RawTextRange::deserialize(deserializer)?;
-    // Temporary for T148094436
-    let _pctx = stdx::panic_context::enter(format!("\neqwalizer::deserialize_text_range"));
+    if range.start > range.end {
+        return Err(serde::de::Error::custom(format!(
+            "invalid range: start {} is after end {}",
+            range.start, range.end
+        )));
+    }
     Ok(TextRange::new(range.start.into(), range.end.into()))
 }
 
 impl Default for Eqwalizer {
     fn default() -> Self {
-        let env = env::var("ELP_EQWALIZER_PATH");
-        let (path, ext, temp_file) = if let Ok(path) = env {
-            let path = PathBuf::from(path);
-            let ext = path
-                .extension()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap()
-                .to_string();
-            (path, ext, None)
-        } else {
-            let extension = env!("ELP_EQWALIZER_EXT").to_string();
-            let eqwalizer_src = include_bytes!(concat!(env!("OUT_DIR"), "/eqwalizer"));
-            let mut temp_file = Builder::new()
-                .prefix("eqwalizer")
-                .tempfile()
-                .expect("can't create eqwalizer temp executable");
-            temp_file
-                .write_all(eqwalizer_src)
-                .expect("can't create eqwalizer temp executable");
-
-            let temp_file = temp_file.into_temp_path();
-
-            let mut perm = fs::metadata(&temp_file)
-                .expect("can't create eqwalizer temp executable")
-                .permissions();
-            perm.set_mode(0o755);
-            fs::set_permissions(&temp_file, perm).expect("can't create eqwalizer temp executable");
-
-            (temp_file.to_path_buf(), extension, Some(temp_file))
+        EqwalizerBuilder::default().build()
+    }
+}
+
+/// All the options of an [`Eqwalizer`] in one place, e.g.
+/// `EqwalizerBuilder::default().path(jar).shell(true).try_build()?`.
+#[derive(Debug, Clone, Default)]
+pub struct EqwalizerBuilder {
+    path: Option<PathBuf>,
+    shell: bool,
+    verbose: bool,
+    deterministic: bool,
+    timeout: Option<Duration>,
+    jvm_args: Option<Vec<OsString>>,
+}
+
+impl EqwalizerBuilder {
+    /// The eqWAlizer executable or jar to run. When not set,
+    /// `ELP_EQWALIZER_PATH` is used, or else the bundled executable.
+    pub fn path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Keep eqWAlizer running between requests, see [`Eqwalizer::shell`].
+    pub fn shell(mut self, shell: bool) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// Ask for explanations in diagnostics, see [`Eqwalizer::verbose`].
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Make runs reproducible, see [`Eqwalizer::deterministic`].
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Give up on an unresponsive eqWAlizer, see [`Eqwalizer::timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run a jar with these JVM arguments, e.g. `-Xmx4G`, instead of those
+    /// in `ELP_EQWALIZER_JVM_ARGS`. Either way they come after the default
+    /// `-Xss20M`, so they can override it. Ignored if the executable is
+    /// not a jar.
+    pub fn jvm_args(mut self, jvm_args: Vec<OsString>) -> Self {
+        self.jvm_args = Some(jvm_args);
+        self
+    }
+
+    pub fn try_build(self) -> Result<Eqwalizer> {
+        let (path, temp_file) = match self.path {
+            Some(path) => {
+                if !path.exists() {
+                    anyhow::bail!("eqwalizer executable {} does not exist", path.display());
+                }
+                (path, None)
+            }
+            None => match env::var("ELP_EQWALIZER_PATH") {
+                Ok(path) => (PathBuf::from(path), None),
+                Err(_) => {
+                    let temp_file = extract_bundled()?;
+                    (temp_file.to_path_buf(), Some(temp_file))
+                }
+            },
         };
+        let mut eqwalizer = Eqwalizer::new(path, temp_file)?;
+        if let Some(jvm_args) = self.jvm_args {
+            eqwalizer = eqwalizer.with_jvm_args(jvm_args);
+        }
+        eqwalizer.shell = self.shell;
+        eqwalizer.verbose = self.verbose;
+        eqwalizer.deterministic = self.deterministic;
+        eqwalizer.timeout = self.timeout;
+        Ok(eqwalizer)
+    }
 
-        let (cmd, args) = match ext.as_str() {
-            "jar" => (
-                "java".into(),
-                vec!["-Xss20M".into(), "-jar".into(), path.into()],
-            ),
-            "" => (path.into(), vec![]),
-            _ => panic!("Unknown eqwalizer executable {:?}", path),
+    /// Like `try_build`, but an `Eqwalizer` that cannot be set up fails
+    /// every request with the reason, rather than at startup.
+    pub fn build(self) -> Eqwalizer {
+        let (shell, verbose, deterministic, timeout) =
+            (self.shell, self.verbose, self.deterministic, self.timeout);
+        self.try_build().unwrap_or_else(|err| {
+            let mut eqwalizer = Eqwalizer::unavailable(err);
+            eqwalizer.shell = shell;
+            eqwalizer.verbose = verbose;
+            eqwalizer.deterministic = deterministic;
+            eqwalizer.timeout = timeout;
+            eqwalizer
+        })
+    }
+}
+
+const TMPDIR_ENV: &str = "ELP_EQWALIZER_TMPDIR";
+
+const MAX_AST_BYTES_ENV: &str = "ELP_EQWALIZER_MAX_AST_BYTES";
+
+/// High enough to only stop runaway typechecks, before they exhaust the
+/// memory of the server.
+const DEFAULT_MAX_AST_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// The cap on the AST bytes sent to eqWAlizer in one typecheck, which can
+/// be changed with `ELP_EQWALIZER_MAX_AST_BYTES`.
+fn max_ast_bytes() -> u64 {
+    env::var(MAX_AST_BYTES_ENV)
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(DEFAULT_MAX_AST_BYTES)
+}
+
+const JVM_ARGS_ENV: &str = "ELP_EQWALIZER_JVM_ARGS";
+
+/// Deeply recursive modules need a larger stack than the JVM default.
+const DEFAULT_JVM_ARGS: &[&str] = &["-Xss20M"];
+
+/// The JVM arguments in `ELP_EQWALIZER_JVM_ARGS`, separated by
+/// whitespace.
+fn env_jvm_args() -> Vec<OsString> {
+    env::var_os(JVM_ARGS_ENV)
+        .map(|args| split_jvm_args(&args))
+        .unwrap_or_default()
+}
+
+/// JVM arguments given as one string, like in `ELP_EQWALIZER_JVM_ARGS`.
+fn split_jvm_args(args: &OsStr) -> Vec<OsString> {
+    args.to_string_lossy()
+        .split_whitespace()
+        .map(OsString::from)
+        .collect()
+}
+
+/// The arguments of `java` to run the jar at `path`: the defaults, then
+/// `jvm_args`, whose flags win over the defaults, then `-jar` and the
+/// path, which `java` expects last. A `-jar` in `jvm_args` would take
+/// their place, so it is dropped along with the path following it.
+fn jar_args(path: &Path, jvm_args: &[OsString]) -> Vec<OsString> {
+    let mut args: Vec<OsString> = DEFAULT_JVM_ARGS.iter().map(OsString::from).collect();
+    let mut jvm_args = jvm_args.iter();
+    while let Some(arg) = jvm_args.next() {
+        if arg == "-jar" {
+            let dropped = jvm_args.next();
+            tracing::warn!(
+                "ignoring -jar {:?} in the JVM arguments of eqWAlizer",
+                dropped
+            );
+            continue;
+        }
+        args.push(arg.clone());
+    }
+    args.push("-jar".into());
+    args.push(path.into());
+    args
+}
+
+/// Write the bundled eqWAlizer executable to a temp file, in
+/// `ELP_EQWALIZER_TMPDIR` if set, e.g. when `/tmp` is mounted noexec.
+fn extract_bundled() -> Result<TempPath> {
+    let eqwalizer_src = include_bytes!(concat!(env!("OUT_DIR"), "/eqwalizer"));
+    let dir = env::var_os(TMPDIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    let context = || {
+        format!(
+            "can't create eqwalizer temp executable in {}: set {} to a writable directory \
+            allowing execution, or ELP_EQWALIZER_PATH to an eqwalizer executable",
+            dir.display(),
+            TMPDIR_ENV
+        )
+    };
+    let mut temp_file = Builder::new()
+        .prefix("eqwalizer")
+        .suffix(bundled_suffix())
+        .tempfile_in(&dir)
+        .with_context(context)?;
+    temp_file.write_all(eqwalizer_src).with_context(context)?;
+
+    let temp_file = temp_file.into_temp_path();
+    make_executable(&temp_file).with_context(context)?;
+
+    Ok(temp_file)
+}
+
+/// The extension of the extracted executable. Windows only runs files
+/// with an executable extension, while a jar is run by `java` anyway.
+fn bundled_suffix() -> &'static str {
+    if cfg!(windows) && env!("ELP_EQWALIZER_EXT").is_empty() {
+        ".exe"
+    } else {
+        ""
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::prelude::PermissionsExt;
+
+    let mut perm = fs::metadata(path)?.permissions();
+    perm.set_mode(0o755);
+    fs::set_permissions(path, perm)
+}
+
+/// The `.exe` extension is enough for the file to be run.
+#[cfg(windows)]
+fn make_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+impl Eqwalizer {
+    /// An `Eqwalizer` running the given executable or jar, e.g. the one
+    /// a project is pinned to, rather than the default.
+    pub fn from_path(path: PathBuf) -> Result<Self> {
+        EqwalizerBuilder::default().path(path).try_build()
+    }
+
+    fn new(path: PathBuf, temp_file: Option<TempPath>) -> Result<Self> {
+        // The bundled executable is a temp file without the extension,
+        // or with `.exe` on Windows
+        let ext = if temp_file.is_some() {
+            env!("ELP_EQWALIZER_EXT").to_string()
+        } else {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let (cmd, args, jar) = match ext.as_str() {
+            "jar" => ("java".into(), jar_args(&path, &env_jvm_args()), Some(path)),
+            "" => (path.into(), vec![], None),
+            "exe" if cfg!(windows) => (path.into(), vec![], None),
+            _ => anyhow::bail!("Unknown eqwalizer executable {:?}", path),
         };
 
-        Self {
+        Ok(Self {
             cmd,
             args,
             shell: false,
+            verbose: false,
+            deterministic: false,
+            timeout: None,
+            jar,
+            setup_error: None,
             _file: temp_file.map(Arc::new),
+        })
+    }
+
+    /// An `Eqwalizer` that could not be set up. Rather than panicking at
+    /// startup, every request fails with the reason.
+    fn unavailable(err: anyhow::Error) -> Self {
+        tracing::error!("{:#}", err);
+        Self {
+            cmd: "eqwalizer".into(),
+            args: vec![],
+            shell: false,
+            verbose: false,
+            deterministic: false,
+            timeout: None,
+            jar: None,
+            setup_error: Some(Arc::new(format!("{:#}", err))),
+            _file: None,
+        }
+    }
+
+    /// See [`EqwalizerBuilder::jvm_args`].
+    fn with_jvm_args(mut self, jvm_args: Vec<OsString>) -> Self {
+        if let Some(jar) = &self.jar {
+            self.args = jar_args(jar, &jvm_args);
+        }
+        self
+    }
+
+    fn read_timeout(&self) -> Duration {
+        self.timeout.unwrap_or(ipc::READ_TIMEOUT)
+    }
+
+    fn check_setup(&self) -> Result<()> {
+        match &self.setup_error {
+            Some(err) => Err(EqwalizerError::Unavailable(format!(
+                "eqwalizer is unavailable: {}",
+                err
+            ))
+            .into()),
+            None => Ok(()),
         }
     }
-}
 
-impl Eqwalizer {
     // Return a smart pointer to bundle lifetime with the temp file's lifetime
     pub fn cmd<'file>(&'file self) -> CommandProxy<'file> {
         let mut cmd = Command::new(&self.cmd);
@@ -209,154 +788,222 @@ impl Eqwalizer {
         CommandProxy::new(cmd)
     }
 
-    pub fn typecheck(
-        &self,
+    fn ipc_cmd<'file>(
+        &'file self,
         build_info_path: &Path,
-        db: &dyn EqwalizerDiagnosticsDatabase,
-        project_id: ProjectId,
-        modules: Vec<&str>,
-    ) -> EqwalizerDiagnostics {
+        modules: &[&str],
+        shell: bool,
+    ) -> CommandProxy<'file> {
         let mut cmd = self.cmd();
         cmd.arg("ipc");
         cmd.args(modules);
         cmd.env("EQWALIZER_IPC", "true");
         cmd.env("EQWALIZER_USE_ELP_CONVERTED_AST", "true");
-        if self.shell {
+        if shell {
             cmd.env("EQWALIZER_ELP_SHELL", "true");
         }
+        if self.verbose {
+            cmd.env("EQWALIZER_VERBOSE", "true");
+        }
         add_env(&mut cmd, build_info_path, None);
+        cmd
+    }
+
+    fn start_shell(
+        &self,
+        build_info_path: &Path,
+        modules: &[&str],
+    ) -> Result<Arc<Mutex<IpcHandle>>, anyhow::Error> {
+        let mut cmd = self.ipc_cmd(build_info_path, modules, true);
+        start_shell(&mut cmd, self.deterministic, self.read_timeout())
+    }
+
+    /// Typecheck the given modules. Besides salsa cancellation, the run
+    /// stops when `cancel` is set, for callers not driving salsa.
+    pub fn typecheck(
+        &self,
+        build_info_path: &Path,
+        db: &dyn EqwalizerDiagnosticsDatabase,
+        project_id: ProjectId,
+        modules: Vec<&str>,
+        cancel: Option<Arc<AtomicBool>>,
+    ) -> EqwalizerDiagnostics {
+        if let Err(err) = self.check_setup() {
+            return EqwalizerDiagnostics::Error(err.into());
+        }
+        let cmd = self.ipc_cmd(build_info_path, &modules, self.shell);
+        let cancel = cancel.as_deref();
+        let timeout = self.read_timeout();
 
         if self.shell {
-            match shell_typecheck(cmd, db, project_id) {
+            match shell_typecheck(cmd, db, project_id, cancel, self.deterministic, timeout) {
                 Ok(diags) => diags,
-                Err(err) => EqwalizerDiagnostics::Error(format!("{}", err)),
+                Err(err) => EqwalizerDiagnostics::Error(err.into()),
             }
         } else {
-            match do_typecheck(cmd, db, project_id) {
+            match do_typecheck(cmd, db, project_id, &modules, cancel, timeout) {
                 Ok(diags) => diags,
-                Err(err) => EqwalizerDiagnostics::Error(format!("{}", err)),
+                Err(err) => EqwalizerDiagnostics::Error(err.into()),
             }
         }
     }
 
+    /// Typecheck the given modules, keeping the result for each module
+    /// separate so that callers can cache them individually.
+    /// Modules are always checked one at a time over the shell protocol,
+    /// so that a `NoAst` or `Error` for one module does not mask the
+    /// results of the others.
+    pub fn typecheck_map(
+        &self,
+        build_info_path: &Path,
+        db: &dyn EqwalizerDiagnosticsDatabase,
+        project_id: ProjectId,
+        modules: Vec<&str>,
+    ) -> FxHashMap<String, EqwalizerDiagnostics> {
+        let handle = self
+            .check_setup()
+            .and_then(|()| self.start_shell(build_info_path, &modules));
+        shell_typecheck_map(handle, db, project_id, &modules)
+    }
+
+    /// Typecheck the given modules over the shell protocol, calling
+    /// `on_module` with the diagnostics of each module as soon as it has
+    /// been checked, e.g. to show progress. A module without an AST or
+    /// failing to check is not passed to `on_module`, but ends up in the
+    /// returned aggregate, which is the same as that of `typecheck`.
+    pub fn typecheck_streaming(
+        &self,
+        build_info_path: &Path,
+        db: &dyn EqwalizerDiagnosticsDatabase,
+        project_id: ProjectId,
+        modules: Vec<&str>,
+        cancel: Option<Arc<AtomicBool>>,
+        mut on_module: impl FnMut(String, Vec<EqwalizerDiagnostic>),
+    ) -> EqwalizerDiagnostics {
+        let handle = self
+            .check_setup()
+            .and_then(|()| self.start_shell(build_info_path, &modules));
+        shell_typecheck_streaming(handle, db, project_id, cancel.as_deref(), &mut on_module)
+    }
+
     pub fn passthrough(
         &self,
         args: &[String],
         build_info_path: &Path,
         elp_ast_dir: &Path,
     ) -> Result<ExitStatus> {
+        self.check_setup()?;
         let mut cmd = self.cmd();
         cmd.args(args);
         add_env(&mut cmd, build_info_path, Some(elp_ast_dir));
         cmd.status()
             .with_context(|| "Error in eqwalizer passthrough")
     }
+
+    /// The AST of `module` in `format`, as it would be sent to eqWAlizer
+    /// when it asks for it, without starting eqWAlizer, e.g. to debug the
+    /// conversion or compare it against a golden file. A module that is
+    /// missing or does not parse is `Error::ModuleNotFound` or
+    /// `Error::ParseError`, which eqWAlizer would be told about instead.
+    pub fn export_ast(
+        &self,
+        db: &dyn EqwalizerDiagnosticsDatabase,
+        project_id: ProjectId,
+        module: &str,
+        format: EqWAlizerASTFormat,
+    ) -> Result<Vec<u8>, Error> {
+        get_ast_bytes(db, project_id, module, format).map(|bytes| bytes.to_vec())
+    }
 }
 
 fn do_typecheck(
     mut cmd: CommandProxy,
     db: &dyn EqwalizerDiagnosticsDatabase,
     project_id: ProjectId,
+    modules: &[&str],
+    cancel: Option<&AtomicBool>,
+    timeout: Duration,
 ) -> Result<EqwalizerDiagnostics, anyhow::Error> {
-    let mut handle = IpcHandle::from_command(&mut cmd)
+    // ELP logs these events through `log`, which drops the fields of the
+    // spans, so the messages repeat the project and module they are for.
+    let _span = tracing::info_span!("do_typecheck", project_id = project_id.0, ?modules).entered();
+    let mut handle = IpcHandle::from_command(&mut cmd, timeout)
         .with_context(|| format!("starting eqWAlizer process: {:?}", cmd))?;
+    if let Some(recorder) = Recorder::for_modules(modules) {
+        handle.add_observer(Box::new(recorder));
+    }
+    if let Some(recorder) = Recorder::from_env() {
+        handle.add_observer(Box::new(recorder));
+    }
     let _pctx = stdx::panic_context::enter(format!("\neqWAlizing with command: {:?}", cmd));
+    drive_typecheck(handle, db, project_id, cancel)
+}
+
+/// Typecheck against a recorded session instead of a running eqWAlizer,
+/// see [`session`]. This makes the IPC loop testable without the JVM.
+pub fn replay_typecheck(
+    session: Session,
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+) -> EqwalizerDiagnostics {
+    match drive_typecheck(IpcHandle::replay(session), db, project_id, None) {
+        Ok(diags) => diags,
+        Err(err) => EqwalizerDiagnostics::Error(err.into()),
+    }
+}
+
+/// Like [`replay_typecheck`], for [`Eqwalizer::typecheck_map`].
+pub fn replay_typecheck_map(
+    session: Session,
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    modules: Vec<&str>,
+) -> FxHashMap<String, EqwalizerDiagnostics> {
+    let handle = Ok(Arc::new(Mutex::new(IpcHandle::replay(session))));
+    shell_typecheck_map(handle, db, project_id, &modules)
+}
+
+/// Like [`replay_typecheck`], for [`Eqwalizer::typecheck_streaming`].
+pub fn replay_typecheck_streaming(
+    session: Session,
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    mut on_module: impl FnMut(String, Vec<EqwalizerDiagnostic>),
+) -> EqwalizerDiagnostics {
+    let handle = Ok(Arc::new(Mutex::new(IpcHandle::replay(session))));
+    shell_typecheck_streaming(handle, db, project_id, None, &mut on_module)
+}
+
+fn drive_typecheck(
+    mut handle: IpcHandle,
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    cancel: Option<&AtomicBool>,
+) -> Result<EqwalizerDiagnostics, anyhow::Error> {
+    let mut unexpected = 0;
     loop {
         db.unwind_if_cancelled();
+        if is_cancelled(cancel) {
+            return Ok(cancelled());
+        }
         match handle.receive()? {
             MsgFromEqWAlizer::GetAstBytes { module, format } => {
-                log::debug!(
-                    "received from eqwalizer: GetAstBytes for module {} (format = {:?})",
-                    module,
-                    format
-                );
-                let module_name = ModuleName::new(&module);
-                let ast = {
-                    match format {
-                        EqWAlizerASTFormat::RawForms => {
-                            db.get_erl_ast_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::ConvertedForms => {
-                            db.converted_ast_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::RawStub => {
-                            db.get_erl_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::ConvertedStub => {
-                            db.converted_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::ExpandedStub => {
-                            db.expanded_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::ContractiveStub => {
-                            db.contractive_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::CovariantStub => {
-                            db.covariant_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::TransitiveStub => {
-                            db.transitive_stub_bytes(project_id, module_name)
-                        }
-                    }
-                };
-                match ast {
-                    Ok(ast_bytes) => {
-                        log::debug!(
-                            "sending to eqwalizer: GetAstBytesReply for module {}",
-                            module
-                        );
-                        let ast_bytes_len = ast_bytes.len().try_into()?;
-                        let reply = &MsgToEqWAlizer::GetAstBytesReply { ast_bytes_len };
-                        handle.send(reply)?;
-                        handle.receive_newline()?;
-                        handle.send_bytes(&ast_bytes)?;
-                    }
-                    Err(Error::ModuleNotFound(_)) => {
-                        log::debug!(
-                            "module not found, sending to eqwalizer: empty GetAstBytesReply for module {}",
-                            module
-                        );
-                        let ast_bytes_len = 0;
-                        let reply = &MsgToEqWAlizer::GetAstBytesReply { ast_bytes_len };
-                        handle.send(reply)?;
-                        handle.receive_newline()?;
-                    }
-                    Err(Error::ParseError) => {
-                        log::debug!(
-                            "parse error, sending to eqwalizer: CannotCompleteRequest for module {}",
-                            module
-                        );
-                        let reply = &MsgToEqWAlizer::CannotCompleteRequest;
-                        handle.send(reply)?;
-                        return Ok(EqwalizerDiagnostics::NoAst { module });
-                    }
-                    Err(err) => {
-                        log::debug!(
-                            "error {} sending to eqwalizer: CannotCompleteRequest for module {}",
-                            err,
-                            module
-                        );
-                        let reply = &MsgToEqWAlizer::CannotCompleteRequest;
-                        handle.send(reply)?;
-                        return Ok(EqwalizerDiagnostics::Error(err.to_string()));
-                    }
+                if let Some(result) = reply_ast_bytes(&mut handle, db, project_id, module, format)?
+                {
+                    return Ok(result);
                 }
             }
             MsgFromEqWAlizer::EqwalizingStart { module } => db.eqwalizing_start(module),
             MsgFromEqWAlizer::EqwalizingDone { module } => db.eqwalizing_done(module),
             MsgFromEqWAlizer::Done { diagnostics } => {
-                log::debug!(
-                    "received from eqwalizer: Done with diagnostics length {}",
-                    diagnostics.len()
+                tracing::debug!(
+                    "received from eqwalizer: Done with diagnostics length {} for project {}",
+                    diagnostics.len(),
+                    project_id.0
                 );
                 return Ok(EqwalizerDiagnostics::Diagnostics(diagnostics));
             }
-            msg => {
-                log::warn!(
-                    "received unexpected message from eqwalizer, ignoring: {:?}",
-                    msg
-                )
-            }
+            msg => on_unexpected_message(&mut handle, msg, &mut unexpected)?,
         }
     }
 }
@@ -365,37 +1012,189 @@ fn shell_typecheck(
     mut cmd: CommandProxy,
     db: &dyn EqwalizerDiagnosticsDatabase,
     project_id: ProjectId,
+    cancel: Option<&AtomicBool>,
+    deterministic: bool,
+    timeout: Duration,
 ) -> Result<EqwalizerDiagnostics, anyhow::Error> {
+    let mut diagnostics = EqwalizerDiagnostics::default();
+    shell_typecheck_modules(
+        cmd,
+        db,
+        project_id,
+        cancel,
+        deterministic,
+        timeout,
+        &mut |_module, diags| {
+            diagnostics = mem::take(&mut diagnostics).combine(&diags);
+        },
+    )?;
+    if is_cancelled(cancel) {
+        return Ok(cancelled());
+    }
+    Ok(diagnostics.dedup())
+}
+
+/// Drive an eqWAlizer shell session, reporting the diagnostics of each
+/// module to `on_module` as soon as it has been checked.
+fn shell_typecheck_modules(
+    mut cmd: CommandProxy,
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    cancel: Option<&AtomicBool>,
+    deterministic: bool,
+    timeout: Duration,
+    on_module: &mut dyn FnMut(String, Arc<EqwalizerDiagnostics>),
+) -> Result<(), anyhow::Error> {
+    // Never cache the results of this function
+    db.salsa_runtime().report_untracked_read();
+    let handle = start_shell(&mut cmd, deterministic, timeout)?;
+    drive_shell(&handle, db, project_id, cancel, on_module)
+}
+
+fn shell_typecheck_map(
+    handle: Result<Arc<Mutex<IpcHandle>>, anyhow::Error>,
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    modules: &[&str],
+) -> FxHashMap<String, EqwalizerDiagnostics> {
+    // Never cache the results of this function
+    db.salsa_runtime().report_untracked_read();
+    let mut results = FxHashMap::default();
+    let outcome = handle.and_then(|handle| {
+        drive_shell(&handle, db, project_id, None, &mut |module, diags| {
+            results.insert(module, (*diags).clone());
+        })
+    });
+    if let Err(err) = outcome {
+        // Modules not reached before the failure get the error.
+        let err = EqwalizerDiagnostics::Error(err.into());
+        for module in modules {
+            results
+                .entry(module.to_string())
+                .or_insert_with(|| err.clone());
+        }
+    }
+    results
+}
+
+fn shell_typecheck_streaming(
+    handle: Result<Arc<Mutex<IpcHandle>>, anyhow::Error>,
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    cancel: Option<&AtomicBool>,
+    on_module: &mut dyn FnMut(String, Vec<EqwalizerDiagnostic>),
+) -> EqwalizerDiagnostics {
     // Never cache the results of this function
     db.salsa_runtime().report_untracked_read();
-    let handle = Arc::new(Mutex::new(
-        IpcHandle::from_command(&mut cmd)
-            .with_context(|| format!("starting eqWAlizer process: {:?}", cmd))?,
-    ));
     let mut diagnostics = EqwalizerDiagnostics::default();
+    let outcome = handle.and_then(|handle| {
+        drive_shell(&handle, db, project_id, cancel, &mut |module, diags| {
+            diagnostics = mem::take(&mut diagnostics).combine(&diags);
+            if let EqwalizerDiagnostics::Diagnostics(by_module) = &*diags {
+                let module_diags = by_module.get(&module).cloned().unwrap_or_default();
+                on_module(module, module_diags);
+            }
+        })
+    });
+    match outcome {
+        Err(err) => EqwalizerDiagnostics::Error(err.into()),
+        Ok(()) if is_cancelled(cancel) => cancelled(),
+        Ok(()) => diagnostics.dedup(),
+    }
+}
+
+fn start_shell(
+    cmd: &mut CommandProxy,
+    deterministic: bool,
+    timeout: Duration,
+) -> Result<Arc<Mutex<IpcHandle>>, anyhow::Error> {
+    let mut handle = IpcHandle::from_command(cmd, timeout)
+        .with_context(|| format!("starting eqWAlizer process: {:?}", cmd))?;
+    if let Some(recorder) = Recorder::from_env() {
+        handle.add_observer(Box::new(recorder));
+    }
+    handle.set_prefetch_dependencies(!deterministic);
+    Ok(Arc::new(Mutex::new(handle)))
+}
+
+/// The `EnteringModule`/`ELPExitingModule` handshake for each module,
+/// until eqWAlizer is `Done`.
+fn drive_shell(
+    handle: &Arc<Mutex<IpcHandle>>,
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    cancel: Option<&AtomicBool>,
+    on_module: &mut dyn FnMut(String, Arc<EqwalizerDiagnostics>),
+) -> Result<(), anyhow::Error> {
+    let mut unexpected = 0;
     loop {
         db.unwind_if_cancelled();
+        if is_cancelled(cancel) {
+            return Ok(());
+        }
         let msg = handle.lock().receive()?;
         match msg {
             MsgFromEqWAlizer::EnteringModule { module } => {
                 db.set_module_ipc_handle(ModuleName::new(&module), handle.clone());
-                let diags = db.module_diagnostics(project_id, module).0;
+                let diags = db.module_diagnostics(project_id, module.clone()).0;
                 handle.lock().send(&MsgToEqWAlizer::ELPExitingModule)?;
-                diagnostics = diagnostics.combine(&diags);
+                on_module(module, diags);
             }
             MsgFromEqWAlizer::Done { .. } => {
-                return Ok(diagnostics);
-            }
-            msg => {
-                log::warn!(
-                    "received unexpected message from eqwalizer, ignoring: {:?}",
-                    msg
-                )
+                return Ok(());
             }
+            msg => on_unexpected_message(&mut handle.lock(), msg, &mut unexpected)?,
         }
     }
 }
 
+/// An unexpected message seen this many times in a session is logged as
+/// an error, as ELP and eqWAlizer are most likely out of sync.
+const MAX_UNEXPECTED_MESSAGES: usize = 3;
+
+/// eqWAlizer may be waiting for a reply to a request that came at an
+/// unexpected time, so answer it with `CannotCompleteRequest` instead of
+/// hanging both sides. Unknown messages are not answered, see
+/// `MsgFromEqWAlizer::is_request`: if eqWAlizer does wait for them, the
+/// session timeout ends the check.
+fn on_unexpected_message(
+    handle: &mut IpcHandle,
+    msg: MsgFromEqWAlizer,
+    unexpected: &mut usize,
+) -> Result<(), anyhow::Error> {
+    *unexpected += 1;
+    if *unexpected >= MAX_UNEXPECTED_MESSAGES {
+        tracing::error!(
+            "received {} unexpected messages from eqwalizer, last: {:?}",
+            unexpected,
+            msg
+        );
+    } else if let MsgFromEqWAlizer::Unknown { tag } = &msg {
+        tracing::error!(
+            "received unknown message {} from eqwalizer, ELP and eqwalizer may be out of sync",
+            tag
+        );
+    } else {
+        tracing::warn!("received unexpected message from eqwalizer: {:?}", msg);
+    }
+    if msg.is_request() {
+        tracing::debug!(
+            "sending to eqwalizer: CannotCompleteRequest for unexpected request {:?}",
+            msg
+        );
+        handle.send(&MsgToEqWAlizer::CannotCompleteRequest)?;
+    }
+    Ok(())
+}
+
+fn is_cancelled(cancel: Option<&AtomicBool>) -> bool {
+    cancel.map_or(false, |cancel| cancel.load(Ordering::Relaxed))
+}
+
+fn cancelled() -> EqwalizerDiagnostics {
+    EqwalizerDiagnostics::Error(EqwalizerError::Cancelled)
+}
+
 fn module_diagnostics(
     db: &dyn EqwalizerDiagnosticsDatabase,
     project_id: ProjectId,
@@ -407,10 +1206,33 @@ fn module_diagnostics(
     let timestamp = Instant::now();
     match get_module_diagnostics(db, project_id, module) {
         Ok(diag) => (Arc::new(diag), timestamp),
-        Err(err) => (
-            Arc::new(EqwalizerDiagnostics::Error(format!("{}", err))),
-            timestamp,
-        ),
+        Err(err) => (Arc::new(EqwalizerDiagnostics::Error(err.into())), timestamp),
+    }
+}
+
+fn module_diagnostics_in_range(
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    module: String,
+    range: TextRange,
+) -> Arc<EqwalizerDiagnostics> {
+    let (diagnostics, _timestamp) = db.module_diagnostics(project_id, module);
+    match &*diagnostics {
+        EqwalizerDiagnostics::Diagnostics(diags) => {
+            let in_range = diags
+                .iter()
+                .map(|(module, diags)| {
+                    let diags = diags
+                        .iter()
+                        .filter(|diag| diag.range.intersect(range).is_some())
+                        .cloned()
+                        .collect();
+                    (module.clone(), diags)
+                })
+                .collect();
+            Arc::new(EqwalizerDiagnostics::Diagnostics(in_range))
+        }
+        EqwalizerDiagnostics::NoAst { .. } | EqwalizerDiagnostics::Error(_) => diagnostics.clone(),
     }
 }
 
@@ -419,6 +1241,12 @@ fn get_module_diagnostics(
     project_id: ProjectId,
     module: String,
 ) -> Result<EqwalizerDiagnostics, anyhow::Error> {
+    let _span = tracing::info_span!(
+        "get_module_diagnostics",
+        project_id = project_id.0,
+        module = module.as_str()
+    )
+    .entered();
     let handle_mutex = db
         .module_ipc_handle(ModuleName::new(&module))
         .ok_or(anyhow::Error::msg(format!(
@@ -427,144 +1255,187 @@ fn get_module_diagnostics(
         )))?;
     let mut handle = handle_mutex.lock();
     handle.send(&MsgToEqWAlizer::ELPEnteringModule)?;
+    let mut unexpected = 0;
     loop {
         db.unwind_if_cancelled();
         match handle.receive()? {
             MsgFromEqWAlizer::GetAstBytes { module, format } => {
-                log::debug!(
-                    "received from eqwalizer: GetAstBytes for module {} (format = {:?})",
-                    module,
-                    format
-                );
-                let module_name = ModuleName::new(&module);
-                let ast = {
-                    match format {
-                        EqWAlizerASTFormat::RawForms => {
-                            db.get_erl_ast_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::ConvertedForms => {
-                            db.converted_ast_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::RawStub => {
-                            db.get_erl_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::ConvertedStub => {
-                            db.converted_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::ExpandedStub => {
-                            db.expanded_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::ContractiveStub => {
-                            db.contractive_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::CovariantStub => {
-                            db.covariant_stub_bytes(project_id, module_name)
-                        }
-                        EqWAlizerASTFormat::TransitiveStub => {
-                            db.transitive_stub_bytes(project_id, module_name)
-                        }
-                    }
-                };
-                match ast {
-                    Ok(ast_bytes) => {
-                        log::debug!(
-                            "sending to eqwalizer: GetAstBytesReply for module {}",
-                            module
-                        );
-                        let ast_bytes_len = ast_bytes.len().try_into()?;
-                        let reply = &MsgToEqWAlizer::GetAstBytesReply { ast_bytes_len };
-                        handle.send(reply)?;
-                        handle.receive_newline()?;
-                        handle.send_bytes(&ast_bytes)?;
-                    }
-                    Err(Error::ModuleNotFound(_)) => {
-                        log::debug!(
-                            "module not found, sending to eqwalizer: empty GetAstBytesReply for module {}",
-                            module
-                        );
-                        let ast_bytes_len = 0;
-                        let reply = &MsgToEqWAlizer::GetAstBytesReply { ast_bytes_len };
-                        handle.send(reply)?;
-                        handle.receive_newline()?;
-                    }
-                    Err(Error::ParseError) => {
-                        log::debug!(
-                            "parse error, sending to eqwalizer: CannotCompleteRequest for module {}",
-                            module
-                        );
-                        let reply = &MsgToEqWAlizer::CannotCompleteRequest;
-                        handle.send(reply)?;
-                        return Ok(EqwalizerDiagnostics::NoAst { module });
-                    }
-                    Err(err) => {
-                        log::debug!(
-                            "error {} sending to eqwalizer: CannotCompleteRequest for module {}",
-                            err,
-                            module
-                        );
-                        let reply = &MsgToEqWAlizer::CannotCompleteRequest;
-                        handle.send(reply)?;
-                        return Ok(EqwalizerDiagnostics::Error(err.to_string()));
-                    }
+                if let Some(result) = reply_ast_bytes(&mut handle, db, project_id, module, format)?
+                {
+                    return Ok(result);
                 }
             }
             MsgFromEqWAlizer::EqwalizingStart { module } => db.eqwalizing_start(module),
             MsgFromEqWAlizer::EqwalizingDone { module } => db.eqwalizing_done(module),
             MsgFromEqWAlizer::Done { diagnostics } => {
-                log::debug!(
-                    "received from eqwalizer: Done with diagnostics length {}",
-                    diagnostics.len()
+                tracing::debug!(
+                    "received from eqwalizer: Done with diagnostics length {} for module {} of project {}",
+                    diagnostics.len(),
+                    module,
+                    project_id.0
                 );
                 return Ok(EqwalizerDiagnostics::Diagnostics(diagnostics));
             }
             MsgFromEqWAlizer::Dependencies { modules } => {
-                modules.iter().for_each(|module| {
-                    let module = ModuleName::new(&module);
-                    _ = db.transitive_stub_bytes(project_id, module);
-                });
-            }
-            msg => {
-                log::warn!(
-                    "received unexpected message from eqwalizer, ignoring: {:?}",
-                    msg
-                )
+                // The stubs are computed anyway when eqWAlizer asks for
+                // them, so this only changes the timing.
+                if handle.prefetch_dependencies() {
+                    modules.iter().for_each(|module| {
+                        let module = ModuleName::new(&module);
+                        _ = db.transitive_stub_bytes(project_id, module);
+                    });
+                }
             }
+            msg => on_unexpected_message(&mut handle, msg, &mut unexpected)?,
         }
     }
 }
 
-fn compute_eqwalizer_stats(
+/// Answer eqWAlizer's request for the AST of `module`. When it cannot
+/// be answered, eqWAlizer gives up on the typecheck, and its result is
+/// returned.
+fn reply_ast_bytes(
+    handle: &mut IpcHandle,
     db: &dyn EqwalizerDiagnosticsDatabase,
     project_id: ProjectId,
-    module: ModuleName,
-) -> Option<Arc<EqwalizerStats>> {
-    let ast = db.converted_ast(project_id, module).ok()?;
-    let mut fixmes = 0;
-    let mut ignores = 0;
-    let mut nowarn = 0;
-    for form in ast.to_vec() {
-        match form {
-            ExternalForm::ElpMetadata(meta) => {
-                for fixme in meta.fixmes {
-                    if fixme.is_ignore {
-                        ignores += 1
-                    } else {
-                        fixmes += 1
-                    }
+    module: String,
+    format: EqWAlizerASTFormat,
+) -> Result<Option<EqwalizerDiagnostics>, anyhow::Error> {
+    tracing::debug!(
+        "received from eqwalizer: GetAstBytes for module {} of project {} (format = {:?})",
+        module,
+        project_id.0,
+        format
+    );
+    match get_ast_bytes(db, project_id, &module, format) {
+        Ok(ast) => {
+            let max_ast_bytes = max_ast_bytes();
+            if handle.add_ast_bytes_sent(ast.len()) > max_ast_bytes {
+                tracing::warn!(
+                    "AST limit of {} bytes exceeded, sending to eqwalizer: CannotCompleteRequest for module {} of project {}",
+                    max_ast_bytes,
+                    module,
+                    project_id.0
+                );
+                handle.send(&MsgToEqWAlizer::CannotCompleteRequest)?;
+                return Err(EqwalizerError::AstLimitExceeded {
+                    module,
+                    limit: max_ast_bytes,
                 }
+                .into());
             }
-            ExternalForm::EqwalizerNowarnFunction(_) => nowarn += 1,
-            _ => (),
+            tracing::debug!(
+                "sending to eqwalizer: GetAstBytesReply for module {} of project {}",
+                module,
+                project_id.0
+            );
+            let ast_bytes_len = ast.len().try_into()?;
+            let reply = &MsgToEqWAlizer::GetAstBytesReply { ast_bytes_len };
+            handle.send(reply)?;
+            handle.receive_newline()?;
+            handle.send_bytes(&ast)?;
+            Ok(None)
+        }
+        Err(Error::ModuleNotFound(_)) => {
+            tracing::debug!(
+                "module not found, sending to eqwalizer: empty GetAstBytesReply for module {} of project {}",
+                module,
+                project_id.0
+            );
+            let ast_bytes_len = 0;
+            let reply = &MsgToEqWAlizer::GetAstBytesReply { ast_bytes_len };
+            handle.send(reply)?;
+            handle.receive_newline()?;
+            Ok(None)
         }
+        Err(Error::ParseError) => {
+            tracing::debug!(
+                "parse error, sending to eqwalizer: CannotCompleteRequest for module {} of project {}",
+                module,
+                project_id.0
+            );
+            let reply = &MsgToEqWAlizer::CannotCompleteRequest;
+            handle.send(reply)?;
+            Ok(Some(EqwalizerDiagnostics::NoAst { module }))
+        }
+        Err(err) => {
+            tracing::debug!(
+                "error {} sending to eqwalizer: CannotCompleteRequest for module {} of project {}",
+                err,
+                module,
+                project_id.0
+            );
+            let reply = &MsgToEqWAlizer::CannotCompleteRequest;
+            handle.send(reply)?;
+            Ok(Some(EqwalizerDiagnostics::Error(err.into())))
+        }
+    }
+}
+
+/// The AST of a module in the format requested by eqWAlizer.
+pub fn get_ast_bytes(
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    module: &str,
+    format: EqWAlizerASTFormat,
+) -> Result<Arc<Vec<u8>>, Error> {
+    let _span =
+        tracing::debug_span!("get_ast_bytes", project_id = project_id.0, module, ?format).entered();
+    let module_name = ModuleName::new(module);
+    match format {
+        EqWAlizerASTFormat::RawForms => db.get_erl_ast_bytes(project_id, module_name),
+        EqWAlizerASTFormat::ConvertedForms => db.converted_ast_bytes(project_id, module_name),
+        EqWAlizerASTFormat::RawStub => db.get_erl_stub_bytes(project_id, module_name),
+        EqWAlizerASTFormat::ConvertedStub => db.converted_stub_bytes(project_id, module_name),
+        EqWAlizerASTFormat::ExpandedStub => db.expanded_stub_bytes(project_id, module_name),
+        EqWAlizerASTFormat::ContractiveStub => db.contractive_stub_bytes(project_id, module_name),
+        EqWAlizerASTFormat::CovariantStub => db.covariant_stub_bytes(project_id, module_name),
+        EqWAlizerASTFormat::TransitiveStub => db.transitive_stub_bytes(project_id, module_name),
     }
-    if fixmes == 0 && ignores == 0 && nowarn == 0 {
+}
+
+fn compute_eqwalizer_stats(
+    db: &dyn EqwalizerDiagnosticsDatabase,
+    project_id: ProjectId,
+    module: ModuleName,
+) -> Option<Arc<EqwalizerStats>> {
+    let ast = db.converted_ast(project_id, module.clone()).ok()?;
+    let stats = EqwalizerStats::from_ast(&module, &ast);
+    if stats == EqwalizerStats::default() {
         return None;
     }
-    Some(Arc::new(EqwalizerStats {
-        fixmes,
-        ignores,
-        nowarn,
-    }))
+    Some(Arc::new(stats))
+}
+
+/// Fold `f` over the converted AST of every module owned by the project
+/// in a file for which `include` holds, e.g. to run a custom analysis
+/// over the modules opted in to eqWAlizer. The ASTs come from the
+/// `converted_ast` query, so they are shared with eqWAlizer and only
+/// recomputed when their module changes. Modules without an AST, e.g.
+/// failing to parse, are skipped.
+pub fn fold_modules<T, Db>(
+    db: &Db,
+    project_id: ProjectId,
+    include: impl Fn(FileId) -> bool,
+    init: T,
+    f: impl Fn(T, ModuleName, &ast::AST) -> T,
+) -> T
+where
+    Db: EqwalizerDiagnosticsDatabase + ?Sized,
+{
+    let module_index = db.module_index(project_id);
+    module_index
+        .iter_own()
+        .filter(|(_module, _source, file_id)| include(*file_id))
+        .fold(init, |acc, (module, _source, _file_id)| {
+            match db.converted_ast(project_id, module.clone()) {
+                Ok(ast) => f(acc, module.clone(), &ast),
+                Err(err) => {
+                    tracing::debug!("no AST for module {}: {:?}", module.as_str(), err);
+                    acc
+                }
+            }
+        })
 }
 
 fn add_env(cmd: &mut Command, build_info_path: &Path, elp_ast_dir: Option<&Path>) {
@@ -601,3 +1472,237 @@ impl fmt::Debug for CommandProxy<'_> {
         write!(f, "{:?}", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionEntry;
+
+    fn diagnostic(uri: &str, start: u32, message: &str) -> EqwalizerDiagnostic {
+        EqwalizerDiagnostic {
+            range: TextRange::new(start.into(), (start + 10).into()),
+            message: message.to_string(),
+            uri: uri.to_string(),
+            code: "incompatible_types".to_string(),
+            expression: Some("X".to_string()),
+            explanation: None,
+            severity: EqwalizerSeverity::Error,
+        }
+    }
+
+    fn by_module(module: &str, diags: Vec<EqwalizerDiagnostic>) -> EqwalizerDiagnostics {
+        EqwalizerDiagnostics::Diagnostics(FxHashMap::from_iter([(module.to_string(), diags)]))
+    }
+
+    fn diagnostic_json(start: u32, end: u32) -> String {
+        format!(
+            r#"{{"range": {{"start": {start}, "end": {end}}}, "message": "m", "uri": "a.erl", "code": "c", "expressionOrNull": null, "explanationOrNull": null}}"#
+        )
+    }
+
+    fn replay(entries: Vec<SessionEntry>) -> IpcHandle {
+        IpcHandle::replay(Session {
+            entries: entries.into(),
+        })
+    }
+
+    #[test]
+    fn receive_non_json_is_a_protocol_error() {
+        let mut handle = replay(vec![SessionEntry::Received("not json\n".to_string())]);
+        let err = handle
+            .receive()
+            .expect_err("a non-JSON line should not decode");
+        assert!(
+            matches!(EqwalizerError::from(err), EqwalizerError::Protocol(_)),
+            "expected a protocol error"
+        );
+    }
+
+    #[test]
+    fn timeout_is_kept_under_context() {
+        let err = anyhow::Error::new(EqwalizerError::Timeout {
+            after: Duration::from_secs(3),
+        })
+        .context("receiving message");
+        assert_eq!(
+            EqwalizerError::from(err),
+            EqwalizerError::Timeout {
+                after: Duration::from_secs(3)
+            }
+        );
+    }
+
+    #[test]
+    fn unexpected_request_is_answered() {
+        let mut handle = replay(vec![SessionEntry::Sent(
+            r#"{"tag":"CannotCompleteRequest"}"#.to_string(),
+        )]);
+        let mut unexpected = 0;
+        let msg = MsgFromEqWAlizer::GetAstBytes {
+            module: "foo".to_string(),
+            format: EqWAlizerASTFormat::ConvertedForms,
+        };
+        on_unexpected_message(&mut handle, msg, &mut unexpected).unwrap();
+        assert_eq!(unexpected, 1);
+    }
+
+    #[test]
+    fn unknown_message_is_not_answered() {
+        // Replaying an empty session fails on anything sent.
+        let mut handle = replay(vec![]);
+        let mut unexpected = 0;
+        for tag in ["GetStubBytes", "TypecheckProgress"] {
+            let msg = MsgFromEqWAlizer::Unknown {
+                tag: tag.to_string(),
+            };
+            on_unexpected_message(&mut handle, msg, &mut unexpected).unwrap();
+        }
+        assert_eq!(unexpected, 2);
+    }
+
+    #[test]
+    fn jar_args_put_user_args_between_defaults_and_jar() {
+        let user: Vec<OsString> = ["-Xmx4G", "-jar", "other.jar", "-Xss40M"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let args = jar_args(Path::new("eqwalizer.jar"), &user);
+        assert_eq!(
+            args,
+            ["-Xss20M", "-Xmx4G", "-Xss40M", "-jar", "eqwalizer.jar"]
+                .iter()
+                .map(OsString::from)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            jar_args(Path::new("eqwalizer.jar"), &[]),
+            ["-Xss20M", "-jar", "eqwalizer.jar"]
+                .iter()
+                .map(OsString::from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn builder_passes_jvm_args_to_jar() {
+        let jar = Builder::new()
+            .suffix(".jar")
+            .tempfile()
+            .unwrap()
+            .into_temp_path();
+        let eqwalizer = EqwalizerBuilder::default()
+            .path(jar.to_path_buf())
+            .jvm_args(vec!["-Xmx4G".into()])
+            .try_build()
+            .unwrap();
+        assert_eq!(eqwalizer.cmd, "java");
+        assert_eq!(
+            eqwalizer.args,
+            vec![
+                OsString::from("-Xss20M"),
+                "-Xmx4G".into(),
+                "-jar".into(),
+                jar.as_os_str().into()
+            ]
+        );
+    }
+
+    #[test]
+    fn jvm_args_split_on_whitespace() {
+        assert_eq!(
+            split_jvm_args(OsStr::new("  -Xmx4G\t-XX:+UseG1GC \n")),
+            vec![OsString::from("-Xmx4G"), "-XX:+UseG1GC".into()]
+        );
+        assert_eq!(split_jvm_args(OsStr::new(" ")), Vec::<OsString>::new());
+    }
+
+    #[test]
+    fn deserialize_inverted_range_is_an_error() {
+        let err = serde_json::from_str::<EqwalizerDiagnostic>(&diagnostic_json(10, 3))
+            .expect_err("an inverted range should not deserialize");
+        assert!(
+            err.to_string()
+                .contains("invalid range: start 10 is after end 3"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn deserialize_range() {
+        let diag = serde_json::from_str::<EqwalizerDiagnostic>(&diagnostic_json(3, 10)).unwrap();
+        assert_eq!(diag.range, TextRange::new(3.into(), 10.into()));
+        let diag = serde_json::from_str::<EqwalizerDiagnostic>(&diagnostic_json(3, 3)).unwrap();
+        assert_eq!(diag.range, TextRange::empty(3.into()));
+    }
+
+    #[test]
+    fn combine_collapses_header_diagnostic_of_includers() {
+        let header = diagnostic("header.hrl", 0, "header error");
+        let combined = ["c", "a", "b"]
+            .iter()
+            .map(|module| {
+                let own = diagnostic(&format!("{module}.erl"), 20, "own error");
+                by_module(module, vec![header.clone(), own])
+            })
+            .fold(EqwalizerDiagnostics::default(), |acc, diags| {
+                acc.combine(&diags)
+            })
+            .dedup();
+        let diags = match combined {
+            EqwalizerDiagnostics::Diagnostics(diags) => diags,
+            other => panic!("expected diagnostics, got {:?}", other),
+        };
+        assert_eq!(diags.len(), 3);
+        assert_eq!(
+            diags["a"],
+            vec![header, diagnostic("a.erl", 20, "own error")]
+        );
+        assert_eq!(diags["b"], vec![diagnostic("b.erl", 20, "own error")]);
+        assert_eq!(diags["c"], vec![diagnostic("c.erl", 20, "own error")]);
+        let total: usize = diags.values().map(|diags| diags.len()).sum();
+        assert_eq!(total, 4);
+    }
+
+    #[test]
+    fn dedup_drops_non_adjacent_duplicates() {
+        let header = diagnostic("header.hrl", 0, "header error");
+        let own = diagnostic("a.erl", 20, "own error");
+        let other = diagnostic("b.erl", 20, "own error");
+        let combined = by_module("a", vec![header.clone(), own.clone(), header.clone()])
+            .combine(&by_module("b", vec![other.clone(), header.clone()]))
+            .dedup();
+        let diags = match combined {
+            EqwalizerDiagnostics::Diagnostics(diags) => diags,
+            other => panic!("expected diagnostics, got {:?}", other),
+        };
+        assert_eq!(diags["a"], vec![header, own]);
+        assert_eq!(diags["b"], vec![other]);
+    }
+
+    #[test]
+    fn dedup_keeps_diagnostics_differing_in_expression() {
+        let header = diagnostic("header.hrl", 0, "header error");
+        let other_expression = EqwalizerDiagnostic {
+            expression: Some("Y".to_string()),
+            ..header.clone()
+        };
+        let other_explanation = EqwalizerDiagnostic {
+            explanation: Some("because".to_string()),
+            ..header.clone()
+        };
+        let diags = EqwalizerDiagnostics::Diagnostics(FxHashMap::from_iter([
+            ("a".to_string(), vec![header.clone()]),
+            (
+                "b".to_string(),
+                vec![header, other_expression.clone(), other_explanation.clone()],
+            ),
+        ]))
+        .dedup();
+        let diags = match diags {
+            EqwalizerDiagnostics::Diagnostics(diags) => diags,
+            other => panic!("expected diagnostics, got {:?}", other),
+        };
+        assert_eq!(diags["b"], vec![other_expression, other_explanation]);
+    }
+}
@@ -0,0 +1,233 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Recording and replay of whole eqWAlizer sessions, so that the IPC
+//! loop can be driven deterministically in tests without the JVM.
+//!
+//! Set `ELP_EQWALIZER_RECORD_DIR` to record every session into that
+//! directory, one `eqwalizer-session-<pid>-<n>.jsonl` file per session.
+//! Each line is a [`SessionEntry`]: a line received from eqWAlizer, a
+//! message sent to it, or the AST bytes sent to it, hex-encoded.
+//!
+//! To record only the typecheck of a module failing for a user, e.g. to
+//! attach to a bug report, set `ELP_EQWALIZER_TRANSCRIPT` to the name of
+//! the module. Its session is written to `eqwalizer-<module>.jsonl` in
+//! `ELP_EQWALIZER_TRANSCRIPT_DIR`, or the system temporary directory.
+//!
+//! A recorded [`Session`] is replayed with `IpcHandle::replay`, which
+//! returns the received lines in order and checks that what ELP sends
+//! matches the recording. A session ending early replays a crash. Whole
+//! typechecks are replayed with `replay_typecheck`, and shell sessions
+//! with `replay_typecheck_map` or `replay_typecheck_streaming`.
+
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use anyhow::Context;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::ipc::IpcObserver;
+
+const RECORD_DIR_ENV: &str = "ELP_EQWALIZER_RECORD_DIR";
+const TRANSCRIPT_MODULE_ENV: &str = "ELP_EQWALIZER_TRANSCRIPT";
+const TRANSCRIPT_DIR_ENV: &str = "ELP_EQWALIZER_TRANSCRIPT_DIR";
+
+static SESSION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SessionEntry {
+    /// A line read from eqWAlizer, including its newline.
+    Received(String),
+    /// A message sent to eqWAlizer, as serialized JSON.
+    Sent(String),
+    /// AST bytes sent to eqWAlizer.
+    SentBytes(#[serde(with = "hex")] Vec<u8>),
+}
+
+/// Bytes as a string of hex digits, rather than a JSON array of numbers
+/// several times the size.
+mod hex {
+    use std::fmt::Write;
+
+    use serde::de::Error;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub(super) fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut hex = String::with_capacity(bytes.len() * 2);
+        for byte in bytes {
+            write!(hex, "{:02x}", byte).expect("writing to a string");
+        }
+        serializer.serialize_str(&hex)
+    }
+
+    pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex = String::deserialize(deserializer)?;
+        if !hex.is_ascii() || hex.len() % 2 != 0 {
+            return Err(D::Error::custom(format!("invalid hex bytes: {:?}", hex)));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    pub entries: VecDeque<SessionEntry>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Session> {
+        let file = File::open(path)
+            .with_context(|| format!("opening eqWAlizer session {}", path.display()))?;
+        let mut entries = VecDeque::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line)
+                .with_context(|| format!("parsing eqWAlizer session {}", path.display()))?;
+            entries.push_back(entry);
+        }
+        Ok(Session { entries })
+    }
+}
+
+/// Writes every entry of a session to a file as it happens, so that the
+/// recording survives a crash of either side.
+pub(crate) struct Recorder {
+    path: PathBuf,
+    file: File,
+}
+
+impl Recorder {
+    /// Start recording if `ELP_EQWALIZER_RECORD_DIR` is set.
+    pub(crate) fn from_env() -> Option<Recorder> {
+        let dir = PathBuf::from(env::var_os(RECORD_DIR_ENV)?);
+        let count = SESSION_COUNT.fetch_add(1, Ordering::Relaxed);
+        Recorder::create(dir.join(format!(
+            "eqwalizer-session-{}-{}.jsonl",
+            process::id(),
+            count
+        )))
+    }
+
+    /// Start recording if `ELP_EQWALIZER_TRANSCRIPT` names one of the
+    /// modules.
+    pub(crate) fn for_modules(modules: &[&str]) -> Option<Recorder> {
+        let module = env::var(TRANSCRIPT_MODULE_ENV).ok()?;
+        if !modules.contains(&module.as_str()) {
+            return None;
+        }
+        let dir = env::var_os(TRANSCRIPT_DIR_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(env::temp_dir);
+        Recorder::create(dir.join(format!("eqwalizer-{}.jsonl", module)))
+    }
+
+    fn create(path: PathBuf) -> Option<Recorder> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        match fs::create_dir_all(dir).and_then(|()| File::create(&path)) {
+            Ok(file) => {
+                tracing::info!("recording eqWAlizer session to {}", path.display());
+                Some(Recorder { path, file })
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "cannot create eqWAlizer session {}: {}",
+                    path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    fn record(&mut self, entry: SessionEntry) {
+        let line = serde_json::to_string(&entry).expect("failed to serialize session entry");
+        // Recording is a testing aid, it must not break the typecheck
+        if let Err(err) = writeln!(self.file, "{}", line) {
+            tracing::warn!(
+                "failed to write eqWAlizer session {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+impl IpcObserver for Recorder {
+    fn on_receive(&mut self, line: &str) {
+        self.record(SessionEntry::Received(line.to_string()));
+    }
+
+    fn on_send(&mut self, line: &str) {
+        self.record(SessionEntry::Sent(line.to_string()));
+    }
+
+    fn on_send_bytes(&mut self, bytes: &[u8]) {
+        self.record(SessionEntry::SentBytes(bytes.to_vec()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_session_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let mut recorder = Recorder::create(path.clone()).unwrap();
+        recorder.on_receive("{\"tag\":\"EnteringModule\"}\n");
+        recorder.on_send("{\"tag\":\"ELPEnteringModule\"}");
+        recorder.on_send_bytes(&[131, 0]);
+        drop(recorder);
+        assert_eq!(
+            Session::load(&path).unwrap().entries,
+            VecDeque::from([
+                SessionEntry::Received("{\"tag\":\"EnteringModule\"}\n".to_string()),
+                SessionEntry::Sent("{\"tag\":\"ELPEnteringModule\"}".to_string()),
+                SessionEntry::SentBytes(vec![131, 0]),
+            ])
+        );
+    }
+
+    #[test]
+    fn sent_bytes_round_trip_as_hex() {
+        let entry = SessionEntry::SentBytes(vec![0, 131, 255]);
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"SentBytes":"0083ff"}"#);
+        assert_eq!(serde_json::from_str::<SessionEntry>(&json).unwrap(), entry);
+        assert!(serde_json::from_str::<SessionEntry>(r#"{"SentBytes":"0g"}"#).is_err());
+        assert!(serde_json::from_str::<SessionEntry>(r#"{"SentBytes":"083"}"#).is_err());
+    }
+}
@@ -53,18 +53,48 @@ use crate::common_test;
 use crate::RootDatabase;
 use crate::SourceDatabase;
 
+mod accidental_callback;
+mod always_crashes;
 mod application_env;
+mod binary_string_encoding;
+mod boolean_operators_in_guard;
+mod constant_record_field;
 mod cross_node_eval;
 mod effect_free_statement;
+mod exception_control_flow;
+mod guard_to_pattern;
+mod guard_variable_not_bound;
 mod head_mismatch;
 // @fb-only: mod meta_only;
+mod high_complexity;
+mod leaky_exported_type;
+mod length_zero_check;
+mod lists_nth_perf;
+mod literal_apply;
+mod map_pattern_operator;
+mod mergeable_clauses;
 mod missing_compile_warn_missing_spec;
+mod missing_reverse;
 mod misspelled_attribute;
 mod module_mismatch;
 mod mutable_variable;
+mod narrowable_spec;
+mod non_boolean_flag;
+mod orphaned_spec;
+mod prefer_exact_equality;
+mod record_element_access;
+mod record_info_usage;
+mod redefined_macro;
 mod redundant_assignment;
+mod redundant_catch;
+mod redundant_conversion;
+mod redundant_module_prefix;
+mod redundant_type_guard;
 mod replace_call;
+mod stale_pid_send;
+mod timer_tc_unit;
 mod trivial_match;
+mod unchecked_io_result;
 mod unused_function_args;
 mod unused_include;
 mod unused_macro;
@@ -81,6 +111,11 @@ pub struct Diagnostic {
     pub fixes: Option<Vec<Assist>>,
     pub related_info: Option<Vec<RelatedInformation>>,
     pub code: DiagnosticCode,
+    /// Tool specific data from a `DiagnosticEnricher`, passed through
+    /// to the client unchanged.
+    pub metadata: Option<serde_json::Value>,
+    /// Hash of the source lines of the diagnostic, see `with_context`.
+    context: Option<u64>,
 }
 
 impl Diagnostic {
@@ -98,6 +133,8 @@ impl Diagnostic {
             experimental: false,
             fixes: None,
             related_info: None,
+            metadata: None,
+            context: None,
         }
     }
 
@@ -127,6 +164,11 @@ impl Diagnostic {
         self
     }
 
+    pub fn with_metadata(mut self, metadata: Option<serde_json::Value>) -> Diagnostic {
+        self.metadata = metadata;
+        self
+    }
+
     pub(crate) fn experimental(mut self) -> Diagnostic {
         self.experimental = true;
         self
@@ -164,6 +206,27 @@ impl Diagnostic {
         self
     }
 
+    /// Remember the source lines the diagnostic is on, with whitespace
+    /// normalized, for `fingerprint`.
+    pub(crate) fn with_context(mut self, text: &str) -> Diagnostic {
+        let start = usize::from(self.range.start()).min(text.len());
+        let end = usize::from(self.range.end()).clamp(start, text.len());
+        let line_start = text[..start].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = text[end..].find('\n').map_or(text.len(), |idx| end + idx);
+        self.context = text.get(line_start..line_end).map(|lines| {
+            let normalized: Vec<&str> = lines.split_whitespace().collect();
+            fxhash::hash64(&normalized)
+        });
+        self
+    }
+
+    /// A hash of the code, the message and the source lines of the
+    /// diagnostic, but not of its range. It stays the same when the code
+    /// moves around, so a baseline file can reference the diagnostic.
+    pub fn fingerprint(&self) -> u64 {
+        fxhash::hash64(&(self.code.as_code(), &self.message, self.context))
+    }
+
     pub fn print(&self, line_index: &LineIndex) -> String {
         let start = line_index.line_col(self.range.start());
         let end = line_index.line_col(self.range.end());
@@ -236,6 +299,36 @@ pub enum DiagnosticCode {
     MissingCompileWarnMissingSpec,
     MisspelledAttribute,
     CrossNodeEval,
+    AccidentalCallback,
+    RedundantCatch,
+    RedundantTypeGuard,
+    StalePidSend,
+    ConstantRecordField,
+    PreferExactEquality,
+    MissingReverse,
+    RedundantModulePrefix,
+    GuardToPattern,
+    LeakyExportedType,
+    LengthZeroCheck,
+    BinaryStringEncoding,
+    MergeableClauses,
+    AlwaysCrashes,
+    NarrowableSpec,
+    OrphanedSpec,
+    BooleanOperatorsInGuard,
+    ExceptionControlFlow,
+    UncheckedIoResult,
+    RecordInfoUsage,
+    NonBooleanFlag,
+    TimerTcUnit,
+    GuardVariableNotBound,
+    MapPatternOperator,
+    LiteralApply,
+    HighComplexity,
+    RecordElementAccess,
+    ListsNthPerf,
+    RedundantConversion,
+    RedefinedMacro,
 
     // Wrapper for erlang service diagnostic codes
     ErlangService(String),
@@ -273,6 +366,36 @@ impl DiagnosticCode {
             DiagnosticCode::MissingCompileWarnMissingSpec => "W0012".to_string(),
             DiagnosticCode::MisspelledAttribute => "W0013".to_string(), // misspelled-attribute
             DiagnosticCode::CrossNodeEval => "W0014".to_string(),       // cross-node-eval
+            DiagnosticCode::AccidentalCallback => "W0015".to_string(),  // accidental-callback
+            DiagnosticCode::RedundantCatch => "W0016".to_string(),      // redundant-catch
+            DiagnosticCode::RedundantTypeGuard => "W0017".to_string(),  // redundant-type-guard
+            DiagnosticCode::StalePidSend => "W0018".to_string(),        // stale-pid-send
+            DiagnosticCode::ConstantRecordField => "W0019".to_string(), // constant-record-field
+            DiagnosticCode::PreferExactEquality => "W0020".to_string(), // prefer-exact-equality
+            DiagnosticCode::MissingReverse => "W0021".to_string(),      // missing-reverse
+            DiagnosticCode::RedundantModulePrefix => "W0022".to_string(), // redundant-module-prefix
+            DiagnosticCode::GuardToPattern => "W0023".to_string(),      // guard-to-pattern
+            DiagnosticCode::LeakyExportedType => "W0024".to_string(),   // leaky-exported-type
+            DiagnosticCode::LengthZeroCheck => "W0025".to_string(),     // length-zero-check
+            DiagnosticCode::BinaryStringEncoding => "W0026".to_string(), // binary-string-encoding
+            DiagnosticCode::MergeableClauses => "W0027".to_string(),    // mergeable-clauses
+            DiagnosticCode::AlwaysCrashes => "W0028".to_string(),       // always-crashes
+            DiagnosticCode::NarrowableSpec => "W0029".to_string(),      // narrowable-spec
+            DiagnosticCode::OrphanedSpec => "W0030".to_string(),        // orphaned-spec
+            DiagnosticCode::BooleanOperatorsInGuard => "W0031".to_string(), // boolean-operators-in-guard
+            DiagnosticCode::ExceptionControlFlow => "W0032".to_string(), // exception-control-flow
+            DiagnosticCode::UncheckedIoResult => "W0033".to_string(),    // unchecked-io-result
+            DiagnosticCode::RecordInfoUsage => "W0034".to_string(),      // record-info-usage
+            DiagnosticCode::NonBooleanFlag => "W0035".to_string(),       // non-boolean-flag
+            DiagnosticCode::TimerTcUnit => "W0036".to_string(),          // timer-tc-unit
+            DiagnosticCode::GuardVariableNotBound => "W0037".to_string(), // guard-variable-not-bound
+            DiagnosticCode::MapPatternOperator => "W0038".to_string(),    // map-pattern-operator
+            DiagnosticCode::LiteralApply => "W0039".to_string(),          // literal-apply
+            DiagnosticCode::HighComplexity => "W0040".to_string(),        // high-complexity
+            DiagnosticCode::RecordElementAccess => "W0041".to_string(),   // record-element-access
+            DiagnosticCode::ListsNthPerf => "W0042".to_string(),          // lists-nth-perf
+            DiagnosticCode::RedundantConversion => "W0043".to_string(),   // redundant-conversion
+            DiagnosticCode::RedefinedMacro => "W0044".to_string(),        // redefined-macro
             DiagnosticCode::ErlangService(c) => c.to_string(),
             DiagnosticCode::AdHoc(c) => format!("ad-hoc: {c}").to_string(),
             // @fb-only: DiagnosticCode::MetaOnly(c) => c.as_code(),
@@ -304,12 +427,103 @@ impl DiagnosticCode {
             DiagnosticCode::ApplicationGetEnv => "application_get_env".to_string(),
             DiagnosticCode::MisspelledAttribute => "misspelled_attribute".to_string(),
             DiagnosticCode::CrossNodeEval => "cross_node_eval".to_string(),
+            DiagnosticCode::AccidentalCallback => "accidental_callback".to_string(),
+            DiagnosticCode::RedundantCatch => "redundant_catch".to_string(),
+            DiagnosticCode::RedundantTypeGuard => "redundant_type_guard".to_string(),
+            DiagnosticCode::StalePidSend => "stale_pid_send".to_string(),
+            DiagnosticCode::ConstantRecordField => "constant_record_field".to_string(),
+            DiagnosticCode::PreferExactEquality => "prefer_exact_equality".to_string(),
+            DiagnosticCode::MissingReverse => "missing_reverse".to_string(),
+            DiagnosticCode::RedundantModulePrefix => "redundant_module_prefix".to_string(),
+            DiagnosticCode::GuardToPattern => "guard_to_pattern".to_string(),
+            DiagnosticCode::LeakyExportedType => "leaky_exported_type".to_string(),
+            DiagnosticCode::LengthZeroCheck => "length_zero_check".to_string(),
+            DiagnosticCode::BinaryStringEncoding => "binary_string_encoding".to_string(),
+            DiagnosticCode::MergeableClauses => "mergeable_clauses".to_string(),
+            DiagnosticCode::AlwaysCrashes => "always_crashes".to_string(),
+            DiagnosticCode::NarrowableSpec => "narrowable_spec".to_string(),
+            DiagnosticCode::OrphanedSpec => "orphaned_spec".to_string(),
+            DiagnosticCode::BooleanOperatorsInGuard => "boolean_operators_in_guard".to_string(),
+            DiagnosticCode::ExceptionControlFlow => "exception_control_flow".to_string(),
+            DiagnosticCode::UncheckedIoResult => "unchecked_io_result".to_string(),
+            DiagnosticCode::RecordInfoUsage => "record_info_usage".to_string(),
+            DiagnosticCode::NonBooleanFlag => "non_boolean_flag".to_string(),
+            DiagnosticCode::TimerTcUnit => "timer_tc_unit".to_string(),
+            DiagnosticCode::GuardVariableNotBound => "guard_variable_not_bound".to_string(),
+            DiagnosticCode::MapPatternOperator => "map_pattern_operator".to_string(),
+            DiagnosticCode::LiteralApply => "literal_apply".to_string(),
+            DiagnosticCode::HighComplexity => "high_complexity".to_string(),
+            DiagnosticCode::RecordElementAccess => "record_element_access".to_string(),
+            DiagnosticCode::ListsNthPerf => "lists_nth_perf".to_string(),
+            DiagnosticCode::RedundantConversion => "redundant_conversion".to_string(),
+            DiagnosticCode::RedefinedMacro => "redefined_macro".to_string(),
             DiagnosticCode::ErlangService(c) => c.to_string(),
             DiagnosticCode::AdHoc(c) => format!("ad-hoc: {c}").to_string(),
             // @fb-only: DiagnosticCode::MetaOnly(c) => c.as_label(),
         }
     }
 
+    /// Whether the fixes offered for this diagnostic are safe to apply
+    /// without review, i.e. they never change the behaviour of the
+    /// code. Used to select the diagnostics run by `get_safe_fixes`.
+    pub fn has_safe_fix(&self) -> bool {
+        match self {
+            DiagnosticCode::UnusedInclude => true,
+            DiagnosticCode::UnusedMacro => true,
+            DiagnosticCode::DefaultCodeForEnumIter
+            | DiagnosticCode::HeadMismatch
+            | DiagnosticCode::MissingModule
+            | DiagnosticCode::ModuleMismatch
+            | DiagnosticCode::BoundVarInPattern
+            | DiagnosticCode::UnusedRecordField
+            | DiagnosticCode::MutableVarBug
+            | DiagnosticCode::SyntaxError
+            | DiagnosticCode::Missing(_)
+            | DiagnosticCode::StatementHasNoEffect
+            | DiagnosticCode::TrivialMatch
+            | DiagnosticCode::UnusedFunctionArg
+            | DiagnosticCode::RedundantAssignment
+            | DiagnosticCode::UnreachableTest
+            | DiagnosticCode::ApplicationGetEnv
+            | DiagnosticCode::MissingCompileWarnMissingSpec
+            | DiagnosticCode::MisspelledAttribute
+            | DiagnosticCode::CrossNodeEval
+            | DiagnosticCode::AccidentalCallback
+            | DiagnosticCode::RedundantCatch
+            | DiagnosticCode::RedundantTypeGuard
+            | DiagnosticCode::StalePidSend
+            | DiagnosticCode::ConstantRecordField
+            | DiagnosticCode::PreferExactEquality
+            | DiagnosticCode::MissingReverse
+            | DiagnosticCode::RedundantModulePrefix
+            | DiagnosticCode::GuardToPattern
+            | DiagnosticCode::LeakyExportedType
+            | DiagnosticCode::LengthZeroCheck
+            | DiagnosticCode::BinaryStringEncoding
+            | DiagnosticCode::MergeableClauses
+            | DiagnosticCode::AlwaysCrashes
+            | DiagnosticCode::NarrowableSpec
+            | DiagnosticCode::OrphanedSpec
+            | DiagnosticCode::BooleanOperatorsInGuard
+            | DiagnosticCode::ExceptionControlFlow
+            | DiagnosticCode::UncheckedIoResult
+            | DiagnosticCode::RecordInfoUsage
+            | DiagnosticCode::NonBooleanFlag
+            | DiagnosticCode::TimerTcUnit
+            | DiagnosticCode::GuardVariableNotBound
+            | DiagnosticCode::MapPatternOperator
+            | DiagnosticCode::LiteralApply
+            | DiagnosticCode::HighComplexity
+            | DiagnosticCode::RecordElementAccess
+            | DiagnosticCode::ListsNthPerf
+            | DiagnosticCode::RedundantConversion
+            | DiagnosticCode::RedefinedMacro
+            | DiagnosticCode::ErlangService(_)
+            | DiagnosticCode::AdHoc(_) => false,
+            // @fb-only: DiagnosticCode::MetaOnly(_) => false,
+        }
+    }
+
     pub fn maybe_from_string(s: &String) -> Option<DiagnosticCode> {
         if let Some(r) = DIAGNOSTIC_CODE_LOOKUPS.get(s) {
             Some(r.clone())
@@ -386,11 +600,29 @@ impl<F> AdhocSemanticDiagnostics for F where
 {
 }
 
+/// Computes the `metadata` of the diagnostics with a given code, for
+/// tools built on top of ELP.
+pub trait DiagnosticEnricher:
+    Fn(&Diagnostic) -> Option<serde_json::Value> + std::panic::RefUnwindSafe + Sync
+{
+}
+impl<F> DiagnosticEnricher for F where
+    F: Fn(&Diagnostic) -> Option<serde_json::Value> + std::panic::RefUnwindSafe + Sync
+{
+}
+
 #[derive(Default, Clone)]
 pub struct DiagnosticsConfig<'a> {
     pub disable_experimental: bool,
     disabled: FxHashSet<DiagnosticCode>,
+    /// Diagnostics that are off by default, and only run if listed here.
+    enabled: FxHashSet<DiagnosticCode>,
     pub adhoc_semantic_diagnostics: Vec<&'a dyn AdhocSemanticDiagnostics>,
+    enrichers: FxHashMap<DiagnosticCode, &'a dyn DiagnosticEnricher>,
+    /// For `high_complexity`, the default is used if not set.
+    complexity_threshold: Option<u32>,
+    /// Also report unused macros in headers with a single includer.
+    unused_header_macros: bool,
 }
 
 impl<'a> DiagnosticsConfig<'a> {
@@ -402,7 +634,11 @@ impl<'a> DiagnosticsConfig<'a> {
         DiagnosticsConfig {
             disable_experimental,
             disabled,
+            enabled: FxHashSet::default(),
             adhoc_semantic_diagnostics,
+            enrichers: FxHashMap::default(),
+            complexity_threshold: None,
+            unused_header_macros: false,
         }
     }
 
@@ -410,6 +646,40 @@ impl<'a> DiagnosticsConfig<'a> {
         self.disabled.insert(code);
         self
     }
+
+    /// Turn on an opt-in diagnostic.
+    pub fn enable(mut self, code: DiagnosticCode) -> DiagnosticsConfig<'a> {
+        self.enabled.insert(code);
+        self
+    }
+
+    pub fn is_enabled(&self, code: &DiagnosticCode) -> bool {
+        self.enabled.contains(code)
+    }
+
+    /// The cyclomatic complexity above which `high_complexity` reports a
+    /// function.
+    pub fn with_complexity_threshold(mut self, threshold: u32) -> DiagnosticsConfig<'a> {
+        self.complexity_threshold = Some(threshold);
+        self
+    }
+
+    /// Report unused macros in a header too, when the header is included
+    /// by exactly one module, directly or through other headers.
+    pub fn with_unused_header_macros(mut self) -> DiagnosticsConfig<'a> {
+        self.unused_header_macros = true;
+        self
+    }
+
+    /// Set the `metadata` of the diagnostics with this code.
+    pub fn with_enricher(
+        mut self,
+        code: DiagnosticCode,
+        enricher: &'a dyn DiagnosticEnricher,
+    ) -> DiagnosticsConfig<'a> {
+        self.enrichers.insert(code, enricher);
+        self
+    }
 }
 
 pub fn diagnostics(
@@ -455,7 +725,8 @@ pub fn diagnostics(
             .adhoc_semantic_diagnostics
             .iter()
             .for_each(|f| f(&mut res, &sema, file_id, ext));
-        semantic_diagnostics(&mut res, &sema, file_id, ext, config.disable_experimental);
+        semantic_diagnostics(&mut res, &sema, file_id, ext, config);
+        unused_macro::unused_macro(&mut res, db, file_id, ext, config.unused_header_macros);
         syntax_diagnostics(db, &parse, &mut res, file_id);
 
         res.extend(parse.errors().iter().take(128).map(|err| {
@@ -473,7 +744,19 @@ pub fn diagnostics(
             && !d.should_be_ignored(&line_index, &parse.syntax_node())
     });
 
-    res
+    let text = db.file_text(file_id);
+    res.into_iter()
+        .map(|d| {
+            let d = d.with_context(&text);
+            match config.enrichers.get(&d.code) {
+                Some(enricher) => {
+                    let metadata = enricher(&d);
+                    d.with_metadata(metadata)
+                }
+                None => d,
+            }
+        })
+        .collect()
 }
 
 pub fn semantic_diagnostics(
@@ -481,15 +764,14 @@ pub fn semantic_diagnostics(
     sema: &Semantic,
     file_id: FileId,
     ext: Option<&str>,
-    disable_experimental: bool,
+    config: &DiagnosticsConfig,
 ) {
     // TODO: disable this check when T151727890 and T151605845 are resolved
-    if !disable_experimental {
+    if !config.disable_experimental {
         unused_function_args::unused_function_args(res, sema, file_id);
         redundant_assignment::redundant_assignment(res, sema, file_id);
         trivial_match::trivial_match(res, sema, file_id);
     }
-    unused_macro::unused_macro(res, sema, file_id, ext);
     unused_record_field::unused_record_field(res, sema, file_id, ext);
     mutable_variable::mutable_variable_bug(res, sema, file_id);
     effect_free_statement::effect_free_statement(res, sema, file_id);
@@ -497,6 +779,208 @@ pub fn semantic_diagnostics(
     // @fb-only: meta_only::diagnostics(res, sema, file_id);
     missing_compile_warn_missing_spec::missing_compile_warn_missing_spec(res, sema, file_id);
     cross_node_eval::cross_node_eval(res, sema, file_id);
+    accidental_callback::accidental_callback(res, sema, file_id);
+    redundant_catch::redundant_catch(res, sema, file_id);
+    redundant_type_guard::redundant_type_guard(res, sema, file_id);
+    if config.is_enabled(&DiagnosticCode::StalePidSend) {
+        stale_pid_send::stale_pid_send(res, sema, file_id);
+    }
+    if config.is_enabled(&DiagnosticCode::ConstantRecordField) {
+        constant_record_field::constant_record_field(res, sema, file_id);
+    }
+    if config.is_enabled(&DiagnosticCode::PreferExactEquality) {
+        prefer_exact_equality::prefer_exact_equality(res, sema, file_id);
+    }
+    missing_reverse::missing_reverse(res, sema, file_id);
+    if config.is_enabled(&DiagnosticCode::RedundantModulePrefix) {
+        redundant_module_prefix::redundant_module_prefix(res, sema, file_id);
+    }
+    guard_to_pattern::guard_to_pattern(res, sema, file_id);
+    leaky_exported_type::leaky_exported_type(res, sema, file_id);
+    length_zero_check::length_zero_check(res, sema, file_id);
+    binary_string_encoding::binary_string_encoding(
+        res,
+        sema,
+        file_id,
+        config.is_enabled(&DiagnosticCode::BinaryStringEncoding),
+    );
+    mergeable_clauses::mergeable_clauses(res, sema, file_id);
+    always_crashes::always_crashes(res, sema, file_id);
+    if config.is_enabled(&DiagnosticCode::NarrowableSpec) {
+        narrowable_spec::narrowable_spec(res, sema, file_id);
+    }
+    orphaned_spec::orphaned_spec(res, sema, file_id, ext);
+    boolean_operators_in_guard::boolean_operators_in_guard(res, sema, file_id);
+    if config.is_enabled(&DiagnosticCode::ExceptionControlFlow) {
+        exception_control_flow::exception_control_flow(res, sema, file_id);
+    }
+    unchecked_io_result::unchecked_io_result(res, sema, file_id);
+    if config.is_enabled(&DiagnosticCode::RecordInfoUsage) {
+        record_info_usage::record_info_usage(res, sema, file_id);
+    }
+    non_boolean_flag::non_boolean_flag(res, sema, file_id);
+    if config.is_enabled(&DiagnosticCode::TimerTcUnit) {
+        timer_tc_unit::timer_tc_unit(res, sema, file_id);
+    }
+    guard_variable_not_bound::guard_variable_not_bound(res, sema, file_id);
+    map_pattern_operator::map_pattern_operator(res, sema, file_id);
+    literal_apply::literal_apply(res, sema, file_id);
+    if config.is_enabled(&DiagnosticCode::HighComplexity) {
+        let threshold = config
+            .complexity_threshold
+            .unwrap_or(high_complexity::DEFAULT_THRESHOLD);
+        high_complexity::high_complexity(res, sema, file_id, threshold);
+    }
+    record_element_access::record_element_access(res, sema, file_id);
+    if config.is_enabled(&DiagnosticCode::ListsNthPerf) {
+        lists_nth_perf::lists_nth_perf(res, sema, file_id);
+    }
+    redundant_conversion::redundant_conversion(res, sema, file_id);
+    redefined_macro::redefined_macro(res, sema, file_id, ext);
+}
+
+/// Run only the diagnostics whose fixes are safe to apply without
+/// review (see `DiagnosticCode::has_safe_fix`), and return their fixes,
+/// e.g. for applying on save.
+pub fn get_safe_fixes(db: &RootDatabase, file_id: FileId) -> Vec<Assist> {
+    let codes = DiagnosticCode::iter()
+        .filter(|code| code.has_safe_fix())
+        .collect();
+    diagnostics_for_codes(db, file_id, &codes, false)
+        .into_iter()
+        .flat_map(|d| d.fixes.unwrap_or_default())
+        .collect()
+}
+
+/// Run the diagnostic with the given code and apply the fix of each of
+/// its diagnostics to the file in one pass, returning the new file text,
+/// or `None` if there was nothing to fix. See `apply_first_fixes`.
+pub fn apply_fixes_for_code(
+    db: &RootDatabase,
+    file_id: FileId,
+    code: DiagnosticCode,
+) -> Option<String> {
+    let codes = FxHashSet::from_iter([code]);
+    let diagnostics = diagnostics_for_codes(db, file_id, &codes, true);
+    apply_first_fixes(&db.file_text(file_id), file_id, diagnostics)
+}
+
+/// Run only the native diagnostics that can report one of `codes`, and
+/// return those not ignored by a comment. Most are found by
+/// `semantic_diagnostics`, run with `codes` enabled as some are opt-in.
+fn diagnostics_for_codes(
+    db: &RootDatabase,
+    file_id: FileId,
+    codes: &FxHashSet<DiagnosticCode>,
+    include_generated: bool,
+) -> Vec<Diagnostic> {
+    let sema = &Semantic::new(db);
+    let root_id = db.file_source_root(file_id);
+    let root = db.source_root(root_id);
+    let ext = root
+        .path_for_file(&file_id)
+        .and_then(|path| path.name_and_extension())
+        .and_then(|(_name, ext)| ext);
+
+    let mut res = Vec::new();
+    let mut semantic_codes = Vec::new();
+    for code in codes {
+        match code {
+            DiagnosticCode::UnusedInclude => {
+                if ext == Some("erl") && (include_generated || !db.is_generated(file_id)) {
+                    unused_include::unused_includes(sema, db, &mut res, file_id);
+                }
+            }
+            DiagnosticCode::UnusedMacro => {
+                unused_macro::unused_macro(&mut res, db, file_id, ext, false)
+            }
+            DiagnosticCode::UnusedRecordField => {
+                unused_record_field::unused_record_field(&mut res, sema, file_id, ext)
+            }
+            code => semantic_codes.push(code.clone()),
+        }
+    }
+    if !semantic_codes.is_empty() {
+        let config = semantic_codes
+            .into_iter()
+            .fold(DiagnosticsConfig::default(), DiagnosticsConfig::enable);
+        semantic_diagnostics(&mut res, sema, file_id, ext, &config);
+    }
+
+    let line_index = db.file_line_index(file_id);
+    let source = db.parse(file_id).syntax_node();
+    res.retain(|d| codes.contains(&d.code) && !d.should_be_ignored(&line_index, &source));
+    res
+}
+
+/// Apply the first fix of each diagnostic to `text`. The others are
+/// alternatives to it, e.g. to delete or to use an unused definition.
+/// Fixes are applied from the end of the file, and a fix overlapping one
+/// already applied is skipped.
+fn apply_first_fixes(text: &str, file_id: FileId, diagnostics: Vec<Diagnostic>) -> Option<String> {
+    let mut edits: Vec<TextEdit> = diagnostics
+        .into_iter()
+        .filter_map(|d| d.fixes.and_then(|fixes| fixes.into_iter().next()))
+        .filter_map(|fix| fix.source_change?.get_source_edit(file_id).cloned())
+        .filter(|edit| !edit.is_empty())
+        .collect();
+    if edits.is_empty() {
+        return None;
+    }
+
+    // Each fix is applied as a whole, so work on the range covered by all
+    // of its edits.
+    let covered = |edit: &TextEdit| {
+        edit.iter()
+            .map(|indel| indel.delete)
+            .reduce(|acc, range| acc.cover(range))
+            .unwrap_or_default()
+    };
+    edits.sort_by_key(|edit| std::cmp::Reverse(covered(edit).start()));
+
+    let mut text = text.to_string();
+    let mut applied_from: Option<TextSize> = None;
+    for edit in edits {
+        let range = covered(&edit);
+        if applied_from.map_or(false, |start| range.end() > start) {
+            continue;
+        }
+        edit.apply(&mut text);
+        applied_from = Some(range.start());
+    }
+    Some(text)
+}
+
+/// The unused macros, records, record fields, functions and types of the
+/// module, from both the native and the Erlang service diagnostics. The
+/// latter can be reported in an included file, hence the `FileId`.
+pub fn unused_definitions(db: &RootDatabase, file_id: FileId) -> Vec<(FileId, Diagnostic)> {
+    if db.is_generated(file_id) {
+        return vec![];
+    }
+    let codes = FxHashSet::from_iter([
+        DiagnosticCode::UnusedMacro,
+        DiagnosticCode::UnusedRecordField,
+    ]);
+    let native = diagnostics_for_codes(db, file_id, &codes, false)
+        .into_iter()
+        .map(|d| (file_id, d));
+    let erlang_service = erlang_service_diagnostics(db, file_id)
+        .into_iter()
+        .flat_map(|(file_id, diags)| diags.into_iter().map(move |d| (file_id, d)));
+    native
+        .chain(erlang_service)
+        .filter(|(_, d)| is_unused_definition(&d.code))
+        .collect()
+}
+
+fn is_unused_definition(code: &DiagnosticCode) -> bool {
+    match code {
+        DiagnosticCode::UnusedMacro | DiagnosticCode::UnusedRecordField => true,
+        // Unused function, record and type
+        DiagnosticCode::ErlangService(code) => matches!(code.as_str(), "L1230" | "L1260" | "L1296"),
+        _ => false,
+    }
 }
 
 pub fn syntax_diagnostics(
@@ -512,6 +996,27 @@ pub fn syntax_diagnostics(
     }
 }
 
+/// The candidate `name` is most likely a misspelling of: the most
+/// similar of those a few edits away from it, the first one on ties.
+pub(crate) fn closest_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let close_enough: usize = std::cmp::max(1, std::cmp::min(3, name.len() / 3));
+    let mut suggestions: Vec<(&str, f64)> = candidates
+        .into_iter()
+        .filter(|known| {
+            triple_accel::levenshtein::rdamerau(name.as_bytes(), known.as_bytes())
+                <= u32::try_from(close_enough).unwrap()
+        })
+        .map(|known| (known, strsim::jaro_winkler(name, known)))
+        .collect();
+    suggestions.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    suggestions
+        .first()
+        .map(|(suggestion, _similarity)| *suggestion)
+}
+
 pub fn filter_diagnostics(diagnostics: Vec<Diagnostic>, code: DiagnosticCode) -> Vec<Diagnostic> {
     diagnostics.into_iter().filter(|d| d.code == code).collect()
 }
@@ -529,6 +1034,8 @@ fn no_module_definition_diagnostic(
             fixes: None,
             related_info: None,
             code: DiagnosticCode::MissingModule,
+            metadata: None,
+            context: None,
         });
     };
     for form in parse.tree().forms() {
@@ -635,12 +1142,12 @@ fn comment_contains_ignore_code(comment: &str, code: &DiagnosticCode) -> bool {
     match comment.find(pattern) {
         Some(start) => {
             let comment = comment[start.into()..].to_string();
-            comment
-                .split_whitespace()
-                .any(|code_str| match DiagnosticCode::from_str(code_str) {
-                    Ok(code_comment) => *code == code_comment,
-                    Err(_) => false,
-                })
+            // Labels can also be spelled with dashes, e.g. `unused-macro`
+            comment.split_whitespace().any(|code_str| {
+                DiagnosticCode::from_str(code_str)
+                    .or_else(|_| DiagnosticCode::from_str(&code_str.replace('-', "_")))
+                    .map_or(false, |code_comment| *code == code_comment)
+            })
         }
         _ => false,
     }
@@ -691,6 +1198,8 @@ fn make_missing_diagnostic(range: TextRange, item: &'static str, code: String) -
         fixes: None,
         related_info: None,
         code: DiagnosticCode::Missing(code),
+        metadata: None,
+        context: None,
     }
 }
 
@@ -771,6 +1280,7 @@ pub fn erlang_service_diagnostics(
     } else {
         let mut diags_map: FxHashMap<FileId, Vec<Diagnostic>> = FxHashMap::default();
         diags.into_iter().for_each(|(file_id, diag)| {
+            let diag = diag.with_context(&db.file_text(file_id));
             diags_map
                 .entry(file_id)
                 .and_modify(|existing| existing.push(diag.clone()))
@@ -1044,6 +1554,7 @@ fn location_range(location: Location, line_index: &LineIndex) -> TextRange {
 // cargo test --package elp_ide --lib
 #[cfg(test)]
 mod tests {
+    use elp_ide_db::elp_base_db::fixture::WithFixture;
     use elp_syntax::ast;
     use expect_test::expect;
 
@@ -1232,6 +1743,7 @@ baz(1)->4.
         let mut config = DiagnosticsConfig {
             disable_experimental: false,
             disabled: FxHashSet::default(),
+            enabled: FxHashSet::default(),
             adhoc_semantic_diagnostics: vec![&|acc, sema, file_id, _ext| {
                 replace_call::replace_call_site(
                     &FunctionMatch::MFA(MFA {
@@ -1351,4 +1863,132 @@ baz(1)->4.
         "#]]
         .assert_debug_eq(&codes);
     }
+
+    #[test]
+    fn safe_fixes_only() {
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(main).
+-define(UNUSED, 1).
+foo() ->
+    42,
+    ok.
+"#,
+        );
+        let fixes: Vec<&str> = get_safe_fixes(&db, file_id)
+            .iter()
+            .map(|fix| fix.id.0)
+            .collect();
+        assert_eq!(fixes, vec!["delete_unused_macro"]);
+    }
+
+    #[test]
+    fn apply_all_fixes_for_code() {
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(main).
+-define(UNUSED_A, 1).
+-define(USED, 2).
+-define(UNUSED_B, 3).
+foo() -> ?USED.
+"#,
+        );
+        let text = apply_fixes_for_code(&db, file_id, DiagnosticCode::UnusedMacro);
+        assert_eq!(
+            text.as_deref(),
+            Some(
+                r#"
+-module(main).
+-define(USED, 2).
+foo() -> ?USED.
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn apply_only_first_fix_of_each_diagnostic() {
+        let text = "foo() -> a.\nbar() -> b.\n";
+        let file_id = FileId(0);
+        // Each diagnostic is on one character, and its fixes replace it
+        let diagnostic = |offset: u32, replacements: &[&str]| {
+            let range = TextRange::at(offset.into(), 1.into());
+            let fixes = replacements
+                .iter()
+                .map(|with| {
+                    let edit = TextEdit::replace(range, with.to_string());
+                    let change = SourceChange::from_text_edit(file_id, edit);
+                    crate::fix("replace", "Replace", change, range)
+                })
+                .collect();
+            Diagnostic::warning(DiagnosticCode::UnusedMacro, range, "unused".to_string())
+                .with_fixes(Some(fixes))
+        };
+        let diagnostics = vec![diagnostic(9, &["x", "y"]), diagnostic(21, &["z"])];
+        assert_eq!(
+            apply_first_fixes(text, file_id, diagnostics).as_deref(),
+            Some("foo() -> x.\nbar() -> z.\n")
+        );
+    }
+
+    #[test]
+    fn fingerprint_ignores_position() {
+        let fingerprints = |fixture: &str| -> Vec<u64> {
+            let (db, file_id) = RootDatabase::with_single_file(fixture);
+            let config =
+                DiagnosticsConfig::default().disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+            diagnostics(&db, &config, file_id, true)
+                .iter()
+                .map(|d| d.fingerprint())
+                .collect()
+        };
+        let before = fingerprints(
+            r#"
+-module(main).
+-define(UNUSED, 1).
+"#,
+        );
+        let moved = fingerprints(
+            r#"
+-module(main).
+
+-define(UNUSED,  1).
+"#,
+        );
+        let changed = fingerprints(
+            r#"
+-module(main).
+-define(UNUSED, 2).
+"#,
+        );
+        assert_eq!(before.len(), 1);
+        assert_eq!(before, moved);
+        assert_ne!(before, changed);
+    }
+
+    #[test]
+    fn enricher_sets_metadata_for_its_code() {
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+-module(main).
+-define(UNUSED, 1).
+"#,
+        );
+        let owner = |_: &Diagnostic| Some(serde_json::json!({"owner": "macros"}));
+        let config = DiagnosticsConfig::default()
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec)
+            .with_enricher(DiagnosticCode::UnusedMacro, &owner);
+        let metadata: Vec<(String, Option<serde_json::Value>)> =
+            diagnostics(&db, &config, file_id, true)
+                .into_iter()
+                .map(|d| (d.code.as_label(), d.metadata))
+                .collect();
+        assert_eq!(
+            metadata,
+            vec![(
+                "unused_macro".to_string(),
+                Some(serde_json::json!({"owner": "macros"}))
+            )]
+        );
+    }
 }
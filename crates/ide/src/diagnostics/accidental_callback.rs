@@ -0,0 +1,147 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: accidental_callback
+//!
+//! Return a hint if a local function has the name and arity of an
+//! optional callback of a behaviour declared by the module, but is not
+//! exported. The behaviour will never call it.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::FunctionDef;
+use hir::Semantic;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+pub(crate) fn accidental_callback(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    let def_map = sema.def_map(file_id);
+    let form_list = sema.db.file_form_list(file_id);
+    for (_idx, behaviour) in form_list.behaviour_attributes() {
+        let module = match sema.resolve_module_name(file_id, behaviour.name.as_str()) {
+            Some(module) => module,
+            None => continue,
+        };
+        let behaviour_def_map = sema.def_map(module.file.file_id);
+        for (name, _callback) in behaviour_def_map.get_callbacks() {
+            if !behaviour_def_map.is_callback_optional(name) {
+                continue;
+            }
+            if let Some(def) = def_map.get_function(name) {
+                if def.file.file_id == file_id && !def.exported {
+                    if let Some(range) = function_name_range(sema, def) {
+                        diags.push(make_diagnostic(
+                            range,
+                            &name.to_string(),
+                            behaviour.name.as_str(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn function_name_range(sema: &Semantic, def: &FunctionDef) -> Option<TextRange> {
+    let fun_decl = def.source(sema.db.upcast());
+    fun_decl.clauses().find_map(|clause| match clause {
+        ast::FunctionOrMacroClause::FunctionClause(clause) => {
+            Some(clause.name()?.syntax().text_range())
+        }
+        ast::FunctionOrMacroClause::MacroCallExpr(_) => None,
+    })
+}
+
+fn make_diagnostic(range: TextRange, name: &str, behaviour: &str) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::AccidentalCallback,
+        format!(
+            "Function `{name}` matches an optional callback of behaviour `{behaviour}`, but is not exported"
+        ),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn unexported_optional_callback() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+-module(main).
+-behaviour(my_behaviour).
+-export([init/1]).
+
+init(Args) -> Args.
+
+terminate(_Reason, _State) -> ok.
+%% ^^^^^^^^^ weak: Function `terminate/2` matches an optional callback of behaviour `my_behaviour`, but is not exported
+
+//- /src/my_behaviour.erl
+-module(my_behaviour).
+-callback init(term()) -> term().
+-callback terminate(term(), term()) -> ok.
+-optional_callbacks([terminate/2]).
+            "#,
+        );
+    }
+
+    #[test]
+    fn exported_optional_callback() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+-module(main).
+-behaviour(my_behaviour).
+-export([init/1, terminate/2]).
+
+init(Args) -> Args.
+
+terminate(_Reason, _State) -> ok.
+
+//- /src/my_behaviour.erl
+-module(my_behaviour).
+-callback init(term()) -> term().
+-callback terminate(term(), term()) -> ok.
+-optional_callbacks([terminate/2]).
+            "#,
+        );
+    }
+
+    #[test]
+    fn different_arity_not_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+-module(main).
+-behaviour(my_behaviour).
+-export([init/1]).
+
+init(Args) -> terminate(Args).
+
+terminate(_State) -> ok.
+
+//- /src/my_behaviour.erl
+-module(my_behaviour).
+-callback init(term()) -> term().
+-callback terminate(term(), term()) -> ok.
+-optional_callbacks([terminate/2]).
+            "#,
+        );
+    }
+}
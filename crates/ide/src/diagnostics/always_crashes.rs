@@ -0,0 +1,186 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: always_crashes
+//!
+//! Return a hint if every clause of a function only calls `error/1,2,3`
+//! or `exit/1`, e.g. `foo() -> erlang:error(not_implemented).`. This is
+//! usually a stub, like the ones generated by the `implement_behaviour`
+//! assist, that was never filled in. A comment containing `TODO` right
+//! before the function, or inside it, marks the stub as known.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::Direction;
+use elp_syntax::SyntaxKind;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::NameArity;
+use hir::Semantic;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+pub(crate) fn always_crashes(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(name, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, name, def)
+            }
+        });
+}
+
+fn process_function(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    name: &NameArity,
+    def: &FunctionDef,
+) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let mut clauses = def_fb.clauses().peekable();
+    if clauses.peek().is_none() {
+        return;
+    }
+    let only_crashes = clauses.all(|(_clause_id, clause)| match clause.exprs.as_slice() {
+        [expr] => is_crash(sema, &def_fb, *expr),
+        _ => false,
+    });
+    if !only_crashes {
+        return;
+    }
+    let fun_decl = def.source(sema.db.upcast());
+    if has_todo_comment(&fun_decl) {
+        return;
+    }
+    if let Some(range) = name_range(&fun_decl) {
+        diags.push(
+            Diagnostic::new(
+                DiagnosticCode::AlwaysCrashes,
+                format!("Function `{name}` always crashes, it may be an unimplemented stub"),
+                range,
+            )
+            .severity(Severity::WeakWarning),
+        );
+    }
+}
+
+/// Match a call to `error/1,2,3` or `exit/1`, local or in `erlang`.
+fn is_crash(sema: &Semantic, def_fb: &InFunctionBody<&FunctionDef>, expr_id: ExprId) -> bool {
+    match &def_fb[expr_id] {
+        Expr::Call { target, args } => {
+            let name = match target {
+                CallTarget::Local { name } => def_fb.as_atom_name(sema.db, name),
+                CallTarget::Remote { module, name } => match def_fb.as_atom_name(sema.db, module) {
+                    Some(module) if module.as_str() == "erlang" => {
+                        def_fb.as_atom_name(sema.db, name)
+                    }
+                    _ => None,
+                },
+            };
+            match name {
+                Some(name) => matches!((name.as_str(), args.len()), ("error", 1..=3) | ("exit", 1)),
+                None => false,
+            }
+        }
+        Expr::MacroCall { expansion, .. } => is_crash(sema, def_fb, *expansion),
+        _ => false,
+    }
+}
+
+fn has_todo_comment(fun_decl: &ast::FunDecl) -> bool {
+    let is_todo =
+        |kind: SyntaxKind, text: String| kind == SyntaxKind::COMMENT && text.contains("TODO");
+    let before = fun_decl
+        .syntax()
+        .siblings_with_tokens(Direction::Prev)
+        .skip(1) // starts with self
+        .find(|node| node.kind() != SyntaxKind::WHITESPACE)
+        .map_or(false, |node| is_todo(node.kind(), node.to_string()));
+    before
+        || fun_decl
+            .syntax()
+            .descendants()
+            .any(|node| is_todo(node.kind(), node.text().to_string()))
+}
+
+fn name_range(fun_decl: &ast::FunDecl) -> Option<TextRange> {
+    fun_decl.clauses().find_map(|clause| match clause {
+        ast::FunctionOrMacroClause::FunctionClause(clause) => {
+            Some(clause.name()?.syntax().text_range())
+        }
+        ast::FunctionOrMacroClause::MacroCallExpr(_) => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn functions_only_crashing() {
+        check_diagnostics(
+            r#"
+    -module(main).
+    -export([foo/1, bar/0, baz/0]).
+
+    foo(a) -> erlang:error(not_implemented);
+ %% ^^^ weak: Function `foo/1` always crashes, it may be an unimplemented stub
+    foo(_) -> exit(badarg).
+
+    bar() -> error(not_implemented, []).
+ %% ^^^ weak: Function `bar/0` always crashes, it may be an unimplemented stub
+
+    baz() -> throw(not_implemented).
+            "#,
+        );
+    }
+
+    #[test]
+    fn functions_not_only_crashing() {
+        check_diagnostics(
+            r#"
+    -module(main).
+    -export([foo/1, bar/0]).
+
+    foo(a) -> erlang:error(not_implemented);
+    foo(_) -> ok.
+
+    bar() ->
+        io:format("stop"),
+        erlang:exit(stop).
+            "#,
+        );
+    }
+
+    #[test]
+    fn todo_comment_suppresses() {
+        check_diagnostics(
+            r#"
+    -module(main).
+    -export([foo/0, bar/0]).
+
+    %% TODO: implement once the API is settled
+    foo() -> erlang:error(not_implemented).
+
+    bar() ->
+        %% TODO: abstract, overridden by the parse transform
+        erlang:error(abstract).
+            "#,
+        );
+    }
+}
@@ -0,0 +1,173 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: binary_string_encoding
+//!
+//! Return a hint if a string literal with non-ASCII characters is put in
+//! a binary without a type, e.g. `<<"café">>`. Each character is then
+//! stored as a byte, silently truncating anything outside Latin-1, and
+//! offer to add `/utf8`. When the diagnostic is explicitly enabled, all
+//! string literal segments without a type are reported.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::Literal;
+use hir::Semantic;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn binary_string_encoding(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+    all_strings: bool,
+) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def, all_strings)
+            }
+        });
+}
+
+fn process_function(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    def: &FunctionDef,
+    all_strings: bool,
+) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+
+    def_fb.fold_function(
+        (),
+        &mut |_acc, _, ctx| {
+            let segs = match ctx.expr {
+                Expr::Binary { segs } => segs,
+                _ => return,
+            };
+            for seg in segs {
+                if !seg.tys.is_empty() || seg.size.is_some() || seg.unit.is_some() {
+                    continue;
+                }
+                let is_ascii = match &def_fb[seg.elem] {
+                    Expr::Literal(Literal::String(string)) => string.is_ascii(),
+                    _ => continue,
+                };
+                if is_ascii && !all_strings {
+                    continue;
+                }
+                // Only literals written in this segment, not coming from
+                // a macro expansion.
+                let literal = match body_map
+                    .expr(seg.elem)
+                    .and_then(|ptr| ptr.to_node(&source_file))
+                {
+                    Some(literal) if literal.syntax().text().char_at(0.into()) == Some('"') => {
+                        literal
+                    }
+                    _ => continue,
+                };
+                diags.push(make_diagnostic(
+                    def.file.file_id,
+                    literal.syntax().text_range(),
+                ));
+            }
+        },
+        &mut |_acc, _, _| (),
+    );
+}
+
+fn make_diagnostic(file_id: FileId, range: TextRange) -> Diagnostic {
+    let mut edit_builder = TextEdit::builder();
+    edit_builder.insert(range.end(), "/utf8".to_string());
+    let edit = edit_builder.finish();
+    Diagnostic::new(
+        DiagnosticCode::BinaryStringEncoding,
+        "String in binary without an encoding, characters outside Latin-1 are truncated"
+            .to_string(),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+    .with_fixes(Some(vec![fix(
+        "add_utf8_encoding",
+        "Add `/utf8`",
+        SourceChange::from_text_edit(file_id, edit),
+        range,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_diagnostics_with_config;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn non_ascii_string_without_encoding() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/0]).
+
+foo() -> {<<"café">>, <<"tea">>, <<"thé"/utf8>>, <<"naïve"/binary>>}.
+%%          ^^^^^^^ 💡 weak: String in binary without an encoding, characters outside Latin-1 are truncated
+            "#,
+        );
+    }
+
+    #[test]
+    fn all_strings_when_enabled() {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::BinaryStringEncoding)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(
+            config,
+            r#"
+-module(main).
+-export([foo/0]).
+
+foo() -> {<<"tea">>, <<"tea"/utf8>>}.
+%%          ^^^^^ 💡 weak: String in binary without an encoding, characters outside Latin-1 are truncated
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_adds_utf8() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/0]).
+
+foo() -> <<"ca~fé", 0>>.
+            "#,
+            r#"
+-module(main).
+-export([foo/0]).
+
+foo() -> <<"café"/utf8, 0>>.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,282 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: boolean_operators_in_guard
+//!
+//! Return a hint if `and` or `or` is used in a guard, e.g.
+//! `when is_list(X) and (hd(X) =:= ok)`. Both sides are always
+//! evaluated, so a test meant to protect the other side does not, and
+//! offer to switch to `andalso` or `orelse`, which short-circuit. These
+//! bind looser than comparisons and arithmetic, so the fix parenthesises
+//! an operation nested in another, e.g. `X == 1 and Y` becomes
+//! `X == (1 andalso Y)`.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::LogicOp;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::Semantic;
+use hir::Strategy;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn boolean_operators_in_guard(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+
+    // Guards of the function clauses, and of the case, receive, try, if
+    // and fun clauses in their bodies.
+    let mut guards: Vec<ExprId> = def_fb
+        .clauses()
+        .flat_map(|(_clause_id, clause)| clause.guards.iter().flatten().copied())
+        .collect();
+    def_fb.fold_function(
+        (),
+        &mut |(), _, ctx| match ctx.expr {
+            Expr::Case { clauses, .. } | Expr::Receive { clauses, .. } => {
+                guards.extend(clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+            }
+            Expr::Try {
+                of_clauses,
+                catch_clauses,
+                ..
+            } => {
+                guards.extend(of_clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+                guards.extend(catch_clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+            }
+            Expr::If { clauses } => {
+                guards.extend(clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+            }
+            Expr::Closure { clauses, .. } => {
+                guards.extend(clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+            }
+            _ => {}
+        },
+        &mut |(), _, _| (),
+    );
+
+    for guard in guards {
+        def_fb.fold_expr(
+            Strategy::TopDown,
+            guard,
+            (),
+            &mut |(), ctx| {
+                let (op, lazy_op) = match ctx.expr {
+                    Expr::BinaryOp {
+                        op: BinaryOp::LogicOp(LogicOp::And { lazy: false }),
+                        ..
+                    } => ("and", "andalso"),
+                    Expr::BinaryOp {
+                        op: BinaryOp::LogicOp(LogicOp::Or { lazy: false }),
+                        ..
+                    } => ("or", "orelse"),
+                    _ => return,
+                };
+                let range = match def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                    Some(range) => range,
+                    None => return,
+                };
+                let op_range = body_map
+                    .expr(ctx.expr_id)
+                    .and_then(|ptr| ptr.to_node(&source_file))
+                    .and_then(|expr| match expr {
+                        ast::Expr::BinaryOpExpr(expr) => {
+                            let nested = expr.syntax().parent().map_or(false, |parent| {
+                                ast::BinaryOpExpr::can_cast(parent.kind())
+                                    || ast::UnaryOpExpr::can_cast(parent.kind())
+                            });
+                            Some((expr.op()?.1.text_range(), nested))
+                        }
+                        _ => None,
+                    });
+                diags.push(make_diagnostic(
+                    def.file.file_id,
+                    range,
+                    op_range,
+                    op,
+                    lazy_op,
+                ));
+            },
+            &mut |(), _| (),
+        );
+    }
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    op_range: Option<(TextRange, bool)>,
+    op: &str,
+    lazy_op: &str,
+) -> Diagnostic {
+    let diag = Diagnostic::new(
+        DiagnosticCode::BooleanOperatorsInGuard,
+        format!(
+            "Prefer `{lazy_op}` to `{op}` in guards, it does not evaluate the right side needlessly"
+        ),
+        range,
+    )
+    .severity(Severity::WeakWarning);
+
+    match op_range {
+        Some((op_range, nested)) => {
+            let mut edit_builder = TextEdit::builder();
+            if nested {
+                edit_builder.insert(range.start(), "(".to_string());
+            }
+            edit_builder.replace(op_range, lazy_op.to_string());
+            if nested {
+                edit_builder.insert(range.end(), ")".to_string());
+            }
+            let edit = edit_builder.finish();
+            diag.with_fixes(Some(vec![fix(
+                "use_short_circuit_operator",
+                &format!("Use `{lazy_op}`"),
+                SourceChange::from_text_edit(file_id, edit),
+                range,
+            )]))
+        }
+        None => diag,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn operators_in_guards() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(X) when is_list(X) and (hd(X) =:= ok) -> X;
+%%          ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: Prefer `andalso` to `and` in guards, it does not evaluate the right side needlessly
+foo(X) -> X.
+
+bar(X) ->
+    case X of
+        Y when (Y =:= a) or (Y =:= b) -> Y;
+%%             ^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: Prefer `orelse` to `or` in guards, it does not evaluate the right side needlessly
+        _ -> other
+    end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn short_circuit_or_body_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/2]).
+
+foo(X) when is_list(X) andalso hd(X) =:= ok; X =:= a orelse X =:= b -> X.
+
+bar(X, Y) -> X and Y.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_operator() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(X) when is_atom(X) a~nd (X =/= ok) -> X.
+            "#,
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(X) when is_atom(X) andalso (X =/= ok) -> X.
+            "#,
+        );
+    }
+    #[test]
+    fn fix_operator_in_comparison() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(X, Y) when X == 1 a~nd Y -> X.
+            "#,
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(X, Y) when X == (1 andalso Y) -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_mixed_operators() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/3]).
+
+foo(X, Y, Z) when X or Y a~nd Z -> X.
+            "#,
+            r#"
+-module(main).
+-export([foo/3]).
+
+foo(X, Y, Z) when X or (Y andalso Z) -> X.
+            "#,
+        );
+        check_fix(
+            r#"
+-module(main).
+-export([foo/3]).
+
+foo(X, Y, Z) when X o~r Y andalso Z -> X.
+            "#,
+            r#"
+-module(main).
+-export([foo/3]).
+
+foo(X, Y, Z) when (X orelse Y) andalso Z -> X.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,298 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: constant_record_field
+//!
+//! Return a hint if a record field is set to the same literal value at
+//! every construction and update of the record in the project. The field
+//! could be replaced by a macro or removed.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::SymbolDefinition;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use fxhash::FxHashMap;
+use fxhash::FxHashSet;
+use hir::Expr;
+use hir::Literal;
+use hir::Name;
+use hir::RecordDef;
+use hir::Semantic;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+/// The value a field gets across the constructions seen so far.
+enum FieldValue {
+    /// Always the literal, with the source text of its first occurrence.
+    Constant(Literal, String),
+    Varies,
+}
+
+pub(crate) fn constant_record_field(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    let def_map = sema.def_map(file_id);
+    for (name, def) in def_map.get_records() {
+        // Only run the check for records defined in this file, not in
+        // the included ones.
+        if def.file.file_id == file_id {
+            process_record(diags, sema, file_id, name, def);
+        }
+    }
+}
+
+fn process_record(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+    name: &Name,
+    def: &RecordDef,
+) -> Option<()> {
+    let mut files: FxHashSet<FileId> = FxHashSet::default();
+    files.insert(file_id);
+    files.extend(
+        SymbolDefinition::Record(def.clone())
+            .usages(sema)
+            .all()
+            .into_iter()
+            .map(|(file_id, _refs)| file_id),
+    );
+
+    // A record from a header used by other applications is part of
+    // their interface too, we cannot see how they construct it.
+    let app = sema.db.file_app_name(file_id);
+    if files
+        .iter()
+        .any(|other| sema.db.file_app_name(*other) != app)
+    {
+        return None;
+    }
+
+    let mut assignments = 0;
+    let mut values: FxHashMap<Name, FieldValue> = FxHashMap::default();
+    for other in files {
+        let source_file = sema.parse(other);
+        for (_arity, fun_def) in sema.def_map(other).get_functions() {
+            if fun_def.file.file_id != other {
+                continue;
+            }
+            let def_fb = fun_def.in_function_body(sema.db, fun_def);
+            let body_map = def_fb.get_body_map(sema.db);
+            def_fb.fold_function(
+                (),
+                &mut |_acc, _, ctx| {
+                    let (fields, update) = match ctx.expr {
+                        Expr::Record { name: rec, fields } if &sema.db.lookup_atom(rec) == name => {
+                            (fields, false)
+                        }
+                        Expr::RecordUpdate {
+                            name: rec, fields, ..
+                        } if &sema.db.lookup_atom(rec) == name => (fields, true),
+                        _ => return,
+                    };
+                    assignments += 1;
+                    for field_name in def.field_names(sema.db) {
+                        let field = fields
+                            .iter()
+                            .find(|(field, _)| sema.db.lookup_atom(*field) == field_name);
+                        // An update keeps the fields it does not set.
+                        if update && field.is_none() {
+                            continue;
+                        }
+                        let value = field.and_then(|(_, value)| match &def_fb[*value] {
+                            Expr::Literal(literal) => {
+                                let text = body_map
+                                    .expr(*value)
+                                    .and_then(|ptr| ptr.to_node(&source_file))?
+                                    .syntax()
+                                    .text()
+                                    .to_string();
+                                Some((literal.clone(), text))
+                            }
+                            _ => None,
+                        });
+                        // A field left out takes its default, which is
+                        // not set at the construction, so does not count.
+                        let entry = values.entry(field_name).or_insert_with(|| match &value {
+                            Some((literal, text)) => {
+                                FieldValue::Constant(literal.clone(), text.clone())
+                            }
+                            None => FieldValue::Varies,
+                        });
+                        if let FieldValue::Constant(literal, _) = &*entry {
+                            if value.as_ref().map(|(value, _)| value) != Some(literal) {
+                                *entry = FieldValue::Varies;
+                            }
+                        }
+                    }
+                },
+                &mut |_acc, _, _| (),
+            );
+        }
+    }
+
+    // A single assignment says nothing about the field being constant.
+    if assignments < 2 {
+        return None;
+    }
+
+    for (field_name, field_def) in def.fields(sema.db) {
+        if let Some(FieldValue::Constant(_, text)) = values.get(&field_name) {
+            let range = field_def.source(sema.db.upcast()).syntax().text_range();
+            diags.push(make_diagnostic(
+                range,
+                &format!("{name}.{field_name}"),
+                text,
+            ));
+        }
+    }
+    Some(())
+}
+
+fn make_diagnostic(range: TextRange, name: &str, value: &str) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::ConstantRecordField,
+        format!(
+            "Record field ({name}) is always set to `{value}`, consider a macro or removing it"
+        ),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::ConstantRecordField)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[test]
+    fn field_always_same_literal() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([a/1, b/1]).
+
+-record(conn, {host, port}).
+                  %% ^^^^ weak: Record field (conn.port) is always set to `8080`, consider a macro or removing it
+
+a(H) -> #conn{host = H, port = 8080}.
+b(H) -> #conn{host = H, port = 8080}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn field_differs_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([a/1, b/1]).
+
+-record(conn, {host, port}).
+
+a(H) -> #conn{host = H, port = 8080}.
+b(H) -> #conn{host = H, port = 443}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn field_defaulted_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([a/1, b/1]).
+
+-record(conn, {host, port = 8080}).
+
+a(H) -> #conn{host = H, port = 8080}.
+b(H) -> #conn{host = H}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn field_updated_to_other_literal_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([a/1, b/1, c/1]).
+
+-record(conn, {host, port}).
+
+a(H) -> #conn{host = H, port = 8080}.
+b(H) -> #conn{host = H, port = 8080}.
+c(C) -> C#conn{port = 443}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn field_kept_by_update() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([a/1, b/1, c/1]).
+
+-record(conn, {host, port}).
+                  %% ^^^^ weak: Record field (conn.port) is always set to `8080`, consider a macro or removing it
+
+a(H) -> #conn{host = H, port = 8080}.
+b(C) -> C#conn{port = 8080}.
+c(C) -> C#conn{host = localhost}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn single_construction_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([a/1]).
+
+-record(conn, {host, port}).
+
+a(H) -> #conn{host = H, port = 8080}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn header_used_by_other_app_not_reported() {
+        check_diagnostics(
+            r#"
+//- /app_a/include/conn.hrl app:app_a
+-record(conn, {host, port}).
+
+//- /app_a/src/a.erl app:app_a
+-module(a).
+-include_lib("app_a/include/conn.hrl").
+-export([a/1]).
+a(H) -> #conn{host = H, port = 8080}.
+
+//- /app_b/src/b.erl app:app_b
+-module(b).
+-include_lib("app_a/include/conn.hrl").
+-export([b/1]).
+b(H) -> #conn{host = H, port = 8080}.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,220 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: exception_control_flow
+//!
+//! Return a hint if a literal is thrown inside a `try` that catches it
+//! by that very literal, e.g. `try throw(done) catch throw:done -> ok end`.
+//! Using exceptions for normal control flow is slow and hides the intent,
+//! a `case` or an early return is usually clearer. This is opt-in, as
+//! the pattern is occasionally justified, e.g. to leave a deep fold.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use hir::CallTarget;
+use hir::CatchClause;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Pat;
+use hir::Semantic;
+use hir::Strategy;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+pub(crate) fn exception_control_flow(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    def_fb.fold_function(
+        (),
+        &mut |(), _, ctx| {
+            if let Expr::Try {
+                exprs,
+                catch_clauses,
+                ..
+            } = ctx.expr
+            {
+                for expr in exprs {
+                    check_throws(diags, sema, &def_fb, expr, &catch_clauses);
+                }
+            }
+        },
+        &mut |(), _, _| (),
+    );
+}
+
+/// Report the throws of a literal under `expr` that one of the catch
+/// clauses matches by that literal.
+fn check_throws(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    expr: ExprId,
+    catch_clauses: &[CatchClause],
+) {
+    def_fb.fold_expr(
+        Strategy::TopDown,
+        expr,
+        (),
+        &mut |(), ctx| {
+            let literal = match thrown_literal(sema, def_fb, &ctx.expr) {
+                Some(literal) => literal,
+                None => return,
+            };
+            let caught = catch_clauses
+                .iter()
+                .any(|clause| catches_literal(sema, def_fb, clause, literal));
+            if !caught {
+                return;
+            }
+            if let Some(range) = def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                diags.push(
+                    Diagnostic::new(
+                        DiagnosticCode::ExceptionControlFlow,
+                        "Exception thrown to be caught by the enclosing `try`, consider a `case` or an early return"
+                            .to_string(),
+                        range,
+                    )
+                    .severity(Severity::WeakWarning),
+                );
+            }
+        },
+        &mut |(), _| (),
+    );
+}
+
+/// The literal thrown by `throw/1` or `erlang:throw/1`.
+fn thrown_literal<'a>(
+    sema: &Semantic,
+    def_fb: &'a InFunctionBody<&FunctionDef>,
+    expr: &Expr,
+) -> Option<&'a Literal> {
+    let (target, args) = match expr {
+        Expr::Call { target, args } => (target, args),
+        _ => return None,
+    };
+    let name = match target {
+        CallTarget::Local { name } => def_fb.as_atom_name(sema.db, name)?,
+        CallTarget::Remote { module, name } => {
+            if def_fb.as_atom_name(sema.db, module)?.as_str() != "erlang" {
+                return None;
+            }
+            def_fb.as_atom_name(sema.db, name)?
+        }
+    };
+    match args.as_slice() {
+        [arg] if name.as_str() == "throw" => match &def_fb[*arg] {
+            Expr::Literal(literal) => Some(literal),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A `throw:Literal` clause, or just `Literal` as `throw` is the default
+/// class.
+fn catches_literal(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    clause: &CatchClause,
+    literal: &Literal,
+) -> bool {
+    let class_is_throw = match clause.class {
+        None => true,
+        Some(class) => match &def_fb[class] {
+            Pat::Literal(Literal::Atom(atom)) => sema.db.lookup_atom(*atom).as_str() == "throw",
+            _ => false,
+        },
+    };
+    class_is_throw
+        && clause.guards.is_empty()
+        && matches!(&def_fb[clause.reason], Pat::Literal(reason) if reason == literal)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::ExceptionControlFlow)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[test]
+    fn throw_caught_by_same_try() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(L) ->
+    try
+        lists:foreach(fun(X) when X > 3 -> throw(done); (_) -> ok end, L),
+%%                                         ^^^^^^^^^^^ weak: Exception thrown to be caught by the enclosing `try`, consider a `case` or an early return
+        not_found
+    catch
+        throw:done -> found
+    end.
+
+bar(X) ->
+    try erlang:throw(42)
+%%      ^^^^^^^^^^^^^^^^ weak: Exception thrown to be caught by the enclosing `try`, consider a `case` or an early return
+    catch
+        42 -> X
+    end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn other_exceptions_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/0]).
+
+foo(X) ->
+    try throw({error, X})
+    catch
+        throw:{error, Y} -> Y
+    end.
+
+bar() ->
+    try error(done)
+    catch
+        throw:done -> ok;
+        error:other -> error
+    end.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,446 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: guard_to_pattern
+//!
+//! Return a hint if the guard of a function clause checks the shape of
+//! an argument, e.g. `f(X) when is_tuple(X), element(1, X) =:= ok`, where
+//! a pattern in the clause head would do. When the guard also fixes the
+//! tuple size, offer to move the tests into the pattern, e.g. `f({ok, _})`.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::CompOp;
+use elp_syntax::AstNode;
+use elp_syntax::SourceFile;
+use elp_syntax::TextRange;
+use elp_syntax::TextSize;
+use fxhash::FxHashMap;
+use hir::BodySourceMap;
+use hir::CallTarget;
+use hir::Clause;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Pat;
+use hir::Semantic;
+use hir::Strategy;
+use hir::Var;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+/// A guard test on the structure of a tuple.
+#[derive(Debug, Clone, Copy)]
+enum TupleTest {
+    /// `is_tuple(X)`
+    IsTuple,
+    /// `tuple_size(X) =:= N`
+    Size(usize),
+    /// `element(K, X) =:= Literal`
+    Element(usize, ExprId),
+}
+
+pub(crate) fn guard_to_pattern(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_clauses(diags, sema, def)
+            }
+        });
+}
+
+fn process_clauses(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+
+    for (_clause_id, clause) in def_fb.clauses() {
+        // With `;` each alternative could test a different shape.
+        let tests = match clause.guards.as_slice() {
+            [tests] => tests,
+            _ => continue,
+        };
+
+        let mut by_var: FxHashMap<Var, Vec<(usize, TupleTest)>> = FxHashMap::default();
+        for (idx, test) in tests.iter().enumerate() {
+            if let Some((var, tuple_test)) = tuple_test(sema, &def_fb, *test) {
+                by_var.entry(var).or_default().push((idx, tuple_test));
+            }
+        }
+
+        for (pat_idx, pat_id) in clause.pats.iter().enumerate() {
+            let var = match &def_fb[*pat_id] {
+                Pat::Var(var) => *var,
+                _ => continue,
+            };
+            let var_tests = match by_var.get(&var) {
+                Some(var_tests) => var_tests,
+                None => continue,
+            };
+            let is_tuple = var_tests
+                .iter()
+                .find(|(_, test)| matches!(test, TupleTest::IsTuple));
+            let has_element = var_tests
+                .iter()
+                .any(|(_, test)| matches!(test, TupleTest::Element(..)));
+            let is_tuple_idx = match is_tuple {
+                Some((idx, _)) if has_element => *idx,
+                _ => continue,
+            };
+            let range = match def_fb.range_for_expr(sema.db, tests[is_tuple_idx]) {
+                Some(range) => range,
+                None => continue,
+            };
+            let var_name = var.as_string(sema.db.upcast());
+            let pattern = tuple_pattern(&def_fb, &body_map, &source_file, var_tests);
+            let edit = pattern.and_then(|pattern| {
+                let pat_range = body_map
+                    .pat(*pat_id)?
+                    .to_node(&source_file)?
+                    .syntax()
+                    .text_range();
+                let consumed: Vec<usize> = var_tests.iter().map(|(idx, _)| *idx).collect();
+                let used = var_used(&def_fb, clause, pat_idx, tests, &consumed, var);
+                let pattern = if used {
+                    format!("{pattern} = {var_name}")
+                } else {
+                    pattern
+                };
+                let guard_edit = guard_edit(&body_map, &source_file, tests, &consumed)?;
+                let mut edit_builder = TextEdit::builder();
+                edit_builder.replace(pat_range, pattern.clone());
+                match guard_edit {
+                    (guard_range, Some(remaining)) => edit_builder.replace(guard_range, remaining),
+                    (guard_range, None) => edit_builder.delete(guard_range),
+                }
+                Some((pattern, edit_builder.finish()))
+            });
+            diags.push(make_diagnostic(def.file.file_id, range, &var_name, edit));
+        }
+    }
+}
+
+/// Match the guard tests on a tuple variable we know how to turn into
+/// a pattern.
+fn tuple_test(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    test: ExprId,
+) -> Option<(Var, TupleTest)> {
+    match &def_fb[test] {
+        Expr::Call { .. } => match call(sema, def_fb, test)? {
+            ("is_tuple", [arg]) => Some((def_fb[*arg].as_var()?, TupleTest::IsTuple)),
+            _ => None,
+        },
+        Expr::BinaryOp {
+            lhs,
+            rhs,
+            op:
+                BinaryOp::CompOp(CompOp::Eq {
+                    strict,
+                    negated: false,
+                }),
+        } => [(*lhs, *rhs), (*rhs, *lhs)]
+            .into_iter()
+            .find_map(|(call_id, value)| match call(sema, def_fb, call_id)? {
+                ("tuple_size" | "size", [arg]) => Some((
+                    def_fb[*arg].as_var()?,
+                    TupleTest::Size(integer(def_fb, value)?),
+                )),
+                ("element", [pos, arg]) => {
+                    // `==` also matches numbers of a different type, a
+                    // pattern would not, so only atoms are safe there.
+                    match (&def_fb[value], *strict) {
+                        (Expr::Literal(Literal::Atom(_)), _) | (Expr::Literal(_), true) => Some((
+                            def_fb[*arg].as_var()?,
+                            TupleTest::Element(integer(def_fb, *pos)?, value),
+                        )),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }),
+        _ => None,
+    }
+}
+
+/// Match `f(Args)` or `erlang:f(Args)`, returning the name of `f`.
+fn call<'a>(
+    sema: &Semantic,
+    def_fb: &'a InFunctionBody<&FunctionDef>,
+    expr_id: ExprId,
+) -> Option<(&'static str, &'a [ExprId])> {
+    match &def_fb[expr_id] {
+        Expr::Call { target, args } => {
+            let name = match target {
+                CallTarget::Local { name } => def_fb.as_atom_name(sema.db, name)?,
+                CallTarget::Remote { module, name } => {
+                    if def_fb.as_atom_name(sema.db, module)?.as_str() != "erlang" {
+                        return None;
+                    }
+                    def_fb.as_atom_name(sema.db, name)?
+                }
+            };
+            let name = ["is_tuple", "tuple_size", "size", "element"]
+                .into_iter()
+                .find(|known| *known == name.as_str())?;
+            Some((name, args.as_slice()))
+        }
+        _ => None,
+    }
+}
+
+fn integer(def_fb: &InFunctionBody<&FunctionDef>, expr_id: ExprId) -> Option<usize> {
+    match &def_fb[expr_id] {
+        Expr::Literal(Literal::Integer(int)) => usize::try_from(*int).ok(),
+        _ => None,
+    }
+}
+
+/// The tuple pattern equivalent to the tests, if the guard fixes its
+/// size and the element tests are consistent with it.
+fn tuple_pattern(
+    def_fb: &InFunctionBody<&FunctionDef>,
+    body_map: &BodySourceMap,
+    source_file: &SourceFile,
+    var_tests: &[(usize, TupleTest)],
+) -> Option<String> {
+    let mut size = None;
+    for (_, test) in var_tests {
+        if let TupleTest::Size(n) = test {
+            if size.map_or(false, |size| size != *n) {
+                return None;
+            }
+            size = Some(*n);
+        }
+    }
+    let mut elements = vec![None; size?];
+    for (_, test) in var_tests {
+        if let TupleTest::Element(pos, value) = test {
+            let slot = elements.get_mut(pos.checked_sub(1)?)?;
+            if let Some(other) = slot {
+                if def_fb[*other] != def_fb[*value] {
+                    return None;
+                }
+            }
+            *slot = Some(*value);
+        }
+    }
+    let elements = elements
+        .into_iter()
+        .map(|element| match element {
+            Some(value) => Some(
+                body_map
+                    .expr(value)?
+                    .to_node(source_file)?
+                    .syntax()
+                    .text()
+                    .to_string(),
+            ),
+            None => Some("_".to_string()),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(format!("{{{}}}", elements.join(", ")))
+}
+
+/// Whether the variable is still needed once the tests on it are moved
+/// to the pattern.
+fn var_used(
+    def_fb: &InFunctionBody<&FunctionDef>,
+    clause: &Clause,
+    pat_idx: usize,
+    tests: &[ExprId],
+    consumed: &[usize],
+    var: Var,
+) -> bool {
+    let in_pats = clause
+        .pats
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| *idx != pat_idx)
+        .any(|(_, pat_id)| {
+            def_fb.fold_pat(
+                Strategy::TopDown,
+                *pat_id,
+                false,
+                &mut |acc, _| acc,
+                &mut |acc, ctx| acc || ctx.pat.as_var() == Some(var),
+            )
+        });
+    let in_exprs = tests
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !consumed.contains(idx))
+        .map(|(_, test)| test)
+        .chain(clause.exprs.iter())
+        .any(|expr_id| {
+            def_fb.fold_expr(
+                Strategy::TopDown,
+                *expr_id,
+                false,
+                &mut |acc, ctx| acc || ctx.expr.as_var() == Some(var),
+                &mut |acc, _| acc,
+            )
+        });
+    in_pats || in_exprs
+}
+
+/// The range of the guard to rewrite, and what to replace it with. If
+/// no test is left, the whole `when` part of the clause goes.
+fn guard_edit(
+    body_map: &BodySourceMap,
+    source_file: &SourceFile,
+    tests: &[ExprId],
+    consumed: &[usize],
+) -> Option<(TextRange, Option<String>)> {
+    let test_asts = tests
+        .iter()
+        .map(|test| body_map.expr(*test)?.to_node(source_file))
+        .collect::<Option<Vec<_>>>()?;
+    let remaining: Vec<String> = test_asts
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !consumed.contains(idx))
+        .map(|(_, test)| test.syntax().text().to_string())
+        .collect();
+    let first = test_asts.first()?;
+    let last = test_asts.last()?;
+    if remaining.is_empty() {
+        let clause = first
+            .syntax()
+            .ancestors()
+            .find_map(ast::FunctionClause::cast)?;
+        let start: TextSize = clause.args()?.syntax().text_range().end();
+        let end = clause.guard()?.syntax().text_range().end();
+        Some((TextRange::new(start, end), None))
+    } else {
+        let range = TextRange::new(
+            first.syntax().text_range().start(),
+            last.syntax().text_range().end(),
+        );
+        Some((range, Some(remaining.join(", "))))
+    }
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    var_name: &str,
+    edit: Option<(String, TextEdit)>,
+) -> Diagnostic {
+    let diag = Diagnostic::new(
+        DiagnosticCode::GuardToPattern,
+        format!("Guard tests on `{var_name}` could be a pattern in the clause head"),
+        range,
+    )
+    .severity(Severity::WeakWarning);
+    match edit {
+        Some((pattern, edit)) => diag.with_fixes(Some(vec![fix(
+            "guard_to_pattern",
+            &format!("Match `{pattern}` in the clause head"),
+            SourceChange::from_text_edit(file_id, edit),
+            range,
+        )])),
+        None => diag,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn tuple_tests_in_guard() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(X) when is_tuple(X), tuple_size(X) =:= 2, element(1, X) =:= ok -> ok;
+%%          ^^^^^^^^^^^ 💡 weak: Guard tests on `X` could be a pattern in the clause head
+foo(_) -> error.
+
+bar(X) when is_tuple(X), element(1, X) =:= ok -> ok;
+%%          ^^^^^^^^^^^ weak: Guard tests on `X` could be a pattern in the clause head
+bar(_) -> error.
+            "#,
+        );
+    }
+
+    #[test]
+    fn no_structural_test_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1, baz/1]).
+
+foo(X) when is_tuple(X) -> ok;
+foo(_) -> error.
+
+bar(X) when is_tuple(X), element(1, X) == 1 -> ok;
+bar(_) -> error.
+
+baz(X) when is_tuple(X), element(1, X) =:= ok; is_atom(X) -> ok;
+baz(_) -> error.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_moves_whole_guard() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(X) when is_tu~ple(X), tuple_size(X) =:= 3, element(1, X) =:= ok -> ok;
+foo(_) -> error.
+            "#,
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo({ok, _, _}) -> ok;
+foo(_) -> error.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_keeps_variable_and_other_tests() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(X, Y) when is_tu~ple(X), Y > 0, size(X) =:= 2, element(2, X) =:= 1 -> {X, Y};
+foo(_, _) -> error.
+            "#,
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo({_, 1} = X, Y) when Y > 0 -> {X, Y};
+foo(_, _) -> error.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,184 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: guard-variable-not-bound
+//
+// Return a warning if the guard of a function clause references a
+// variable the clause patterns do not bind, usually a typo, e.g.
+// `foo(Value) when Valeu > 0`, and offer to use the closest bound name.
+//
+// The compiler reports these too, as unbound variables, but offers no
+// fix: this lint is for the suggested name.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::TextRange;
+use fxhash::FxHashSet;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::Pat;
+use hir::Semantic;
+use hir::Strategy;
+use text_edit::TextEdit;
+
+use crate::diagnostics::closest_match;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+use crate::Diagnostic;
+
+pub(crate) fn guard_variable_not_bound(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    // Only function clauses, the guards of other clauses can also see
+    // the variables bound before them.
+    for (_clause_id, clause) in def_fb.clauses() {
+        let mut bound: FxHashSet<String> = FxHashSet::default();
+        for pat in &clause.pats {
+            def_fb.fold_pat(
+                Strategy::TopDown,
+                *pat,
+                (),
+                &mut |(), _| (),
+                &mut |(), ctx| {
+                    if let Pat::Var(var) = ctx.pat {
+                        bound.insert(var.as_string(sema.db.upcast()));
+                    }
+                },
+            );
+        }
+
+        for guard in clause.guards.iter().flatten() {
+            def_fb.fold_expr(
+                Strategy::TopDown,
+                *guard,
+                (),
+                &mut |(), ctx| {
+                    let name = match ctx.expr {
+                        Expr::Var(var) => var.as_string(sema.db.upcast()),
+                        _ => return,
+                    };
+                    if bound.contains(&name) {
+                        return;
+                    }
+                    if let Some(range) = def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                        diags.push(make_diagnostic(
+                            def.file.file_id,
+                            range,
+                            &name,
+                            closest_bound(&name, &bound),
+                        ));
+                    }
+                },
+                &mut |(), _| (),
+            );
+        }
+    }
+}
+
+/// The bound variable the name is most likely a misspelling of.
+fn closest_bound<'a>(name: &str, bound: &'a FxHashSet<String>) -> Option<&'a str> {
+    let mut bound: Vec<&str> = bound.iter().map(String::as_str).collect();
+    // Sort by name first, so that ties are broken deterministically
+    bound.sort();
+    closest_match(name, bound)
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    name: &str,
+    suggestion: Option<&str>,
+) -> Diagnostic {
+    let diag = Diagnostic::warning(
+        DiagnosticCode::GuardVariableNotBound,
+        range,
+        format!("Variable `{name}` in guard is not bound by the clause patterns"),
+    );
+    match suggestion {
+        Some(suggestion) => diag.with_fixes(Some(vec![fix(
+            "use_bound_variable",
+            &format!("Change to `{suggestion}`"),
+            SourceChange::from_text_edit(file_id, TextEdit::replace(range, suggestion.to_string())),
+            range,
+        )])),
+        None => diag,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn unbound_guard_variable() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/2]).
+
+foo(Value) when Valeu > 0 -> Value;
+%%              ^^^^^ 💡 warning: Variable `Valeu` in guard is not bound by the clause patterns
+foo(_) -> 0.
+
+bar({A, _}, B) when A > B; Other =:= 0 -> A;
+%%                         ^^^^^ warning: Variable `Other` in guard is not bound by the clause patterns
+bar(_, B) -> B.
+            "#,
+        );
+    }
+
+    #[test]
+    fn bound_guard_variables_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo([H | T], #{key := K}) when is_integer(H), length(T) > K ->
+    case H of
+        X when X > K -> X;
+        _ -> K
+    end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_closest_bound_name() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(Count, Limit) when Cu~ont > Limit -> Count.
+            "#,
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(Count, Limit) when Count > Limit -> Count.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,214 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: high_complexity
+//!
+//! Return a hint if the cyclomatic complexity of a function is above a
+//! threshold, see `DiagnosticsConfig::with_complexity_threshold`. Every
+//! function starts at 1, and each of the following adds 1: a function,
+//! `case`, `if`, `receive` or `try` branch after the first, an `after`
+//! of a `receive`, a `catch` clause, a guard alternative (separated by
+//! `;`), and an `andalso` or `orelse`. This is a maintainability metric,
+//! so it is opt-in.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::ast;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::LogicOp;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::NameArity;
+use hir::Semantic;
+use hir::Strategy;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+/// The threshold when none is configured, as suggested by McCabe.
+pub(crate) const DEFAULT_THRESHOLD: u32 = 10;
+
+pub(crate) fn high_complexity(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+    threshold: u32,
+) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(name, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, name, def, threshold)
+            }
+        });
+}
+
+fn process_function(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    name: &NameArity,
+    def: &FunctionDef,
+    threshold: u32,
+) {
+    let score = complexity(sema, def);
+    if score <= threshold {
+        return;
+    }
+    if let Some(range) = name_range(&def.source(sema.db.upcast())) {
+        diags.push(
+            Diagnostic::new(
+                DiagnosticCode::HighComplexity,
+                format!(
+                    "Function `{name}` has a cyclomatic complexity of {score}, above the threshold of {threshold}"
+                ),
+                range,
+            )
+            .severity(Severity::WeakWarning),
+        );
+    }
+}
+
+fn complexity(sema: &Semantic, def: &FunctionDef) -> u32 {
+    let def_fb = def.in_function_body(sema.db, def);
+    let decisions = |acc: u32, expr: Expr| -> u32 {
+        let branches = |n: usize| n.saturating_sub(1) as u32;
+        let alternatives = |guards: &[Vec<ExprId>]| guards.len() as u32;
+        acc + match expr {
+            Expr::Case { clauses, .. } => {
+                branches(clauses.len())
+                    + clauses.iter().map(|c| alternatives(&c.guards)).sum::<u32>()
+            }
+            Expr::Receive { clauses, after } => {
+                branches(clauses.len())
+                    + clauses.iter().map(|c| alternatives(&c.guards)).sum::<u32>()
+                    + after.map_or(0, |_| 1)
+            }
+            // The guards of an `if` are its branches.
+            Expr::If { clauses } => branches(clauses.len()),
+            Expr::Try {
+                of_clauses,
+                catch_clauses,
+                ..
+            } => {
+                branches(of_clauses.len())
+                    + of_clauses
+                        .iter()
+                        .map(|c| alternatives(&c.guards))
+                        .sum::<u32>()
+                    + catch_clauses.len() as u32
+                    + catch_clauses
+                        .iter()
+                        .map(|c| alternatives(&c.guards))
+                        .sum::<u32>()
+            }
+            Expr::BinaryOp {
+                op: BinaryOp::LogicOp(LogicOp::And { lazy: true } | LogicOp::Or { lazy: true }),
+                ..
+            } => 1,
+            _ => 0,
+        }
+    };
+
+    let mut score = 1;
+    let mut clauses = 0;
+    for (_clause_id, clause) in def_fb.clauses() {
+        clauses += 1;
+        score += clause.guards.len() as u32;
+        // The guards of the function clauses are not part of the fold
+        // below.
+        for guard in clause.guards.iter().flatten() {
+            score = def_fb.fold_expr(
+                Strategy::TopDown,
+                *guard,
+                score,
+                &mut |acc, ctx| decisions(acc, ctx.expr),
+                &mut |acc, _| acc,
+            );
+        }
+    }
+    score += clauses.saturating_sub(1);
+    def_fb.fold_function(
+        score,
+        &mut |acc, _, ctx| decisions(acc, ctx.expr),
+        &mut |acc, _, _| acc,
+    )
+}
+
+fn name_range(fun_decl: &ast::FunDecl) -> Option<TextRange> {
+    fun_decl.clauses().find_map(|clause| match clause {
+        ast::FunctionOrMacroClause::FunctionClause(clause) => {
+            Some(clause.name()?.syntax().text_range())
+        }
+        ast::FunctionOrMacroClause::MacroCallExpr(_) => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(threshold: u32, fixture: &str) {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::HighComplexity)
+            .with_complexity_threshold(threshold)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[test]
+    fn complexity_above_threshold() {
+        check_diagnostics(
+            4,
+            r#"
+    -module(main).
+    -export([foo/2, bar/1]).
+
+    foo(X, Y) when X > 0; Y > 0 ->
+ %% ^^^ weak: Function `foo/2` has a cyclomatic complexity of 8, above the threshold of 4
+        case X of
+            1 -> one;
+            2 when Y > 1 andalso Y < 10 -> two;
+            _ -> many
+        end;
+    foo(_, _) -> none.
+
+    bar(X) ->
+        case X of
+            1 -> one;
+            _ -> other
+        end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn complexity_at_threshold_not_reported() {
+        check_diagnostics(
+            3,
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(X) ->
+    receive
+        {X, Y} when Y > 0 -> Y
+    after 100 -> timeout
+    end.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,272 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: leaky_exported_type
+//!
+//! Return a warning if a type in `-export_type` is defined in terms of
+//! local types that are not exported themselves, so that other modules
+//! cannot name all the parts of it, and offer to export them too.
+//!
+
+use std::sync::Arc;
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use fxhash::FxHashSet;
+use hir::Body;
+use hir::CallTarget;
+use hir::DefMap;
+use hir::FormIdx;
+use hir::FunType;
+use hir::InFile;
+use hir::ListType;
+use hir::NameArity;
+use hir::Semantic;
+use hir::TypeAlias;
+use hir::TypeAliasDef;
+use hir::TypeBody;
+use hir::TypeExpr;
+use hir::TypeExprId;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn leaky_exported_type(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    let def_map = sema.def_map(file_id);
+    let form_list = sema.db.file_form_list(file_id);
+    let source_file = sema.parse(file_id).value;
+    for form in form_list.forms() {
+        let export = match form {
+            FormIdx::TypeExport(idx) => &form_list[*idx],
+            _ => continue,
+        };
+        let export_ast = export.form_id.get(&source_file);
+        let last_entry = match export_ast.types().last() {
+            Some(last_entry) => last_entry,
+            None => continue,
+        };
+        for entry_id in export.entries.clone() {
+            let entry = &form_list[entry_id];
+            let missing = match def_map.get_type(&entry.name) {
+                // The structure of an opaque type is not visible outside
+                // the module, so it may use anything.
+                Some(def) if matches!(def.type_alias, TypeAlias::Regular { .. }) => {
+                    unexported_references(sema, &def_map, def)
+                }
+                _ => continue,
+            };
+            if missing.is_empty() {
+                continue;
+            }
+            if let Some(fa) = export_ast.types().nth(entry.idx as usize) {
+                diags.push(make_diagnostic(
+                    file_id,
+                    fa.syntax().text_range(),
+                    last_entry.syntax().text_range(),
+                    &entry.name,
+                    &missing,
+                ));
+            }
+        }
+    }
+}
+
+/// The local types the definition refers to, directly or through other
+/// non-exported local types, that are not exported.
+fn unexported_references(sema: &Semantic, def_map: &DefMap, def: &TypeAliasDef) -> Vec<NameArity> {
+    let mut seen: FxHashSet<NameArity> = FxHashSet::default();
+    seen.insert(def.name().clone());
+    let mut pending = vec![def.clone()];
+    let mut missing = Vec::new();
+    while let Some(def) = pending.pop() {
+        let body = match type_body(sema, &def) {
+            Some(body) => body,
+            None => continue,
+        };
+        let mut references = Vec::new();
+        collect_local_types(sema, &body.body, body.ty, &mut references);
+        for name in references {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            // Built-in types do not resolve to a definition.
+            if let Some(referenced) = def_map.get_type(&name) {
+                if !referenced.exported {
+                    missing.push(name);
+                    pending.push(referenced.clone());
+                }
+            }
+        }
+    }
+    missing
+}
+
+fn type_body(sema: &Semantic, def: &TypeAliasDef) -> Option<Arc<TypeBody>> {
+    let file_id = def.file.file_id;
+    let form_list = sema.db.file_form_list(file_id);
+    form_list.forms().iter().find_map(|form| match form {
+        FormIdx::TypeAlias(idx) if form_list[*idx].name() == def.name() => {
+            Some(sema.db.type_body(InFile::new(file_id, *idx)))
+        }
+        _ => None,
+    })
+}
+
+fn collect_local_types(
+    sema: &Semantic,
+    body: &Body,
+    type_expr: TypeExprId,
+    acc: &mut Vec<NameArity>,
+) {
+    let children: Vec<TypeExprId> = match &body[type_expr] {
+        TypeExpr::AnnType { ty, .. } => vec![*ty],
+        TypeExpr::BinaryOp { lhs, rhs, .. } | TypeExpr::Range { lhs, rhs } => vec![*lhs, *rhs],
+        TypeExpr::Call { target, args } => {
+            if let CallTarget::Local { name } = target {
+                if let Some(name) = body[*name].as_atom() {
+                    acc.push(NameArity::new(sema.db.lookup_atom(name), args.len() as u32));
+                }
+            }
+            args.clone()
+        }
+        TypeExpr::Fun(FunType::Any) => vec![],
+        TypeExpr::Fun(FunType::AnyArgs { result }) => vec![*result],
+        TypeExpr::Fun(FunType::Full { params, result }) => {
+            params.iter().chain(Some(result)).copied().collect()
+        }
+        TypeExpr::List(ListType::Empty) => vec![],
+        TypeExpr::List(ListType::Regular(ty)) | TypeExpr::List(ListType::NonEmpty(ty)) => {
+            vec![*ty]
+        }
+        TypeExpr::Map { fields } => fields
+            .iter()
+            .flat_map(|(key, _op, value)| [*key, *value])
+            .collect(),
+        TypeExpr::Union { types } => types.clone(),
+        TypeExpr::Record { fields, .. } => fields.iter().map(|(_, ty)| *ty).collect(),
+        TypeExpr::Tuple { args } => args.clone(),
+        TypeExpr::UnaryOp { type_expr, .. } => vec![*type_expr],
+        TypeExpr::MacroCall { expansion, .. } => vec![*expansion],
+        TypeExpr::Literal(_) | TypeExpr::Missing | TypeExpr::Var(_) => vec![],
+    };
+    for child in children {
+        collect_local_types(sema, body, child, acc);
+    }
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    last_entry: TextRange,
+    name: &NameArity,
+    missing: &[NameArity],
+) -> Diagnostic {
+    let missing = missing
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    let quoted = missing
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut edit_builder = TextEdit::builder();
+    edit_builder.insert(last_entry.end(), format!(", {}", missing.join(", ")));
+    let edit = edit_builder.finish();
+    Diagnostic::new(
+        DiagnosticCode::LeakyExportedType,
+        format!("Exported type `{name}` uses types that are not exported: {quoted}"),
+        range,
+    )
+    .severity(Severity::Warning)
+    .with_fixes(Some(vec![fix(
+        "export_referenced_types",
+        &format!("Export {quoted}"),
+        SourceChange::from_text_edit(file_id, edit),
+        range,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn exported_type_uses_local_type() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export_type([t/0]).
+%%            ^^^ 💡 warning: Exported type `t/0` uses types that are not exported: `u/0`
+
+-type t() :: {ok, u()} | error.
+-type u() :: integer().
+            "#,
+        );
+    }
+
+    #[test]
+    fn transitive_references() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export_type([t/0, v/1]).
+%%            ^^^ 💡 warning: Exported type `t/0` uses types that are not exported: `u/0`, `w/0`
+
+-type t() :: [u()].
+-type u() :: #{key => v(w())}.
+-type v(A) :: {A}.
+-type w() :: atom().
+            "#,
+        );
+    }
+
+    #[test]
+    fn complete_exports_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export_type([t/0, u/0, o/0]).
+
+-type t() :: {ok, u()} | integer() | other:type().
+-type u() :: binary().
+-opaque o() :: {private()}.
+-type private() :: reference().
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_exports_referenced_types() {
+        check_fix(
+            r#"
+-module(main).
+-export_type([~t/0]).
+
+-type t() :: {u(), v()}.
+-type u() :: integer().
+-type v() :: atom().
+            "#,
+            r#"
+-module(main).
+-export_type([t/0, u/0, v/0]).
+
+-type t() :: {u(), v()}.
+-type u() :: integer().
+-type v() :: atom().
+            "#,
+        );
+    }
+}
@@ -0,0 +1,306 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: length_zero_check
+//!
+//! Return a hint if `length/1` is compared with zero to check whether a
+//! list is empty, e.g. `length(L) == 0` or `length(L) > 0`. This walks
+//! the whole list, where `L =:= []` or `L =/= []` is constant time, and
+//! offer to use the latter. In guards, only the empty checks are
+//! reported: `length(L) > 0` also fails when `L` is not a proper list,
+//! where `L =/= []` succeeds.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::CompOp;
+use elp_syntax::ast::Ordering;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Semantic;
+use hir::Strategy;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn length_zero_check(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+
+    // The check is as common in guards as in bodies, so visit both.
+    let roots: Vec<(ExprId, bool)> = def_fb
+        .clauses()
+        .flat_map(|(_clause_id, clause)| {
+            let guards = clause.guards.iter().flatten().map(|expr| (*expr, true));
+            let exprs = clause.exprs.iter().map(|expr| (*expr, false));
+            guards.chain(exprs).collect::<Vec<_>>()
+        })
+        .collect();
+
+    for (root, in_guard) in roots {
+        def_fb.fold_expr(
+            Strategy::TopDown,
+            root,
+            (),
+            &mut |(), ctx| {
+                let (lhs, rhs, op) = match ctx.expr {
+                    Expr::BinaryOp {
+                        lhs,
+                        rhs,
+                        op: BinaryOp::CompOp(op),
+                    } => (lhs, rhs, op),
+                    _ => return,
+                };
+                // Normalise to `length(L) Op N`.
+                let (list, n, op) = match (
+                    length_arg(sema, &def_fb, lhs),
+                    length_arg(sema, &def_fb, rhs),
+                ) {
+                    (Some(list), None) => (list, rhs, op),
+                    (None, Some(list)) => (list, lhs, flip(op)),
+                    _ => return,
+                };
+                let n = match &def_fb[n] {
+                    Expr::Literal(Literal::Integer(n)) => *n,
+                    _ => return,
+                };
+                let empty = match (op, n) {
+                    (CompOp::Eq { negated: false, .. }, 0) => true,
+                    (CompOp::Eq { negated: true, .. }, 0) => false,
+                    (
+                        CompOp::Ord {
+                            ordering: Ordering::Greater,
+                            strict: true,
+                        },
+                        0,
+                    ) => false,
+                    (
+                        CompOp::Ord {
+                            ordering: Ordering::Greater,
+                            strict: false,
+                        },
+                        1,
+                    ) => false,
+                    (
+                        CompOp::Ord {
+                            ordering: Ordering::Less,
+                            strict: true,
+                        },
+                        1,
+                    ) => true,
+                    (
+                        CompOp::Ord {
+                            ordering: Ordering::Less,
+                            strict: false,
+                        },
+                        0,
+                    ) => true,
+                    _ => return,
+                };
+                if in_guard && !empty {
+                    return;
+                }
+                let range = match def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                    Some(range) => range,
+                    None => return,
+                };
+                let list_text = match body_map
+                    .expr(list)
+                    .and_then(|ptr| ptr.to_node(&source_file))
+                {
+                    Some(list_ast) => list_ast.syntax().text().to_string(),
+                    None => return,
+                };
+                // Operators binding looser than a comparison.
+                let list_text = match &def_fb[list] {
+                    Expr::BinaryOp { .. } | Expr::Match { .. } | Expr::Catch { .. } => {
+                        format!("({list_text})")
+                    }
+                    _ => list_text,
+                };
+                let replacement = if empty {
+                    format!("{list_text} =:= []")
+                } else {
+                    format!("{list_text} =/= []")
+                };
+                diags.push(make_diagnostic(def.file.file_id, range, replacement));
+            },
+            &mut |(), _| (),
+        );
+    }
+}
+
+/// Match `length(L)` or `erlang:length(L)`, returning `L`.
+fn length_arg(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    expr_id: ExprId,
+) -> Option<ExprId> {
+    match &def_fb[expr_id] {
+        Expr::Call { target, args } => {
+            let name = match target {
+                CallTarget::Local { name } => def_fb.as_atom_name(sema.db, name)?,
+                CallTarget::Remote { module, name } => {
+                    if def_fb.as_atom_name(sema.db, module)?.as_str() != "erlang" {
+                        return None;
+                    }
+                    def_fb.as_atom_name(sema.db, name)?
+                }
+            };
+            match args.as_slice() {
+                [arg] if name.as_str() == "length" => Some(*arg),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The operator giving the same result with the operands swapped.
+fn flip(op: CompOp) -> CompOp {
+    match op {
+        CompOp::Ord { ordering, strict } => CompOp::Ord {
+            ordering: match ordering {
+                Ordering::Less => Ordering::Greater,
+                Ordering::Greater => Ordering::Less,
+            },
+            strict,
+        },
+        CompOp::Eq { .. } => op,
+    }
+}
+
+fn make_diagnostic(file_id: FileId, range: TextRange, replacement: String) -> Diagnostic {
+    let mut edit_builder = TextEdit::builder();
+    edit_builder.replace(range, replacement.clone());
+    let edit = edit_builder.finish();
+    Diagnostic::new(
+        DiagnosticCode::LengthZeroCheck,
+        format!("`length/1` walks the whole list, use `{replacement}` instead"),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+    .with_fixes(Some(vec![fix(
+        "replace_length_zero_check",
+        &format!("Replace with `{replacement}`"),
+        SourceChange::from_text_edit(file_id, edit),
+        range,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn length_compared_with_zero() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(L) when length(L) == 0 -> empty;
+%%          ^^^^^^^^^^^^^^ 💡 weak: `length/1` walks the whole list, use `L =:= []` instead
+foo(L) -> 0 < length(L).
+%%        ^^^^^^^^^^^^^ 💡 weak: `length/1` walks the whole list, use `L =/= []` instead
+
+bar(L) -> erlang:length(L) >= 1.
+%%        ^^^^^^^^^^^^^^^^^^^^^ 💡 weak: `length/1` walks the whole list, use `L =/= []` instead
+            "#,
+        );
+    }
+
+    #[test]
+    fn non_empty_check_in_guard_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(L) when length(L) > 0 -> non_empty;
+foo(L) when is_atom(L); length(L) >= 1 -> atom_or_non_empty;
+foo(L) when length(L) =< 0 -> empty.
+%%          ^^^^^^^^^^^^^^ 💡 weak: `length/1` walks the whole list, use `L =:= []` instead
+            "#,
+        );
+    }
+
+    #[test]
+    fn other_comparisons_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(L) -> {length(L) == 1, length(L) > 2, length(L) >= 0, lists:length(L) == 0}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_empty_check() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(L) when len~gth(L) =:= 0 -> empty;
+foo(_) -> non_empty.
+            "#,
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(L) when L =:= [] -> empty;
+foo(_) -> non_empty.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_adds_parens() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(A, B) -> len~gth(A ++ B) /= 0.
+            "#,
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(A, B) -> (A ++ B) =/= [].
+            "#,
+        );
+    }
+}
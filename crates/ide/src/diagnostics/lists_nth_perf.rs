@@ -0,0 +1,130 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: lists_nth_perf
+//!
+//! Return a hint if `lists:nth/2` is called with a literal index above a
+//! small threshold, e.g. `lists:nth(7, L)`. It walks the list up to the
+//! index, and a large fixed position usually means a tuple, a map or an
+//! array would fit the data better. This is a heuristic, so it is
+//! opt-in.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use hir::CallTarget;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::Literal;
+use hir::Semantic;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+/// Indices up to this one are cheap enough to be left alone.
+const MAX_INDEX: i128 = 5;
+
+pub(crate) fn lists_nth_perf(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    def_fb.fold_function(
+        (),
+        &mut |(), _, ctx| {
+            let (module, name, args) = match ctx.expr {
+                Expr::Call {
+                    target: CallTarget::Remote { module, name },
+                    args,
+                } => (module, name, args),
+                _ => return,
+            };
+            let is_nth = def_fb
+                .as_atom_name(sema.db, &module)
+                .map_or(false, |module| module.as_str() == "lists")
+                && def_fb
+                    .as_atom_name(sema.db, &name)
+                    .map_or(false, |name| name.as_str() == "nth");
+            if !is_nth {
+                return;
+            }
+            let index = match args.as_slice() {
+                [index, _list] => match &def_fb[*index] {
+                    Expr::Literal(Literal::Integer(index)) if *index > MAX_INDEX => *index,
+                    _ => return,
+                },
+                _ => return,
+            };
+            if let Some(range) = def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                diags.push(
+                    Diagnostic::new(
+                        DiagnosticCode::ListsNthPerf,
+                        format!(
+                            "`lists:nth({index}, ...)` walks the list to a fixed position, \
+                            consider a tuple with `element/2`, a map, or an `array` instead"
+                        ),
+                        range,
+                    )
+                    .severity(Severity::WeakWarning),
+                );
+            }
+        },
+        &mut |(), _, _| (),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::ListsNthPerf)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[test]
+    fn large_constant_index() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(L) -> lists:nth(7, L).
+%%        ^^^^^^^^^^^^^^^ weak: `lists:nth(7, ...)` walks the list to a fixed position, consider a tuple with `element/2`, a map, or an `array` instead
+            "#,
+        );
+    }
+
+    #[test]
+    fn small_or_variable_index_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(N, L) -> {lists:nth(1, L), lists:nth(5, L), lists:nth(N, L), nth(9, L)}.
+
+nth(N, L) -> lists:nth(N, L).
+            "#,
+        );
+    }
+}
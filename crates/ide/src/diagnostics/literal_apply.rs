@@ -0,0 +1,191 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: literal_apply
+//!
+//! Return a hint if `apply/3` is called with a module, a function and an
+//! argument list that are all literals, e.g. `apply(mod, func, [A, B])`,
+//! and offer to replace it with the direct call `mod:func(A, B)`, which
+//! is clearer and can be found by cross reference tools.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Semantic;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn literal_apply(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+    let text = |expr_id: ExprId| -> Option<String> {
+        let expr: ast::Expr = body_map
+            .expr(expr_id)
+            .and_then(|ptr| ptr.to_node(&source_file))?;
+        Some(expr.syntax().text().to_string())
+    };
+
+    def_fb.fold_function(
+        (),
+        &mut |(), _, ctx| {
+            let (module, function, args) = match literal_mfa(sema, &def_fb, ctx.expr_id) {
+                Some(mfa) => mfa,
+                None => return,
+            };
+            let range = match def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                Some(range) => range,
+                None => return,
+            };
+            let args: Option<Vec<String>> = args.iter().map(|arg| text(*arg)).collect();
+            if let (Some(module), Some(function), Some(args)) = (text(module), text(function), args)
+            {
+                diags.push(make_diagnostic(
+                    def.file.file_id,
+                    range,
+                    format!("{module}:{function}/{}", args.len()),
+                    format!("{module}:{function}({})", args.join(", ")),
+                ));
+            }
+        },
+        &mut |(), _, _| (),
+    );
+}
+
+/// Match `apply(M, F, [A, ...])` or `erlang:apply(M, F, [A, ...])`, where
+/// `M` and `F` are atoms and the arguments a proper list, returning `M`,
+/// `F` and the arguments.
+fn literal_mfa(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    expr_id: ExprId,
+) -> Option<(ExprId, ExprId, Vec<ExprId>)> {
+    match &def_fb[expr_id] {
+        Expr::Call { target, args } => {
+            let name = match target {
+                CallTarget::Local { name } => def_fb.as_atom_name(sema.db, name)?,
+                CallTarget::Remote { module, name } => {
+                    if def_fb.as_atom_name(sema.db, module)?.as_str() != "erlang" {
+                        return None;
+                    }
+                    def_fb.as_atom_name(sema.db, name)?
+                }
+            };
+            if name.as_str() != "apply" {
+                return None;
+            }
+            let (module, function, list) = match args.as_slice() {
+                [module, function, list] => (*module, *function, *list),
+                _ => return None,
+            };
+            let is_atom = |id: ExprId| matches!(&def_fb[id], Expr::Literal(Literal::Atom(_)));
+            if !is_atom(module) || !is_atom(function) {
+                return None;
+            }
+            match &def_fb[list] {
+                Expr::List { exprs, tail: None } => Some((module, function, exprs.clone())),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn make_diagnostic(file_id: FileId, range: TextRange, mfa: String, call: String) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::LiteralApply,
+        format!("`apply/3` is called with a known function, call `{mfa}` directly"),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+    .with_fixes(Some(vec![fix(
+        "call_directly",
+        &format!("Replace with `{call}`"),
+        SourceChange::from_text_edit(file_id, TextEdit::replace(range, call)),
+        range,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn apply_with_literal_mfa() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/2, bar/0]).
+
+foo(A, B) -> apply(lists, append, [A, B]).
+%%           ^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: `apply/3` is called with a known function, call `lists:append/2` directly
+
+bar() -> erlang:apply(erlang, node, []).
+%%       ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: `apply/3` is called with a known function, call `erlang:node/0` directly
+            "#,
+        );
+    }
+
+    #[test]
+    fn apply_with_unknown_parts_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/3]).
+
+foo(M, F, Args) ->
+    {apply(M, append, [a]), apply(lists, F, [a]), apply(lists, append, Args),
+     apply(lists, append, [a | Args]), apply(fun lists:append/1, [[a]])}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_direct_call() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(A, B) -> app~ly(lists, append, [A, B]).
+            "#,
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(A, B) -> lists:append(A, B).
+            "#,
+        );
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: map-pattern-operator
+//
+// Return an error if a field of a map pattern uses `=>`, e.g.
+// `foo(#{key => V})`, which is only allowed when building a map, and
+// offer to use `:=` instead.
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::TextRange;
+use hir::FunctionDef;
+use hir::Pat;
+use hir::PatId;
+use hir::Semantic;
+use hir::Strategy;
+use text_edit::TextEdit;
+
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+use crate::Diagnostic;
+
+pub(crate) fn map_pattern_operator(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+    let file_id = def.file.file_id;
+
+    // The patterns of the function clauses, and all the ones in their
+    // bodies.
+    let mut maps: Vec<PatId> = Vec::new();
+    for (_clause_id, clause) in def_fb.clauses() {
+        for pat in &clause.pats {
+            def_fb.fold_pat(
+                Strategy::TopDown,
+                *pat,
+                (),
+                &mut |(), _| (),
+                &mut |(), ctx| {
+                    if matches!(ctx.pat, Pat::Map { .. }) {
+                        maps.push(ctx.pat_id);
+                    }
+                },
+            );
+        }
+    }
+    def_fb.fold_function((), &mut |(), _, _| (), &mut |(), _, ctx| {
+        if matches!(ctx.pat, Pat::Map { .. }) {
+            maps.push(ctx.pat_id);
+        }
+    });
+
+    for pat in maps {
+        // The fields using `=>` are dropped when lowering the pattern,
+        // so look for them in the syntax.
+        let map = match body_map.pat(pat).and_then(|ptr| ptr.to_node(&source_file)) {
+            Some(ast::Expr::MapExpr(map)) => map,
+            _ => continue,
+        };
+        for field in map.fields() {
+            if let Some((ast::MapOp::Assoc, token)) = field.op() {
+                diags.push(make_diagnostic(file_id, token.text_range()));
+            }
+        }
+    }
+}
+
+fn make_diagnostic(file_id: FileId, range: TextRange) -> Diagnostic {
+    Diagnostic::error(
+        DiagnosticCode::MapPatternOperator,
+        range,
+        "Map patterns must use `:=`, `=>` is only allowed when building a map".to_string(),
+    )
+    .with_fixes(Some(vec![fix(
+        "use_map_match_operator",
+        "Use `:=`",
+        SourceChange::from_text_edit(file_id, TextEdit::replace(range, ":=".to_string())),
+        range,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn assoc_in_map_pattern() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(#{key => V}) -> V;
+%%        ^^ 💡 error: Map patterns must use `:=`, `=>` is only allowed when building a map
+foo(M) ->
+    case M of
+        #{a := A, b => B} -> {A, B};
+%%                  ^^ 💡 error: Map patterns must use `:=`, `=>` is only allowed when building a map
+        _ -> none
+    end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn map_expressions_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(#{key := V} = M) -> M#{key => V + 1, other => #{}}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_operator() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(#{key =~> V}) -> V.
+            "#,
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(#{key := V}) -> V.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,279 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: mergeable_clauses
+//!
+//! Return a hint if adjacent function clauses only differ by an atom in
+//! one argument and have the same body, e.g. `f(a) -> X; f(b) -> X`,
+//! and offer to merge them with a guard, e.g.
+//! `f(K) when K =:= a; K =:= b -> X`.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use fxhash::FxHashSet;
+use hir::AnyExprId;
+use hir::Body;
+use hir::Clause;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Pat;
+use hir::Semantic;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn mergeable_clauses(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body = def_fb.body();
+    let clauses: Vec<&Clause> = def_fb
+        .clauses()
+        .map(|(_clause_id, clause)| clause)
+        .collect();
+    // Clauses coming from macros cannot be matched up with the HIR ones.
+    let ast_clauses: Option<Vec<ast::FunctionClause>> = def
+        .source(sema.db.upcast())
+        .clauses()
+        .map(|clause| match clause {
+            ast::FunctionOrMacroClause::FunctionClause(clause) => Some(clause),
+            ast::FunctionOrMacroClause::MacroCallExpr(_) => None,
+        })
+        .collect();
+    let ast_clauses = match ast_clauses {
+        Some(ast_clauses) if ast_clauses.len() == clauses.len() => ast_clauses,
+        _ => return,
+    };
+
+    let mut first = 0;
+    while first + 1 < clauses.len() {
+        let pos = match differing_atom(sema, &body, clauses[first], clauses[first + 1]) {
+            Some(pos) => pos,
+            None => {
+                first += 1;
+                continue;
+            }
+        };
+        let mut last = first + 1;
+        while last + 1 < clauses.len()
+            && differing_atom(sema, &body, clauses[first], clauses[last + 1]) == Some(pos)
+        {
+            last += 1;
+        }
+        if let Some(diag) = merge_diagnostic(
+            sema,
+            &def_fb,
+            def.file.file_id,
+            &ast_clauses[first..=last],
+            pos,
+        ) {
+            diags.push(diag);
+        }
+        first = last + 1;
+    }
+}
+
+/// The position of the only argument in which the clauses differ, if
+/// both have an atom there, neither has a guard and the bodies are the
+/// same. The other arguments being the same, both clauses bind the
+/// same variables.
+fn differing_atom(sema: &Semantic, body: &Body, a: &Clause, b: &Clause) -> Option<usize> {
+    if !a.guards.is_empty()
+        || !b.guards.is_empty()
+        || a.pats.len() != b.pats.len()
+        || a.exprs.len() != b.exprs.len()
+    {
+        return None;
+    }
+    let print = |id: AnyExprId| body.print_any_expr(sema.db.upcast(), id);
+    let same_body = a
+        .exprs
+        .iter()
+        .zip(b.exprs.iter())
+        .all(|(a, b)| print(AnyExprId::Expr(*a)) == print(AnyExprId::Expr(*b)));
+    if !same_body {
+        return None;
+    }
+    let mut differing = a
+        .pats
+        .iter()
+        .zip(b.pats.iter())
+        .enumerate()
+        .filter(|(_, (a, b))| print(AnyExprId::Pat(**a)) != print(AnyExprId::Pat(**b)));
+    match (differing.next(), differing.next()) {
+        (Some((pos, (a, b))), None) => match (&body[*a], &body[*b]) {
+            (Pat::Literal(Literal::Atom(_)), Pat::Literal(Literal::Atom(_))) => Some(pos),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn merge_diagnostic(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    file_id: FileId,
+    clauses: &[ast::FunctionClause],
+    pos: usize,
+) -> Option<Diagnostic> {
+    let first = clauses.first()?;
+    let last = clauses.last()?;
+    let first_args = first.args()?;
+    let atoms: Vec<String> = clauses
+        .iter()
+        .map(|clause| Some(clause.args()?.args().nth(pos)?.syntax().text().to_string()))
+        .collect::<Option<_>>()?;
+    let first_atom = first_args.args().nth(pos)?;
+    let range = first_atom.syntax().text_range();
+
+    let var = fresh_var(sema, def_fb);
+    let guard = atoms
+        .iter()
+        .map(|atom| format!("{var} =:= {atom}"))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let mut edit_builder = TextEdit::builder();
+    edit_builder.replace(range, var);
+    edit_builder.insert(
+        first_args.syntax().text_range().end(),
+        format!(" when {guard}"),
+    );
+    edit_builder.delete(TextRange::new(
+        first.syntax().text_range().end(),
+        last.syntax().text_range().end(),
+    ));
+    let edit = edit_builder.finish();
+
+    let quoted = atoms
+        .iter()
+        .map(|atom| format!("`{atom}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(
+        Diagnostic::new(
+            DiagnosticCode::MergeableClauses,
+            format!("Clauses for {quoted} have the same body and could be merged"),
+            range,
+        )
+        .severity(Severity::WeakWarning)
+        .with_fixes(Some(vec![fix(
+            "merge_clauses",
+            "Merge clauses using a guard",
+            SourceChange::from_text_edit(file_id, edit),
+            range,
+        )])),
+    )
+}
+
+/// A variable name not used anywhere in the function.
+fn fresh_var(sema: &Semantic, def_fb: &InFunctionBody<&FunctionDef>) -> String {
+    let used = def_fb.fold_function(
+        FxHashSet::default(),
+        &mut |mut acc, _, ctx| {
+            if let Expr::Var(var) = ctx.expr {
+                acc.insert(var.as_string(sema.db.upcast()));
+            }
+            acc
+        },
+        &mut |mut acc, _, ctx| {
+            if let Pat::Var(var) = ctx.pat {
+                acc.insert(var.as_string(sema.db.upcast()));
+            }
+            acc
+        },
+    );
+    let mut name = "K".to_string();
+    let mut idx = 0;
+    while used.contains(&name) {
+        idx += 1;
+        name = format!("K{idx}");
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn clauses_with_same_body() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(a, X) -> {ok, X};
+%%  ^ 💡 weak: Clauses for `a`, `b`, `c` have the same body and could be merged
+foo(b, X) -> {ok, X};
+foo(c, X) -> {ok, X};
+foo(_, _) -> error.
+            "#,
+        );
+    }
+
+    #[test]
+    fn different_clauses_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/2, bar/2, baz/2]).
+
+foo(a, X) -> {ok, X};
+foo(b, Y) -> {ok, Y};
+foo(c, _) -> other.
+
+bar(a, 1) -> ok;
+bar(b, 2) -> ok.
+
+baz(a, X) when X > 0 -> X;
+baz(b, X) -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_merges_clauses() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(~a, K) -> K;
+foo(b, K) -> K;
+foo(_, _) -> error.
+            "#,
+            r#"
+-module(main).
+-export([foo/2]).
+
+foo(K1, K) when K1 =:= a; K1 =:= b -> K;
+foo(_, _) -> error.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,189 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: missing_reverse
+//!
+//! Return a hint if a function builds a list in an accumulator, by
+//! recursing with `[X | Acc]`, but the base case returns the accumulator
+//! as is. The result is then in reverse order, which is often a bug.
+//!
+//! ```erlang
+//! f([H | T], Acc) -> f(T, [H | Acc]);
+//! f([], Acc) -> Acc.
+//! ```
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::Clause;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Semantic;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+pub(crate) fn missing_reverse(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let clauses: Vec<&Clause> = def_fb
+        .clauses()
+        .map(|(_clause_id, clause)| clause)
+        .collect();
+
+    // Argument positions used as list accumulators by a recursive clause.
+    let accumulators: Vec<usize> = (0..def.function.name.arity() as usize)
+        .filter(|idx| {
+            clauses
+                .iter()
+                .any(|clause| conses_onto_accumulator(sema, &def_fb, def, clause, *idx))
+        })
+        .collect();
+
+    for idx in accumulators {
+        for clause in &clauses {
+            let acc = match clause.pats.get(idx).and_then(|pat| def_fb[*pat].as_var()) {
+                Some(acc) => acc,
+                None => continue,
+            };
+            let last = match clause.exprs.last() {
+                Some(last) => *last,
+                None => continue,
+            };
+            if def_fb[last].as_var() != Some(acc) {
+                continue;
+            }
+            if let Some(range) = def_fb.range_for_expr(sema.db, last) {
+                diags.push(make_diagnostic(range, &acc.as_string(sema.db.upcast())));
+            }
+        }
+    }
+}
+
+/// Whether the clause ends with a call to the function itself, passing
+/// `[X | Acc]` at position `idx`, where `Acc` is the clause argument at
+/// the same position.
+fn conses_onto_accumulator(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    def: &FunctionDef,
+    clause: &Clause,
+    idx: usize,
+) -> bool {
+    let acc = match clause.pats.get(idx).and_then(|pat| def_fb[*pat].as_var()) {
+        Some(acc) => acc,
+        None => return false,
+    };
+    let last = match clause.exprs.last() {
+        Some(last) => *last,
+        None => return false,
+    };
+    let args = match &def_fb[last] {
+        Expr::Call {
+            target: CallTarget::Local { name },
+            args,
+        } if args.len() == clause.pats.len()
+            && def_fb.as_atom_name(sema.db, name).as_ref() == Some(def.function.name.name()) =>
+        {
+            args
+        }
+        _ => return false,
+    };
+    match &def_fb[args[idx]] {
+        Expr::List {
+            exprs,
+            tail: Some(tail),
+        } => exprs.len() == 1 && def_fb[*tail].as_var() == Some(acc),
+        _ => false,
+    }
+}
+
+fn make_diagnostic(range: TextRange, acc: &str) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::MissingReverse,
+        format!(
+            "The accumulator `{acc}` is built in reverse order, did you mean `lists:reverse({acc})`?"
+        ),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn accumulator_returned_as_is() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([double/2]).
+
+double([H | T], Acc) -> double(T, [H * 2 | Acc]);
+double([], Acc) -> Acc.
+%%                 ^^^ weak: The accumulator `Acc` is built in reverse order, did you mean `lists:reverse(Acc)`?
+            "#,
+        );
+    }
+
+    #[test]
+    fn accumulator_reversed_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([double/2]).
+
+double([H | T], Acc) -> double(T, [H * 2 | Acc]);
+double([], Acc) -> lists:reverse(Acc).
+            "#,
+        );
+    }
+
+    #[test]
+    fn counter_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([count/2]).
+
+count([_ | T], N) -> count(T, N + 1);
+count([], N) -> N.
+            "#,
+        );
+    }
+
+    #[test]
+    fn other_function_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([collect/2]).
+
+collect([H | T], Acc) -> other(T, [H | Acc]);
+collect([], Acc) -> Acc.
+
+other(T, Acc) -> collect(T, Acc).
+            "#,
+        );
+    }
+}
@@ -16,6 +16,7 @@ use hir::Attribute;
 use text_edit::TextEdit;
 
 use super::Diagnostic;
+use crate::diagnostics::closest_match;
 use crate::diagnostics::RelatedInformation;
 use crate::fix;
 use crate::TextRange;
@@ -77,21 +78,13 @@ const KNOWN_ATTRIBUTES: &[&str] = &[
 ];
 
 fn looks_like_misspelling(attr: &Attribute) -> Option<&str> {
-    let mut suggestions: Vec<(&str, f64)> = KNOWN_ATTRIBUTES
-        .iter()
-        .filter(|&known| &attr.name != known)
-        .filter(|&known| {
-            let close_enough: usize = std::cmp::max(1, std::cmp::min(3, attr.name.len() / 3));
-            triple_accel::levenshtein::rdamerau(attr.name.as_str().as_bytes(), known.as_bytes())
-                <= u32::try_from(close_enough).unwrap()
-        })
-        .map(|&known| (known, strsim::jaro_winkler(&attr.name, known)))
-        .collect::<Vec<(&str, f64)>>();
-    suggestions.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-    suggestions
-        .first()
-        .map(|(suggestion, _similarity)| suggestion)
-        .copied()
+    closest_match(
+        attr.name.as_str(),
+        KNOWN_ATTRIBUTES
+            .iter()
+            .copied()
+            .filter(|known| attr.name.as_str() != *known),
+    )
 }
 
 fn make_diagnostic(
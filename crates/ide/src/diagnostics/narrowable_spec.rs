@@ -0,0 +1,233 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: narrowable_spec
+//!
+//! Return a hint if a spec argument is `term()` or `any()` but every
+//! clause of the function matches that argument against a specific
+//! shape, e.g. `{ok, _}`, suggesting the narrower type derived from the
+//! clause heads. This is opt-in, and there is no fix: the suggestion is
+//! a starting point only.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::AstNode;
+use hir::Body;
+use hir::CallTarget;
+use hir::FunctionDef;
+use hir::InFile;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Pat;
+use hir::PatId;
+use hir::Semantic;
+use hir::TypeExpr;
+use hir::TypeExprId;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+pub(crate) fn narrowable_spec(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    let def_map = sema.def_map(file_id);
+    for (name, spec_def) in def_map.get_specs() {
+        if spec_def.file.file_id != file_id {
+            continue;
+        }
+        let fun_def = match def_map.get_function(name) {
+            Some(fun_def) => fun_def,
+            None => continue,
+        };
+        let spec_body = sema.db.spec_body(InFile::new(file_id, spec_def.spec_id));
+        // With several signatures each argument type has a different role.
+        let sig = match spec_body.sigs.as_slice() {
+            [sig] => sig,
+            _ => continue,
+        };
+        let ast_args: Vec<_> = match spec_def
+            .source(sema.db.upcast())
+            .sigs()
+            .next()
+            .and_then(|sig| sig.args())
+        {
+            Some(args) => args.args().collect(),
+            None => continue,
+        };
+        if ast_args.len() != sig.args.len() {
+            continue;
+        }
+        let def_fb = fun_def.in_function_body(sema.db, fun_def);
+        for (pos, (ty, ast_arg)) in sig.args.iter().zip(ast_args.iter()).enumerate() {
+            if !is_top_type(sema, &spec_body.body, *ty) {
+                continue;
+            }
+            if let Some(narrower) = matched_type(sema, &def_fb, pos) {
+                diags.push(
+                    Diagnostic::new(
+                        DiagnosticCode::NarrowableSpec,
+                        format!(
+                            "Argument {} of `{name}` is always matched as `{narrower}`, the spec could be narrowed",
+                            pos + 1
+                        ),
+                        ast_arg.syntax().text_range(),
+                    )
+                    .severity(Severity::WeakWarning),
+                );
+            }
+        }
+    }
+}
+
+/// `term()` or `any()`, possibly annotated, e.g. `Reply :: term()`.
+fn is_top_type(sema: &Semantic, body: &Body, ty: TypeExprId) -> bool {
+    match &body[ty] {
+        TypeExpr::AnnType { ty, .. } => is_top_type(sema, body, *ty),
+        TypeExpr::Call {
+            target: CallTarget::Local { name },
+            args,
+        } if args.is_empty() => match body[*name].as_atom() {
+            Some(name) => matches!(sema.db.lookup_atom(name).as_str(), "term" | "any"),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// The union of the shapes the clauses match the argument against, if
+/// none of them accepts anything.
+fn matched_type(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    pos: usize,
+) -> Option<String> {
+    let mut shapes: Vec<String> = Vec::new();
+    for (_clause_id, clause) in def_fb.clauses() {
+        let shape = shape(sema, def_fb, *clause.pats.get(pos)?)?;
+        if !shapes.contains(&shape) {
+            shapes.push(shape);
+        }
+    }
+    if shapes.is_empty() || shapes.iter().any(|shape| shape == "term()") {
+        return None;
+    }
+    Some(shapes.join(" | "))
+}
+
+/// The shape of a clause head argument, `None` if it is a variable.
+fn shape(sema: &Semantic, def_fb: &InFunctionBody<&FunctionDef>, pat_id: PatId) -> Option<String> {
+    match &def_fb[pat_id] {
+        Pat::Var(_) => None,
+        Pat::Match { lhs, rhs } => match &def_fb[*lhs] {
+            Pat::Var(_) => shape(sema, def_fb, *rhs),
+            _ => shape(sema, def_fb, *lhs),
+        },
+        Pat::MacroCall { expansion, .. } => shape(sema, def_fb, *expansion),
+        _ => Some(pat_type(sema, def_fb, pat_id)),
+    }
+}
+
+/// A type covering everything the pattern matches.
+fn pat_type(sema: &Semantic, def_fb: &InFunctionBody<&FunctionDef>, pat_id: PatId) -> String {
+    match &def_fb[pat_id] {
+        Pat::Literal(Literal::Atom(atom)) => sema.db.lookup_atom(*atom).to_quoted_string(),
+        Pat::Literal(Literal::Integer(_)) | Pat::RecordIndex { .. } => "integer()".to_string(),
+        Pat::Literal(Literal::Float(_)) => "float()".to_string(),
+        Pat::Literal(Literal::Char(_)) => "char()".to_string(),
+        Pat::Literal(Literal::String(_)) => "string()".to_string(),
+        Pat::Tuple { pats } => format!(
+            "{{{}}}",
+            pats.iter()
+                .map(|pat| pat_type(sema, def_fb, *pat))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Pat::List { pats, tail: None } if pats.is_empty() => "[]".to_string(),
+        Pat::List { pats, .. } if pats.is_empty() => "list()".to_string(),
+        Pat::List { .. } => "nonempty_list()".to_string(),
+        Pat::Binary { .. } => "binary()".to_string(),
+        Pat::Map { .. } => "map()".to_string(),
+        Pat::Record { name, .. } => {
+            format!("#{}{{}}", sema.db.lookup_atom(*name).to_quoted_string())
+        }
+        Pat::Match { lhs, rhs } => match &def_fb[*lhs] {
+            Pat::Var(_) => pat_type(sema, def_fb, *rhs),
+            _ => pat_type(sema, def_fb, *lhs),
+        },
+        Pat::MacroCall { expansion, .. } => pat_type(sema, def_fb, *expansion),
+        Pat::Var(_) | Pat::Missing | Pat::UnaryOp { .. } | Pat::BinaryOp { .. } => {
+            "term()".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    fn config() -> DiagnosticsConfig<'static> {
+        DiagnosticsConfig::default()
+            .enable(DiagnosticCode::NarrowableSpec)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec)
+    }
+
+    #[test]
+    fn argument_always_matched() {
+        check_diagnostics_with_config(
+            config(),
+            r#"
+-module(main).
+-export([foo/2, bar/1]).
+
+-spec foo(term(), any()) -> ok.
+%%        ^^^^^^ weak: Argument 1 of `foo/2` is always matched as `{ok, term()} | {error, atom}`, the spec could be narrowed
+foo({ok, _}, _) -> ok;
+foo({error, atom}, _) -> ok.
+
+-spec bar(Reply :: term()) -> ok.
+%%        ^^^^^^^^^^^^^^^ weak: Argument 1 of `bar/1` is always matched as `[] | nonempty_list()`, the spec could be narrowed
+bar([]) -> ok;
+bar(R = [_ | _]) -> R, ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn catch_all_or_specific_type_not_reported() {
+        check_diagnostics_with_config(
+            config(),
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+-spec foo(term()) -> ok.
+foo({ok, _}) -> ok;
+foo(_Other) -> ok.
+
+-spec bar({ok, integer()}) -> ok.
+bar({ok, _}) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn not_reported_when_disabled() {
+        check_diagnostics_with_config(
+            DiagnosticsConfig::default().disable(DiagnosticCode::MissingCompileWarnMissingSpec),
+            r#"
+-module(main).
+-export([foo/1]).
+
+-spec foo(term()) -> ok.
+foo({ok, _}) -> ok.
+            "#,
+        );
+    }
+}
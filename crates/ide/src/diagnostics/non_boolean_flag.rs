@@ -0,0 +1,224 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: non_boolean_flag
+//!
+//! Return a hint if an atom other than `true` or `false`, e.g. `yes` or
+//! `ok`, is used where a boolean is expected: as a guard, or as an
+//! operand of a boolean operator. Such a guard never succeeds, so an
+//! `if` relying on it fails with `if_clause`, and an operator raises
+//! `badarg`.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::LogicOp;
+use elp_syntax::ast::UnaryOp;
+use fxhash::FxHashSet;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Semantic;
+use hir::Strategy;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+pub(crate) fn non_boolean_flag(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    // Expressions used as booleans, each reported once.
+    let mut flags: FxHashSet<ExprId> = FxHashSet::default();
+
+    // Guards of the function clauses, and of the case, receive, try, if
+    // and fun clauses in their bodies.
+    let mut guards: Vec<ExprId> = def_fb
+        .clauses()
+        .flat_map(|(_clause_id, clause)| clause.guards.iter().flatten().copied())
+        .collect();
+    def_fb.fold_function(
+        (),
+        &mut |(), _, ctx| match ctx.expr {
+            Expr::Case { clauses, .. } | Expr::Receive { clauses, .. } => {
+                guards.extend(clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+            }
+            Expr::Try {
+                of_clauses,
+                catch_clauses,
+                ..
+            } => {
+                guards.extend(of_clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+                guards.extend(catch_clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+            }
+            Expr::If { clauses } => {
+                guards.extend(clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+            }
+            Expr::Closure { clauses, .. } => {
+                guards.extend(clauses.iter().flat_map(|c| c.guards.iter().flatten()));
+            }
+            Expr::BinaryOp {
+                lhs,
+                rhs,
+                op: BinaryOp::LogicOp(op),
+            } => {
+                flags.insert(lhs);
+                // `Cond andalso Value` is a common idiom outside guards,
+                // only the left side is required to be a boolean there.
+                if !matches!(op, LogicOp::And { lazy: true } | LogicOp::Or { lazy: true }) {
+                    flags.insert(rhs);
+                }
+            }
+            Expr::UnaryOp {
+                expr,
+                op: UnaryOp::Not,
+            } => {
+                flags.insert(expr);
+            }
+            _ => {}
+        },
+        &mut |(), _, _| (),
+    );
+
+    // In a guard, both sides of any boolean operator must be booleans.
+    for guard in guards {
+        flags.insert(guard);
+        def_fb.fold_expr(
+            Strategy::TopDown,
+            guard,
+            (),
+            &mut |(), ctx| match ctx.expr {
+                Expr::BinaryOp {
+                    lhs,
+                    rhs,
+                    op: BinaryOp::LogicOp(_),
+                } => {
+                    flags.insert(lhs);
+                    flags.insert(rhs);
+                }
+                Expr::UnaryOp {
+                    expr,
+                    op: UnaryOp::Not,
+                } => {
+                    flags.insert(expr);
+                }
+                _ => {}
+            },
+            &mut |(), _| (),
+        );
+    }
+
+    let mut flags: Vec<ExprId> = flags.into_iter().collect();
+    flags.sort_by_key(|expr| u32::from(expr.into_raw()));
+    for flag in flags {
+        let atom = match non_boolean_atom(sema, &def_fb, flag) {
+            Some(atom) => atom,
+            None => continue,
+        };
+        if let Some(range) = def_fb.range_for_expr(sema.db, flag) {
+            diags.push(
+                Diagnostic::new(
+                    DiagnosticCode::NonBooleanFlag,
+                    format!("`{atom}` is used as a boolean, but only `true` and `false` are"),
+                    range,
+                )
+                .severity(Severity::WeakWarning),
+            );
+        }
+    }
+}
+
+/// The atom `expr` evaluates to, if it is clearly not a boolean.
+fn non_boolean_atom(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    expr: ExprId,
+) -> Option<String> {
+    match &def_fb[expr] {
+        Expr::MacroCall { expansion, .. } => non_boolean_atom(sema, def_fb, *expansion),
+        _ => {
+            let atom = def_fb.as_atom_name(sema.db, &expr)?;
+            match atom.as_str() {
+                "true" | "false" => None,
+                _ => Some(atom.to_quoted_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn atoms_used_as_booleans() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(X) ->
+    if
+        yes -> X;
+%%      ^^^ weak: `yes` is used as a boolean, but only `true` and `false` are
+        true -> ok
+    end.
+
+bar(X) when no -> X;
+%%          ^^ weak: `no` is used as a boolean, but only `true` and `false` are
+bar(X) -> ok andalso X.
+%%        ^^ weak: `ok` is used as a boolean, but only `true` and `false` are
+            "#,
+        );
+    }
+
+    #[test]
+    fn booleans_and_values_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(X) when X =:= yes; X -> true;
+foo(X) -> X andalso ok.
+
+bar(X) ->
+    if
+        X, not false -> true orelse X;
+        true -> ok
+    end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn atom_from_macro() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1]).
+-define(ENABLED, yes).
+
+foo(X) when ?ENABLED -> X.
+%%          ^^^^^^^^ weak: `yes` is used as a boolean, but only `true` and `false` are
+            "#,
+        );
+    }
+}
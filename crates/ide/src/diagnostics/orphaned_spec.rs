@@ -0,0 +1,162 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: orphaned-spec
+//
+// Return a warning if a -spec in an .erl file has no function with the
+// same name and arity in the module, e.g. left over after deleting the
+// function, and offer to delete the spec.
+
+use elp_ide_assists::Assist;
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::AstNode;
+use elp_syntax::SyntaxKind;
+use elp_syntax::TextRange;
+use elp_syntax::TextSize;
+use hir::Semantic;
+use text_edit::TextEdit;
+
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+use crate::Diagnostic;
+
+pub(crate) fn orphaned_spec(
+    acc: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+    ext: Option<&str>,
+) -> Option<()> {
+    // A header does not know the functions of the modules including it.
+    if Some("erl") != ext {
+        return Some(());
+    }
+    let def_map = sema.def_map(file_id);
+    for (name, def) in def_map.get_specs() {
+        if def.file.file_id != file_id || def_map.get_function(name).is_some() {
+            continue;
+        }
+        let source = def.source(sema.db.upcast());
+        let spec_syntax = source.syntax();
+        // If after the spec there's a new line, drop it
+        let spec_range = match spec_syntax
+            .last_token()
+            .and_then(|token| token.next_token())
+        {
+            Some(next_token)
+                if next_token.kind() == SyntaxKind::WHITESPACE
+                    && next_token.text().starts_with('\n') =>
+            {
+                let range = spec_syntax.text_range();
+                TextRange::new(range.start(), range.end() + TextSize::from(1))
+            }
+            _ => spec_syntax.text_range(),
+        };
+        let name_range = match source.fun() {
+            Some(fun) => fun.syntax().text_range(),
+            None => spec_syntax.text_range(),
+        };
+        acc.push(make_diagnostic(
+            file_id,
+            spec_range,
+            name_range,
+            &name.to_string(),
+        ));
+    }
+    Some(())
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    spec_range: TextRange,
+    name_range: TextRange,
+    name: &str,
+) -> Diagnostic {
+    Diagnostic::warning(
+        DiagnosticCode::OrphanedSpec,
+        name_range,
+        format!("Spec for undefined function ({name})"),
+    )
+    .with_fixes(Some(vec![delete_orphaned_spec(file_id, spec_range, name)]))
+}
+
+fn delete_orphaned_spec(file_id: FileId, range: TextRange, name: &str) -> Assist {
+    let mut builder = TextEdit::builder();
+    builder.delete(range);
+    let edit = builder.finish();
+    fix(
+        "delete_orphaned_spec",
+        &format!("Delete spec for undefined function ({name})"),
+        SourceChange::from_text_edit(file_id, edit),
+        range,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn test_orphaned_spec() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1]).
+
+-spec foo(integer()) -> ok.
+foo(_) -> ok.
+
+-spec foo(integer(), integer()) -> ok.
+%%    ^^^ 💡 warning: Spec for undefined function (foo/2)
+-spec bar() -> ok.
+%%    ^^^ 💡 warning: Spec for undefined function (bar/0)
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_orphaned_spec_fix() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/1]).
+
+-spec b~ar() -> ok.
+-spec foo(integer()) -> ok.
+foo(_) -> ok.
+            "#,
+            r#"
+-module(main).
+-export([foo/1]).
+
+-spec foo(integer()) -> ok.
+foo(_) -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_spec_in_header_not_reported() {
+        check_diagnostics(
+            r#"
+//- /include/defs.hrl include_path:/include
+-define(OK, ok).
+-spec foo() -> ok.
+//- /src/main.erl
+-module(main).
+-include("defs.hrl").
+-export([foo/0]).
+
+foo() -> ?OK.
+            "#,
+        );
+    }
+}
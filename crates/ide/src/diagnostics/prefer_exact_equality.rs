@@ -0,0 +1,307 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: prefer_exact_equality
+//!
+//! Return a hint if `==` or `/=` is used where the result cannot differ
+//! from `=:=` or `=/=`, i.e. when comparing with an atom, or when both
+//! sides are integers, and offer to switch to the exact operator.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::ast::ArithOp;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::CompOp;
+use elp_syntax::ast::UnaryOp;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Semantic;
+use hir::Strategy;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+/// Functions known to always return an integer, when they return.
+const INTEGER_FUNCTIONS: &[&str] = &[
+    "length",
+    "byte_size",
+    "bit_size",
+    "tuple_size",
+    "map_size",
+    "size",
+    "round",
+    "trunc",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperandType {
+    Atom,
+    Integer,
+}
+
+pub(crate) fn prefer_exact_equality(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+
+    // Comparisons in guards are as common as in bodies, so visit both.
+    let roots: Vec<ExprId> = def_fb
+        .clauses()
+        .flat_map(|(_clause_id, clause)| {
+            clause
+                .guards
+                .iter()
+                .flatten()
+                .chain(clause.exprs.iter())
+                .copied()
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    for root in roots {
+        def_fb.fold_expr(
+            Strategy::TopDown,
+            root,
+            (),
+            &mut |(), ctx| {
+                let (lhs, rhs, negated) = match ctx.expr {
+                    Expr::BinaryOp {
+                        lhs,
+                        rhs,
+                        op:
+                            BinaryOp::CompOp(CompOp::Eq {
+                                strict: false,
+                                negated,
+                            }),
+                    } => (lhs, rhs, negated),
+                    _ => return,
+                };
+                let lhs_ty = operand_type(sema, &def_fb, lhs);
+                let rhs_ty = operand_type(sema, &def_fb, rhs);
+                // An atom is only ever equal to itself, whatever the other
+                // side is. Integers only compare differently to floats.
+                let exact = lhs_ty == Some(OperandType::Atom)
+                    || rhs_ty == Some(OperandType::Atom)
+                    || (lhs_ty == Some(OperandType::Integer)
+                        && rhs_ty == Some(OperandType::Integer));
+                if !exact {
+                    return;
+                }
+                let range = match def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                    Some(range) => range,
+                    None => return,
+                };
+                let op_range = body_map
+                    .expr(ctx.expr_id)
+                    .and_then(|ptr| ptr.to_node(&source_file))
+                    .and_then(|expr| match expr {
+                        ast::Expr::BinaryOpExpr(expr) => Some(expr.op()?.1.text_range()),
+                        _ => None,
+                    });
+                diags.push(make_diagnostic(def.file.file_id, range, op_range, negated));
+            },
+            &mut |(), _| (),
+        );
+    }
+}
+
+fn operand_type(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    expr_id: ExprId,
+) -> Option<OperandType> {
+    match &def_fb[expr_id] {
+        Expr::Literal(Literal::Atom(_)) => Some(OperandType::Atom),
+        Expr::Literal(Literal::Integer(_)) | Expr::Literal(Literal::Char(_)) => {
+            Some(OperandType::Integer)
+        }
+        Expr::UnaryOp {
+            op: UnaryOp::Not, ..
+        } => Some(OperandType::Atom),
+        Expr::UnaryOp {
+            op: UnaryOp::Bnot, ..
+        } => Some(OperandType::Integer),
+        Expr::UnaryOp { expr, .. } => match operand_type(sema, def_fb, *expr)? {
+            OperandType::Integer => Some(OperandType::Integer),
+            OperandType::Atom => None,
+        },
+        Expr::BinaryOp {
+            op: BinaryOp::CompOp(_),
+            ..
+        } => Some(OperandType::Atom),
+        Expr::BinaryOp {
+            lhs,
+            rhs,
+            op: BinaryOp::ArithOp(op),
+        } => match op {
+            ArithOp::Div
+            | ArithOp::Rem
+            | ArithOp::Band
+            | ArithOp::Bor
+            | ArithOp::Bxor
+            | ArithOp::Bsr
+            | ArithOp::Bsl => Some(OperandType::Integer),
+            ArithOp::FloatDiv => None,
+            ArithOp::Add | ArithOp::Mul | ArithOp::Sub => {
+                let both_integers = operand_type(sema, def_fb, *lhs) == Some(OperandType::Integer)
+                    && operand_type(sema, def_fb, *rhs) == Some(OperandType::Integer);
+                both_integers.then_some(OperandType::Integer)
+            }
+        },
+        Expr::Call { target, args } if args.len() == 1 => {
+            let name = match target {
+                CallTarget::Local { name } => def_fb.as_atom_name(sema.db, name)?,
+                CallTarget::Remote { module, name } => {
+                    if def_fb.as_atom_name(sema.db, module)?.as_str() != "erlang" {
+                        return None;
+                    }
+                    def_fb.as_atom_name(sema.db, name)?
+                }
+            };
+            INTEGER_FUNCTIONS
+                .contains(&name.as_str())
+                .then_some(OperandType::Integer)
+        }
+        Expr::MacroCall { expansion, .. } => operand_type(sema, def_fb, *expansion),
+        _ => None,
+    }
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    op_range: Option<TextRange>,
+    negated: bool,
+) -> Diagnostic {
+    let (op, exact) = if negated {
+        ("/=", "=/=")
+    } else {
+        ("==", "=:=")
+    };
+    let diag = Diagnostic::new(
+        DiagnosticCode::PreferExactEquality,
+        format!("Prefer `{exact}` to `{op}` here, the result is the same"),
+        range,
+    )
+    .severity(Severity::WeakWarning);
+
+    match op_range {
+        Some(op_range) => {
+            let mut edit_builder = TextEdit::builder();
+            edit_builder.replace(op_range, exact.to_string());
+            let edit = edit_builder.finish();
+            diag.with_fixes(Some(vec![fix(
+                "use_exact_equality",
+                &format!("Use `{exact}`"),
+                SourceChange::from_text_edit(file_id, edit),
+                range,
+            )]))
+        }
+        None => diag,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+    use crate::tests::check_fix_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::PreferExactEquality)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[track_caller]
+    fn check_fix(fixture_before: &str, fixture_after: &str) {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::PreferExactEquality)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_fix_with_config(config, fixture_before, fixture_after)
+    }
+
+    #[test]
+    fn compare_with_atom() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(X) when X == ok -> X;
+%%          ^^^^^^^ 💡 weak: Prefer `=:=` to `==` here, the result is the same
+foo(X) -> X /= error.
+%%        ^^^^^^^^^^ 💡 weak: Prefer `=/=` to `/=` here, the result is the same
+            "#,
+        );
+    }
+
+    #[test]
+    fn compare_integers() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(L) -> length(L) == 2 * 3.
+%%        ^^^^^^^^^^^^^^^^^^ 💡 weak: Prefer `=:=` to `==` here, the result is the same
+            "#,
+        );
+    }
+
+    #[test]
+    fn compare_possible_float_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(X, L) -> {X == 1, length(L) == X / 2, X =:= ok}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_operator() {
+        check_fix(
+            r#"
+-module(main).
+
+foo(X) -> X /~= error.
+            "#,
+            r#"
+-module(main).
+
+foo(X) -> X =/= error.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,282 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: record_element_access
+//!
+//! Return a hint if `element/2` reads a field of a variable known to be a
+//! record, e.g. `element(2, R)` after `#person{} = R`, and offer to use
+//! the named access `R#person.name` instead, which survives the fields
+//! being reordered. The record of the variable is only known if the
+//! clause matches it against a single record, in a pattern or with
+//! `R = #person{...}`.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::TextRange;
+use fxhash::FxHashMap;
+use hir::Atom;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Pat;
+use hir::Semantic;
+use hir::Strategy;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn record_element_access(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let def_map = sema.def_map(def.file.file_id);
+
+    // Variables are scoped to the clause.
+    for (_clause_id, clause) in def_fb.clauses() {
+        let mut bindings: Vec<(String, Atom)> = Vec::new();
+        for pat in &clause.pats {
+            bindings = def_fb.fold_pat(
+                Strategy::TopDown,
+                *pat,
+                bindings,
+                &mut |acc, _| acc,
+                &mut |mut acc, ctx| {
+                    acc.extend(pat_binding(sema, &def_fb, &ctx.pat));
+                    acc
+                },
+            );
+        }
+        for expr in &clause.exprs {
+            bindings = def_fb.fold_expr(
+                Strategy::TopDown,
+                *expr,
+                bindings,
+                &mut |mut acc, ctx| {
+                    if let Expr::Match { lhs, rhs } = ctx.expr {
+                        if let (Pat::Var(var), Expr::Record { name, .. }) =
+                            (&def_fb[lhs], &def_fb[rhs])
+                        {
+                            acc.push((var.as_string(sema.db.upcast()), *name));
+                        }
+                    }
+                    acc
+                },
+                &mut |mut acc, ctx| {
+                    acc.extend(pat_binding(sema, &def_fb, &ctx.pat));
+                    acc
+                },
+            );
+        }
+
+        // A variable matched against two different records is not known.
+        let mut records: FxHashMap<String, Option<Atom>> = FxHashMap::default();
+        for (var, record) in bindings {
+            let known = records.entry(var).or_insert(Some(record));
+            if *known != Some(record) {
+                *known = None;
+            }
+        }
+        if records.is_empty() {
+            continue;
+        }
+
+        for root in clause.guards.iter().flatten().chain(clause.exprs.iter()) {
+            def_fb.fold_expr(
+                Strategy::TopDown,
+                *root,
+                (),
+                &mut |(), ctx| {
+                    let (index, var) = match element_call(sema, &def_fb, ctx.expr_id) {
+                        Some(call) => call,
+                        None => return,
+                    };
+                    let record = match records.get(&var) {
+                        Some(Some(record)) => sema.db.lookup_atom(*record),
+                        _ => return,
+                    };
+                    // The record name is the first element of the tuple
+                    let field = usize::try_from(index - 2)
+                        .ok()
+                        .and_then(|idx| def_map.get_record(&record)?.field_names(sema.db).nth(idx));
+                    let field = match field {
+                        Some(field) => field,
+                        None => return,
+                    };
+                    if let Some(range) = def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                        diags.push(make_diagnostic(
+                            def.file.file_id,
+                            range,
+                            index,
+                            format!(
+                                "{var}#{}.{}",
+                                record.to_quoted_string(),
+                                field.to_quoted_string()
+                            ),
+                        ));
+                    }
+                },
+                &mut |(), _| (),
+            );
+        }
+    }
+}
+
+/// `R = #rec{...}` or `#rec{...} = R` in a pattern.
+fn pat_binding(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    pat: &Pat,
+) -> Option<(String, Atom)> {
+    match pat {
+        Pat::Match { lhs, rhs } => match (&def_fb[*lhs], &def_fb[*rhs]) {
+            (Pat::Var(var), Pat::Record { name, .. })
+            | (Pat::Record { name, .. }, Pat::Var(var)) => {
+                Some((var.as_string(sema.db.upcast()), *name))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Match `element(N, Var)` or `erlang:element(N, Var)` with an integer
+/// literal `N` past the record name, returning `N` and the variable.
+fn element_call(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    expr_id: ExprId,
+) -> Option<(i128, String)> {
+    match &def_fb[expr_id] {
+        Expr::Call { target, args } => {
+            let name = match target {
+                CallTarget::Local { name } => def_fb.as_atom_name(sema.db, name)?,
+                CallTarget::Remote { module, name } => {
+                    if def_fb.as_atom_name(sema.db, module)?.as_str() != "erlang" {
+                        return None;
+                    }
+                    def_fb.as_atom_name(sema.db, name)?
+                }
+            };
+            if name.as_str() != "element" {
+                return None;
+            }
+            match args.as_slice() {
+                [index, var] => match (&def_fb[*index], &def_fb[*var]) {
+                    (Expr::Literal(Literal::Integer(index)), Expr::Var(var)) if *index >= 2 => {
+                        Some((*index, var.as_string(sema.db.upcast())))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn make_diagnostic(file_id: FileId, range: TextRange, index: i128, access: String) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::RecordElementAccess,
+        format!("`element({index}, ...)` reads a record field by position, use `{access}` instead"),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+    .with_fixes(Some(vec![fix(
+        "use_record_field_access",
+        &format!("Replace with `{access}`"),
+        SourceChange::from_text_edit(file_id, TextEdit::replace(range, access)),
+        range,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn element_of_known_record() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/0]).
+-record(person, {name, age}).
+
+foo(#person{} = P) when element(3, P) > 18 -> element(2, P).
+%%                      ^^^^^^^^^^^^^ 💡 weak: `element(3, ...)` reads a record field by position, use `P#person.age` instead
+%%                                            ^^^^^^^^^^^^^ 💡 weak: `element(2, ...)` reads a record field by position, use `P#person.name` instead
+
+bar() ->
+    P = #person{name = bob},
+    erlang:element(2, P).
+%%  ^^^^^^^^^^^^^^^^^^^^ 💡 weak: `element(2, ...)` reads a record field by position, use `P#person.name` instead
+            "#,
+        );
+    }
+
+    #[test]
+    fn element_of_unknown_record_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/2, baz/1]).
+-record(person, {name, age}).
+-record(pet, {name}).
+
+foo(P) -> element(2, P).
+
+bar(#person{} = P, _) -> element(1, P);
+bar(_, P = #pet{}) -> element(3, P).
+
+baz(X) ->
+    case X of
+        #person{} = P -> ok;
+        #pet{} = P -> ok
+    end,
+    element(2, P).
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_named_access() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/1]).
+-record(person, {name, age}).
+
+foo(#person{} = P) -> ele~ment(3, P).
+            "#,
+            r#"
+-module(main).
+-export([foo/1]).
+-record(person, {name, age}).
+
+foo(#person{} = P) -> P#person.age.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,186 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: record_info_usage
+//!
+//! Return a hint for `record_info(fields, r)` and `record_info(size, r)`,
+//! which silently change meaning when the record changes, and offer to
+//! expand them to the field list or size of the record. This is opt-in,
+//! for teams preferring explicit field handling.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::Semantic;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn record_info_usage(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let def_map = sema.def_map(def.file.file_id);
+    def_fb.fold_function(
+        (),
+        &mut |(), _, ctx| {
+            let (name, args) = match ctx.expr {
+                Expr::Call {
+                    target: CallTarget::Local { name },
+                    args,
+                } => (name, args),
+                _ => return,
+            };
+            match def_fb.as_atom_name(sema.db, &name) {
+                Some(name) if name.as_str() == "record_info" => {}
+                _ => return,
+            }
+            let (info, record) = match args.as_slice() {
+                [info, record] => (
+                    def_fb.as_atom_name(sema.db, info),
+                    def_fb.as_atom_name(sema.db, record),
+                ),
+                _ => return,
+            };
+            let (info, record) = match (info, record) {
+                (Some(info), Some(record)) => (info, record),
+                _ => return,
+            };
+            let field_names: Vec<String> = match def_map.get_record(&record) {
+                Some(record_def) => record_def
+                    .field_names(sema.db)
+                    .map(|field| field.to_quoted_string())
+                    .collect(),
+                None => return,
+            };
+            let expansion = match info.as_str() {
+                "fields" => format!("[{}]", field_names.join(", ")),
+                // The record name is the first element of the tuple
+                "size" => (field_names.len() + 1).to_string(),
+                _ => return,
+            };
+            if let Some(range) = def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                diags.push(make_diagnostic(
+                    def.file.file_id,
+                    range,
+                    info.as_str(),
+                    &record.to_quoted_string(),
+                    expansion,
+                ));
+            }
+        },
+        &mut |(), _, _| (),
+    );
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    info: &str,
+    record: &str,
+    expansion: String,
+) -> Diagnostic {
+    let mut edit_builder = TextEdit::builder();
+    edit_builder.replace(range, expansion.clone());
+    let edit = edit_builder.finish();
+    Diagnostic::new(
+        DiagnosticCode::RecordInfoUsage,
+        format!("`record_info({info}, {record})` changes silently with the record"),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+    .with_fixes(Some(vec![fix(
+        "expand_record_info",
+        &format!("Replace with `{expansion}`"),
+        SourceChange::from_text_edit(file_id, edit),
+        range,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+    use crate::tests::check_fix_with_config;
+
+    fn config() -> DiagnosticsConfig<'static> {
+        DiagnosticsConfig::default()
+            .enable(DiagnosticCode::RecordInfoUsage)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec)
+    }
+
+    #[test]
+    fn record_info_reported() {
+        check_diagnostics_with_config(
+            config(),
+            r#"
+-module(main).
+-export([foo/0, bar/0]).
+-record(person, {name, age = 0}).
+
+foo() -> record_info(fields, person).
+%%       ^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: `record_info(fields, person)` changes silently with the record
+
+bar() -> {record_info(size, person), record_info(fields, unknown)}.
+%%        ^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: `record_info(size, person)` changes silently with the record
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_expands_fields() {
+        check_fix_with_config(
+            config(),
+            r#"
+-module(main).
+-export([foo/0]).
+-record(person, {name, age = 0}).
+
+foo() -> record_i~nfo(fields, person).
+            "#,
+            r#"
+-module(main).
+-export([foo/0]).
+-record(person, {name, age = 0}).
+
+foo() -> [name, age].
+            "#,
+        );
+    }
+
+    #[test]
+    fn not_reported_by_default() {
+        crate::tests::check_diagnostics(
+            r#"
+-module(main).
+-export([foo/0]).
+-record(person, {name}).
+
+foo() -> record_info(fields, person).
+            "#,
+        );
+    }
+}
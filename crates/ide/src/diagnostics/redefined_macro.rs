@@ -0,0 +1,169 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Diagnostic: redefined-macro
+//
+// Return a warning if a macro defined in an .erl file is defined again
+// later in the file, with the same name and arity and no `-undef` in
+// between. `?FOO` and `?FOO(X)` are different macros. Definitions under
+// different preprocessor conditions, e.g. both branches of an `-ifdef`,
+// are alternatives, and the ones in included headers are not considered.
+
+use std::collections::hash_map::Entry;
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use fxhash::FxHashMap;
+use hir::MacroName;
+use hir::PPConditionId;
+use hir::PPDirective;
+use hir::Semantic;
+
+use crate::diagnostics::DiagnosticCode;
+use crate::diagnostics::RelatedInformation;
+use crate::diagnostics::Severity;
+use crate::Diagnostic;
+
+pub(crate) fn redefined_macro(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+    ext: Option<&str>,
+) {
+    if ext != Some("erl") {
+        return;
+    }
+    let form_list = sema.db.file_form_list(file_id);
+    let source_file = sema.parse(file_id).value;
+    // The name of the latest definition of each macro, under each
+    // condition.
+    let mut defined: FxHashMap<(MacroName, Option<PPConditionId>), TextRange> =
+        FxHashMap::default();
+    for (_idx, directive) in form_list.pp_stack().iter() {
+        match directive {
+            PPDirective::Define(idx) => {
+                let define = &form_list[*idx];
+                let range = match define.form_id.get(&source_file).name() {
+                    Some(name) => name.syntax().text_range(),
+                    None => continue,
+                };
+                match defined.entry((define.name.clone(), define.cond)) {
+                    Entry::Occupied(mut prior) => {
+                        diags.push(make_diagnostic(&define.name, range, *prior.get()));
+                        prior.insert(range);
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(range);
+                    }
+                }
+            }
+            // `-undef` removes the macro whatever its arity
+            PPDirective::Undef { name, .. } => {
+                defined.retain(|(macro_name, _), _| macro_name.name() != name)
+            }
+            PPDirective::Include(_) => {}
+        }
+    }
+}
+
+fn make_diagnostic(name: &MacroName, range: TextRange, prior: TextRange) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::RedefinedMacro,
+        format!("Macro ({name}) is already defined"),
+        range,
+    )
+    .severity(Severity::Warning)
+    .with_related(Some(vec![RelatedInformation {
+        range: prior,
+        message: "Previous definition".to_string(),
+    }]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    /// The earlier definitions are also unused, which is not what these
+    /// tests are about.
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default()
+            .disable(DiagnosticCode::UnusedMacro)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[test]
+    fn redefined_macro() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([main/0]).
+-define(FOO, 1).
+-define(FOO, 2).
+     %% ^^^ warning: Macro (FOO) is already defined
+main() -> ?FOO.
+            "#,
+        );
+    }
+
+    #[test]
+    fn redefined_macro_with_arg() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([main/0]).
+-define(FOO, 1).
+-define(FOO(X), X).
+-define(FOO(Y), Y).
+     %% ^^^^^^ warning: Macro (FOO/1) is already defined
+main() -> {?FOO, ?FOO(1)}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn redefined_macro_after_undef_or_in_other_branch() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([main/0]).
+-define(FOO, 1).
+-undef(FOO).
+-define(FOO, 2).
+-ifdef(TEST).
+-define(BAR, 1).
+-else.
+-define(BAR, 2).
+-endif.
+main() -> {?FOO, ?BAR}.
+            "#,
+        );
+    }
+
+    #[test]
+    fn redefined_macro_from_header_not_reported() {
+        check_diagnostics(
+            r#"
+//- /src/foo.hrl
+-define(FOO, 1).
+-define(BAR, 1).
+//- /src/main.erl
+-module(main).
+-include("foo.hrl").
+-export([main/0]).
+-define(FOO, 2).
+main() -> {?FOO, ?BAR}.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,260 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: redundant_catch
+//!
+//! Return a diagnostic if a `catch` or `try ... catch` protects an
+//! expression that can never raise an exception, and offer to unwrap it.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Semantic;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn redundant_catch(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_catches(diags, sema, def)
+            }
+        });
+}
+
+fn process_catches(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+
+    def_fb.fold_function(
+        (),
+        &mut |_acc, _, ctx| {
+            let protected = match ctx.expr {
+                Expr::Catch { expr } => Some(expr),
+                // A `try` with `of` clauses or an `after` block cannot be
+                // simply unwrapped, so leave those alone.
+                Expr::Try {
+                    exprs,
+                    of_clauses,
+                    after,
+                    ..
+                } if of_clauses.is_empty() && after.is_empty() => match exprs.as_slice() {
+                    [expr] => Some(*expr),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(protected) = protected {
+                if cannot_raise(&def_fb, protected) {
+                    if let Some(range) = def_fb.range_for_expr(sema.db, ctx.expr_id) {
+                        // Only offer the fix if both the catch and the
+                        // protected expression are in the source,
+                        // not in a macro expansion.
+                        let unwrap = body_map
+                            .expr(ctx.expr_id)
+                            .and_then(|ptr| ptr.to_node(&source_file))
+                            .and_then(|catch_ast| {
+                                let protected_ast = body_map
+                                    .expr(protected)
+                                    .and_then(|ptr| ptr.to_node(&source_file))?;
+                                Some((
+                                    catch_ast.syntax().text_range(),
+                                    protected_ast.syntax().text().to_string(),
+                                ))
+                            });
+                        diags.push(make_diagnostic(def.file.file_id, range, unwrap));
+                    }
+                }
+            }
+        },
+        &mut |_acc, _, _| (),
+    );
+}
+
+/// Conservatively decide whether evaluating the expression can raise an
+/// exception. Only expressions built from literals, variables and
+/// constructors that never fail qualify.
+fn cannot_raise(def_fb: &InFunctionBody<&FunctionDef>, expr_id: ExprId) -> bool {
+    match &def_fb[expr_id] {
+        Expr::Literal(_) => true,
+        Expr::Var(_) => true,
+        Expr::Closure { .. } => true,
+        Expr::Tuple { exprs } => exprs.iter().all(|expr| cannot_raise(def_fb, *expr)),
+        Expr::List { exprs, tail } => {
+            exprs.iter().all(|expr| cannot_raise(def_fb, *expr))
+                && tail.iter().all(|expr| cannot_raise(def_fb, *expr))
+        }
+        Expr::Map { fields } => fields
+            .iter()
+            .all(|(k, v)| cannot_raise(def_fb, *k) && cannot_raise(def_fb, *v)),
+        Expr::Record { fields, .. } => fields
+            .iter()
+            .all(|(_name, value)| cannot_raise(def_fb, *value)),
+        Expr::MacroCall { expansion, .. } => cannot_raise(def_fb, *expansion),
+        // Binary construction can raise `badarg`, everything else may
+        // call out, fail to match or fail on a type error.
+        _ => false,
+    }
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    unwrap: Option<(TextRange, String)>,
+) -> Diagnostic {
+    let diag = Diagnostic::new(
+        DiagnosticCode::RedundantCatch,
+        "Redundant catch: the expression cannot raise an exception",
+        range,
+    )
+    .severity(Severity::WeakWarning);
+
+    match unwrap {
+        Some((catch_range, replacement)) => {
+            let mut edit_builder = TextEdit::builder();
+            edit_builder.replace(catch_range, replacement);
+            let edit = edit_builder.finish();
+            diag.with_fixes(Some(vec![fix(
+                "unwrap_redundant_catch",
+                "Remove redundant catch",
+                SourceChange::from_text_edit(file_id, edit),
+                range,
+            )]))
+        }
+        None => diag,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn catch_literal() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(X) ->
+    Y = catch {ok, X},
+    %%  ^^^^^^^^^^^^^ 💡 weak: Redundant catch: the expression cannot raise an exception
+    Y.
+            "#,
+        );
+    }
+
+    #[test]
+    fn try_literal() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(X) ->
+    try [X, 1, #{a => b}] catch _:_ -> error end.
+ %% ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: Redundant catch: the expression cannot raise an exception
+            "#,
+        );
+    }
+
+    #[test]
+    fn catch_call_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(X) ->
+    catch bar(X).
+
+bar(X) -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn try_with_of_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(X) ->
+    try X of
+        ok -> ok
+    catch _:_ -> error
+    end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn catch_arithmetic_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(X) ->
+    catch X + 1.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_catch() {
+        check_fix(
+            r#"
+-module(main).
+
+foo(X) ->
+    Y = cat~ch {ok, X},
+    Y.
+            "#,
+            r#"
+-module(main).
+
+foo(X) ->
+    Y = {ok, X},
+    Y.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_try() {
+        check_fix(
+            r#"
+-module(main).
+
+foo(X) ->
+    t~ry {ok, X}
+    catch _:_ -> error
+    end.
+            "#,
+            r#"
+-module(main).
+
+foo(X) ->
+    {ok, X}.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,267 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: redundant_conversion
+//!
+//! Return a hint if a conversion BIF is applied to the result of another
+//! one and the pair is known to be redundant, e.g.
+//! `list_to_binary(binary_to_list(B))`, which gives back `B`, or
+//! `iolist_to_binary(list_to_binary(L))`, where the outer call has
+//! nothing left to do. Offer to collapse the pair. Only the curated pairs
+//! in `CONVERSIONS` are reported: `binary_to_list(list_to_binary(L))` is
+//! not, since it flattens an iolist `L`.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::ast::in_erlang_module;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::DefMap;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Name;
+use hir::NameArity;
+use hir::Semantic;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+/// What the outer and inner call of a pair collapse to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Collapse {
+    /// The round trip gives back the argument of the inner call.
+    Argument,
+    /// The inner call already returns what the outer one would.
+    Inner,
+}
+
+/// The redundant `(outer, inner)` pairs of `erlang` BIFs, all of arity 1.
+const CONVERSIONS: &[(&str, &str, Collapse)] = &[
+    ("list_to_binary", "binary_to_list", Collapse::Argument),
+    ("iolist_to_binary", "binary_to_list", Collapse::Argument),
+    ("list_to_atom", "atom_to_list", Collapse::Argument),
+    ("binary_to_atom", "atom_to_binary", Collapse::Argument),
+    ("list_to_integer", "integer_to_list", Collapse::Argument),
+    ("binary_to_integer", "integer_to_binary", Collapse::Argument),
+    ("list_to_tuple", "tuple_to_list", Collapse::Argument),
+    ("iolist_to_binary", "list_to_binary", Collapse::Inner),
+    ("iolist_to_binary", "iolist_to_binary", Collapse::Inner),
+];
+
+pub(crate) fn redundant_conversion(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let def_map = sema.def_map(def.file.file_id);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+    let text = |expr_id: ExprId| -> Option<String> {
+        let expr: ast::Expr = body_map
+            .expr(expr_id)
+            .and_then(|ptr| ptr.to_node(&source_file))?;
+        Some(expr.syntax().text().to_string())
+    };
+
+    def_fb.fold_function(
+        (),
+        &mut |(), _, ctx| {
+            let (outer, inner_id) = match conversion(sema, &def_fb, &def_map, ctx.expr_id) {
+                Some(call) => call,
+                None => return,
+            };
+            let (inner, arg_id) = match conversion(sema, &def_fb, &def_map, inner_id) {
+                Some(call) => call,
+                None => return,
+            };
+            let collapse = CONVERSIONS.iter().find_map(|(o, i, collapse)| {
+                (outer.as_str() == *o && inner.as_str() == *i).then_some(*collapse)
+            });
+            let (collapse, replacement) = match collapse {
+                Some(Collapse::Argument) => (Collapse::Argument, text(arg_id)),
+                Some(Collapse::Inner) => (Collapse::Inner, text(inner_id)),
+                None => return,
+            };
+            if let (Some(range), Some(replacement)) =
+                (def_fb.range_for_expr(sema.db, ctx.expr_id), replacement)
+            {
+                diags.push(make_diagnostic(
+                    def.file.file_id,
+                    range,
+                    &outer,
+                    &inner,
+                    collapse,
+                    replacement,
+                ));
+            }
+        },
+        &mut |(), _, _| (),
+    );
+}
+
+/// Match a call of arity 1 to a BIF, either `erlang:f(X)` or an
+/// auto-imported `f(X)` not defined in the module, returning `f` and `X`.
+fn conversion(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    def_map: &DefMap,
+    expr_id: ExprId,
+) -> Option<(Name, ExprId)> {
+    match &def_fb[expr_id] {
+        Expr::Call { target, args } => {
+            let arg = match args.as_slice() {
+                [arg] => *arg,
+                _ => return None,
+            };
+            let name = match target {
+                CallTarget::Local { name } => {
+                    let name = def_fb.as_atom_name(sema.db, name)?;
+                    if !in_erlang_module(name.as_str(), 1)
+                        || def_map
+                            .get_function(&NameArity::new(name.clone(), 1))
+                            .is_some()
+                    {
+                        return None;
+                    }
+                    name
+                }
+                CallTarget::Remote { module, name } => {
+                    if def_fb.as_atom_name(sema.db, module)?.as_str() != "erlang" {
+                        return None;
+                    }
+                    def_fb.as_atom_name(sema.db, name)?
+                }
+            };
+            Some((name, arg))
+        }
+        _ => None,
+    }
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    outer: &Name,
+    inner: &Name,
+    collapse: Collapse,
+    replacement: String,
+) -> Diagnostic {
+    let message = match collapse {
+        Collapse::Argument => format!(
+            "`{outer}({inner}(...))` converts back to the original value, use the argument directly"
+        ),
+        Collapse::Inner => {
+            format!("`{outer}/1` is redundant, `{inner}/1` already returns a binary")
+        }
+    };
+    Diagnostic::new(DiagnosticCode::RedundantConversion, message, range)
+        .severity(Severity::WeakWarning)
+        .with_fixes(Some(vec![fix(
+            "collapse_conversion",
+            &format!("Replace with `{replacement}`"),
+            SourceChange::from_text_edit(file_id, TextEdit::replace(range, replacement)),
+            range,
+        )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn round_trip_conversions() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1, baz/1]).
+
+foo(B) -> list_to_binary(binary_to_list(B)).
+%%        ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: `list_to_binary(binary_to_list(...))` converts back to the original value, use the argument directly
+
+bar(A) -> erlang:list_to_atom(atom_to_list(A)).
+%%        ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: `list_to_atom(atom_to_list(...))` converts back to the original value, use the argument directly
+
+baz(L) -> iolist_to_binary(list_to_binary(L)).
+%%        ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ 💡 weak: `iolist_to_binary/1` is redundant, `list_to_binary/1` already returns a binary
+            "#,
+        );
+    }
+
+    #[test]
+    fn other_conversions_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1, baz/1, atom_to_list/1]).
+-compile({no_auto_import, [atom_to_list/1]}).
+
+foo(L) -> binary_to_list(list_to_binary(L)).
+
+bar(B) -> {list_to_binary(lists:reverse(binary_to_list(B))), list_to_binary(binary_to_list(B, 1, 2))}.
+
+baz(A) -> list_to_atom(atom_to_list(A)).
+
+atom_to_list(A) -> A.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_round_trip() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(B) -> list_to_bi~nary(binary_to_list(B)).
+            "#,
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(B) -> B.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_redundant_outer() {
+        check_fix(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(L) -> iolist_to_bi~nary(iolist_to_binary(L)).
+            "#,
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(L) -> iolist_to_binary(L).
+            "#,
+        );
+    }
+}
@@ -0,0 +1,239 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: redundant_module_prefix
+//!
+//! Return a hint if a function defined in the module is called with the
+//! module prefix, e.g. `?MODULE:f()` or `main:f()`, and offer to remove
+//! it. A fully qualified call is what makes a process pick up new code
+//! after a hot code reload, so this is opt-in.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Name;
+use hir::NameArity;
+use hir::Semantic;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+pub(crate) fn redundant_module_prefix(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    file_id: FileId,
+) {
+    let module_name = match sema.module_name(file_id) {
+        Some(module_name) => module_name,
+        None => return,
+    };
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def, module_name.as_str())
+            }
+        });
+}
+
+fn process_function(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    def: &FunctionDef,
+    module_name: &str,
+) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+    let def_map = sema.def_map(def.file.file_id);
+
+    def_fb.fold_function(
+        (),
+        &mut |_acc, _, ctx| {
+            let (module, name, arity) = match &ctx.expr {
+                Expr::Call {
+                    target: CallTarget::Remote { module, name },
+                    args,
+                } => (*module, *name, args.len()),
+                _ => return,
+            };
+            if module_atom(sema, &def_fb, module)
+                .as_ref()
+                .map(Name::as_str)
+                != Some(module_name)
+            {
+                return;
+            }
+            let name = match def_fb.as_atom_name(sema.db, &name) {
+                Some(name) => name,
+                None => return,
+            };
+            let function = NameArity::new(name, arity as u32);
+            if def_map.get_function(&function).is_none() {
+                return;
+            }
+            let call = match body_map
+                .expr(ctx.expr_id)
+                .and_then(|ptr| ptr.to_node(&source_file))
+            {
+                Some(ast::Expr::Call(call)) => call,
+                _ => return,
+            };
+            let remote = match call.expr() {
+                Some(ast::Expr::Remote(remote)) => remote,
+                _ => return,
+            };
+            if let (Some(prefix), Some(fun)) = (remote.module(), remote.fun()) {
+                let prefix_range = TextRange::new(
+                    prefix.syntax().text_range().start(),
+                    fun.syntax().text_range().start(),
+                );
+                diags.push(make_diagnostic(
+                    def.file.file_id,
+                    remote.syntax().text_range(),
+                    prefix_range,
+                    &function.to_string(),
+                ));
+            }
+        },
+        &mut |_acc, _, _| (),
+    );
+}
+
+/// The module of a remote call, looking through `?MODULE`.
+fn module_atom(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    module: ExprId,
+) -> Option<Name> {
+    match &def_fb[module] {
+        Expr::MacroCall { expansion, .. } => def_fb.as_atom_name(sema.db, expansion),
+        _ => def_fb.as_atom_name(sema.db, &module),
+    }
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    prefix_range: TextRange,
+    function: &str,
+) -> Diagnostic {
+    let mut edit_builder = TextEdit::builder();
+    edit_builder.delete(prefix_range);
+    let edit = edit_builder.finish();
+    Diagnostic::new(
+        DiagnosticCode::RedundantModulePrefix,
+        format!("Redundant module prefix: `{function}` is defined in this module"),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+    .with_fixes(Some(vec![fix(
+        "remove_redundant_module_prefix",
+        "Remove module prefix",
+        SourceChange::from_text_edit(file_id, edit),
+        range,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+    use crate::tests::check_fix_with_config;
+
+    fn config() -> DiagnosticsConfig<'static> {
+        DiagnosticsConfig::default()
+            .enable(DiagnosticCode::RedundantModulePrefix)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec)
+    }
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        check_diagnostics_with_config(config(), fixture)
+    }
+
+    #[track_caller]
+    fn check_fix(fixture_before: &str, fixture_after: &str) {
+        check_fix_with_config(config(), fixture_before, fixture_after)
+    }
+
+    #[test]
+    fn module_prefix_on_local_call() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+-module(main).
+-export([foo/0, bar/1]).
+
+foo() ->
+    main:bar(1),
+%%  ^^^^^^^^ 💡 weak: Redundant module prefix: `bar/1` is defined in this module
+    ?MODULE:bar(2).
+%%  ^^^^^^^^^^^ 💡 weak: Redundant module prefix: `bar/1` is defined in this module
+
+bar(X) -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn other_module_not_reported() {
+        check_diagnostics(
+            r#"
+//- /src/main.erl
+-module(main).
+-export([foo/0, bar/1]).
+
+foo() ->
+    other:bar(1),
+    main:baz(2).
+
+bar(X) -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_removes_prefix() {
+        check_fix(
+            r#"
+//- /src/main.erl
+-module(main).
+-export([foo/0, bar/1]).
+
+foo() ->
+    ?MOD~ULE:bar(2).
+
+bar(X) -> X.
+            "#,
+            r#"
+-module(main).
+-export([foo/0, bar/1]).
+
+foo() ->
+    bar(2).
+
+bar(X) -> X.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,366 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint/fix: redundant_type_guard
+//!
+//! Return a diagnostic if a type test in a function clause guard is
+//! already implied by the clause pattern, e.g.
+//! `f(<<X:8, _/binary>>) when is_integer(X)`, and offer to remove it.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::source_change::SourceChange;
+use elp_syntax::ast;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use fxhash::FxHashMap;
+use hir::CallTarget;
+use hir::Expr;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Pat;
+use hir::PatId;
+use hir::Semantic;
+use hir::Strategy;
+use hir::Var;
+use text_edit::TextEdit;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::codemod_helpers::statement_range;
+use crate::diagnostics::DiagnosticCode;
+use crate::fix;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImpliedType {
+    Integer,
+    Float,
+    Atom,
+    List,
+    Tuple,
+    Map,
+    Binary,
+    Bitstring,
+}
+
+impl ImpliedType {
+    /// The type test BIFs that always succeed for a value of this type.
+    fn implied_tests(&self) -> &'static [&'static str] {
+        match self {
+            ImpliedType::Integer => &["is_integer", "is_number"],
+            ImpliedType::Float => &["is_float", "is_number"],
+            ImpliedType::Atom => &["is_atom"],
+            ImpliedType::List => &["is_list"],
+            ImpliedType::Tuple => &["is_tuple"],
+            ImpliedType::Map => &["is_map"],
+            ImpliedType::Binary => &["is_binary", "is_bitstring"],
+            ImpliedType::Bitstring => &["is_bitstring"],
+        }
+    }
+}
+
+pub(crate) fn redundant_type_guard(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_clauses(diags, sema, def)
+            }
+        });
+}
+
+fn process_clauses(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+
+    for (_clause_id, clause) in def_fb.clauses() {
+        // Only look at a single guard sequence, with `;` we would have to
+        // reason about each alternative separately.
+        let tests = match clause.guards.as_slice() {
+            [tests] => tests,
+            _ => continue,
+        };
+
+        let mut implied: FxHashMap<Var, Vec<ImpliedType>> = FxHashMap::default();
+        for pat_id in &clause.pats {
+            collect_implied_types(sema, &def_fb, *pat_id, &mut implied);
+        }
+        if implied.is_empty() {
+            continue;
+        }
+
+        for test in tests {
+            let (test_name, var) = match type_test(sema, &def_fb, &def_fb[*test]) {
+                Some(test) => test,
+                None => continue,
+            };
+            let is_implied = implied.get(&var).map_or(false, |types| {
+                types
+                    .iter()
+                    .any(|ty| ty.implied_tests().contains(&test_name.as_str()))
+            });
+            if !is_implied {
+                continue;
+            }
+            let test_ast = match body_map
+                .expr(*test)
+                .and_then(|ptr| ptr.to_node(&source_file))
+            {
+                Some(test_ast) => test_ast,
+                None => continue,
+            };
+            let var_name = var.as_string(sema.db.upcast());
+            let removal = removal_range(&test_ast, tests.len() == 1);
+            diags.push(make_diagnostic(
+                def.file.file_id,
+                test_ast.syntax().text_range(),
+                removal,
+                &test_name,
+                &var_name,
+            ));
+        }
+    }
+}
+
+fn collect_implied_types(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    pat_id: PatId,
+    implied: &mut FxHashMap<Var, Vec<ImpliedType>>,
+) {
+    def_fb.fold_pat(
+        Strategy::TopDown,
+        pat_id,
+        (),
+        &mut |(), _| (),
+        &mut |(), ctx| match ctx.pat {
+            Pat::Match { lhs, rhs } => {
+                for (var, other) in [(lhs, rhs), (rhs, lhs)] {
+                    if let (Some(var), Some(ty)) =
+                        (def_fb[var].as_var(), pattern_type(&def_fb[other]))
+                    {
+                        implied.entry(var).or_default().push(ty);
+                    }
+                }
+            }
+            Pat::Binary { segs } => {
+                for seg in segs {
+                    if let Some(var) = def_fb[seg.elem].as_var() {
+                        if let Some(ty) = segment_type(sema, &seg.tys) {
+                            implied.entry(var).or_default().push(ty);
+                        }
+                    }
+                }
+            }
+            _ => (),
+        },
+    );
+}
+
+/// The type every value matching the pattern has, if it is obvious.
+fn pattern_type(pat: &Pat) -> Option<ImpliedType> {
+    match pat {
+        Pat::Literal(Literal::Integer(_)) | Pat::Literal(Literal::Char(_)) => {
+            Some(ImpliedType::Integer)
+        }
+        Pat::Literal(Literal::Float(_)) => Some(ImpliedType::Float),
+        Pat::Literal(Literal::Atom(_)) => Some(ImpliedType::Atom),
+        Pat::Literal(Literal::String(_)) | Pat::List { .. } => Some(ImpliedType::List),
+        Pat::Tuple { .. } | Pat::Record { .. } => Some(ImpliedType::Tuple),
+        Pat::Map { .. } => Some(ImpliedType::Map),
+        _ => None,
+    }
+}
+
+/// The type of a variable bound by a binary segment, given the segment
+/// type specifiers. Segments without a type are integers.
+fn segment_type(sema: &Semantic, tys: &[hir::Atom]) -> Option<ImpliedType> {
+    let mut ty = ImpliedType::Integer;
+    for atom in tys {
+        match sema.db.lookup_atom(*atom).as_str() {
+            "integer" | "utf8" | "utf16" | "utf32" => ty = ImpliedType::Integer,
+            "float" => ty = ImpliedType::Float,
+            "binary" | "bytes" => ty = ImpliedType::Binary,
+            "bitstring" | "bits" => ty = ImpliedType::Bitstring,
+            "signed" | "unsigned" | "big" | "little" | "native" => (),
+            _ => return None,
+        }
+    }
+    Some(ty)
+}
+
+/// Match `is_xxx(Var)` or `erlang:is_xxx(Var)`.
+fn type_test(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    expr: &Expr,
+) -> Option<(String, Var)> {
+    match expr {
+        Expr::Call { target, args } => {
+            let name = match target {
+                CallTarget::Local { name } => def_fb.as_atom_name(sema.db, name)?,
+                CallTarget::Remote { module, name } => {
+                    if def_fb.as_atom_name(sema.db, module)?.as_str() != "erlang" {
+                        return None;
+                    }
+                    def_fb.as_atom_name(sema.db, name)?
+                }
+            };
+            match args.as_slice() {
+                [arg] => Some((name.to_string(), def_fb[*arg].as_var()?)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// If the test is the only one in the guard, remove the whole `when`
+/// part of the clause, otherwise just the test and its separator.
+fn removal_range(test: &ast::Expr, only_test: bool) -> TextRange {
+    if only_test {
+        let clause = test
+            .syntax()
+            .ancestors()
+            .find_map(ast::FunctionClause::cast);
+        if let Some(clause) = clause {
+            if let (Some(args), Some(guard)) = (clause.args(), clause.guard()) {
+                return TextRange::new(
+                    args.syntax().text_range().end(),
+                    guard.syntax().text_range().end(),
+                );
+            }
+        }
+    }
+    statement_range(test)
+}
+
+fn make_diagnostic(
+    file_id: FileId,
+    range: TextRange,
+    removal: TextRange,
+    test_name: &str,
+    var_name: &str,
+) -> Diagnostic {
+    let mut edit_builder = TextEdit::builder();
+    edit_builder.delete(removal);
+    let edit = edit_builder.finish();
+    Diagnostic::new(
+        DiagnosticCode::RedundantTypeGuard,
+        format!("Redundant guard: `{test_name}({var_name})` is implied by the pattern"),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+    .with_fixes(Some(vec![fix(
+        "remove_redundant_type_guard",
+        "Remove redundant guard",
+        SourceChange::from_text_edit(file_id, edit),
+        range,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::tests::check_diagnostics;
+    use crate::tests::check_fix;
+
+    #[test]
+    fn binary_segment_implies_integer() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(<<X:8, _/binary>>) when is_integer(X) -> X.
+%%                          ^^^^^^^^^^^^^ 💡 weak: Redundant guard: `is_integer(X)` is implied by the pattern
+            "#,
+        );
+    }
+
+    #[test]
+    fn alias_implies_list() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo([_ | _] = L, Y) when is_list(L), Y > 0 -> L.
+%%                       ^^^^^^^^^^ 💡 weak: Redundant guard: `is_list(L)` is implied by the pattern
+            "#,
+        );
+    }
+
+    #[test]
+    fn unconstrained_var_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(X) when is_integer(X) -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn different_type_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(<<X:8/binary>>) when is_integer(X) -> X;
+foo({_} = T) when is_list(T) -> T.
+            "#,
+        );
+    }
+
+    #[test]
+    fn guard_alternatives_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo(<<X:8>>, Y) when is_integer(X); Y -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_removes_when() {
+        check_fix(
+            r#"
+-module(main).
+
+foo(<<X:8, _/binary>>) when is_int~eger(X) -> X.
+            "#,
+            r#"
+-module(main).
+
+foo(<<X:8, _/binary>>) -> X.
+            "#,
+        );
+    }
+
+    #[test]
+    fn fix_removes_single_test() {
+        check_fix(
+            r#"
+-module(main).
+
+foo(#{} = M, Y) when is_m~ap(M), Y > 0 -> M.
+            "#,
+            r#"
+-module(main).
+
+foo(#{} = M, Y) when Y > 0 -> M.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,313 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: stale_pid_send
+//!
+//! Return a hint if a message is sent to a pid that was bound many lines
+//! earlier in the same clause, with no `monitor`, `link` or
+//! `is_process_alive` call on it in between. The process may well have
+//! died in the meantime. This is a heuristic, so it is opt-in.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_ide_db::LineIndex;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Pat;
+use hir::PatId;
+use hir::Semantic;
+use hir::Strategy;
+use hir::Var;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+/// Minimum number of lines between binding a pid and sending to it
+/// before we report.
+const STALE_DISTANCE: u32 = 20;
+
+/// Calls that show the code cares whether the process is still alive.
+const LIVENESS_CHECKS: &[&str] = &["monitor", "link", "is_process_alive"];
+
+pub(crate) fn stale_pid_send(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    let line_index = LineIndex::new(&sema.db.file_text(file_id));
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_clauses(diags, sema, &line_index, def)
+            }
+        });
+}
+
+fn process_clauses(
+    diags: &mut Vec<Diagnostic>,
+    sema: &Semantic,
+    line_index: &LineIndex,
+    def: &FunctionDef,
+) {
+    let mut def_fb = def.in_function_body(sema.db, def);
+    for (_clause_id, clause) in def_fb.clone().clauses() {
+        let mut bindings: Vec<(Var, PatId)> = Vec::new();
+        let mut sends: Vec<(Var, ExprId)> = Vec::new();
+        let mut checks: Vec<(Var, ExprId)> = Vec::new();
+
+        for pat_id in &clause.pats {
+            def_fb.fold_pat(
+                Strategy::TopDown,
+                *pat_id,
+                (),
+                &mut |(), _| (),
+                &mut |(), ctx| {
+                    if let Pat::Var(var) = ctx.pat {
+                        bindings.push((var, ctx.pat_id));
+                    }
+                },
+            );
+        }
+        for expr_id in &clause.exprs {
+            def_fb.fold_expr(
+                Strategy::TopDown,
+                *expr_id,
+                (),
+                &mut |(), ctx| match ctx.expr {
+                    Expr::BinaryOp {
+                        lhs,
+                        op: BinaryOp::Send,
+                        ..
+                    } => {
+                        if let Expr::Var(var) = def_fb[lhs] {
+                            sends.push((var, ctx.expr_id));
+                        }
+                    }
+                    Expr::Call { target, args } => {
+                        if is_liveness_check(sema, &def_fb, &target) {
+                            for arg in args {
+                                if let Expr::Var(var) = def_fb[arg] {
+                                    checks.push((var, ctx.expr_id));
+                                }
+                            }
+                        }
+                    }
+                    _ => (),
+                },
+                &mut |(), ctx| {
+                    if let Pat::Var(var) = ctx.pat {
+                        bindings.push((var, ctx.pat_id));
+                    }
+                },
+            );
+        }
+
+        for (var, send_id) in sends {
+            let send_range = match def_fb.range_for_expr(sema.db, send_id) {
+                Some(range) => range,
+                None => continue,
+            };
+            let binding_range = bindings
+                .iter()
+                .filter(|(bound, _)| *bound == var)
+                .filter_map(|(_, pat_id)| def_fb.range_for_pat(sema.db, *pat_id))
+                .min_by_key(|range| range.start());
+            let binding_range = match binding_range {
+                Some(range) if range.start() < send_range.start() => range,
+                _ => continue,
+            };
+            let distance = line_index.line_col(send_range.start()).line
+                - line_index.line_col(binding_range.start()).line;
+            if distance < STALE_DISTANCE {
+                continue;
+            }
+            let checked = checks.iter().any(|(checked, check_id)| {
+                *checked == var
+                    && def_fb
+                        .range_for_expr(sema.db, *check_id)
+                        .map_or(false, |range| {
+                            binding_range.start() < range.start()
+                                && range.start() < send_range.start()
+                        })
+            });
+            if !checked {
+                let var_name = var.as_string(sema.db.upcast());
+                diags.push(make_diagnostic(send_range, &var_name, distance));
+            }
+        }
+    }
+}
+
+fn is_liveness_check(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    target: &CallTarget<ExprId>,
+) -> bool {
+    let name = match target {
+        CallTarget::Local { name } => def_fb.as_atom_name(sema.db, name),
+        CallTarget::Remote { module, name } => match def_fb.as_atom_name(sema.db, module) {
+            Some(module) if module.as_str() == "erlang" => def_fb.as_atom_name(sema.db, name),
+            _ => None,
+        },
+    };
+    name.map_or(false, |name| LIVENESS_CHECKS.contains(&name.as_str()))
+}
+
+fn make_diagnostic(range: TextRange, var_name: &str, distance: u32) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::StalePidSend,
+        format!(
+            "Message sent to `{var_name}`, bound {distance} lines earlier with no monitor or liveness check"
+        ),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(ra_fixture: &str) {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::StalePidSend)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(config, ra_fixture)
+    }
+
+    #[test]
+    fn send_far_from_spawn() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo() ->
+    Pid = spawn(fun work/0),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    Pid ! hello.
+ %% ^^^^^^^^^^^ weak: Message sent to `Pid`, bound 21 lines earlier with no monitor or liveness check
+
+work() -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn send_after_monitor() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo() ->
+    Pid = spawn(fun work/0),
+    erlang:monitor(process, Pid),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    Pid ! hello.
+
+work() -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn send_close_to_spawn() {
+        check_diagnostics(
+            r#"
+-module(main).
+
+foo() ->
+    Pid = spawn(fun work/0),
+    work(),
+    Pid ! hello.
+
+work() -> ok.
+            "#,
+        );
+    }
+
+    #[test]
+    fn not_enabled_by_default() {
+        crate::tests::check_diagnostics(
+            r#"
+-module(main).
+
+foo() ->
+    Pid = spawn(fun work/0),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    work(),
+    Pid ! hello.
+
+work() -> ok.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,233 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: timer_tc_unit
+//!
+//! Return a hint if the time returned by `timer:tc/1,2,3`, which is in
+//! microseconds, looks like it is taken for milliseconds: it is bound to
+//! a variable named as milliseconds, e.g. `{TimeMs, _} = timer:tc(F)`,
+//! or compared with a threshold below a second, e.g. `Time > 1000`. This
+//! is a heuristic, so it is opt-in.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::ast::BinaryOp;
+use elp_syntax::ast::CompOp;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Literal;
+use hir::Pat;
+use hir::PatId;
+use hir::Semantic;
+use hir::Strategy;
+use hir::Var;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+/// A threshold of a second or more is unlikely to be in milliseconds.
+const MICROS_PER_SECOND: i128 = 1_000_000;
+
+/// `{Time, _} = timer:tc(...)`, with the name of the function.
+struct Binding {
+    var: Var,
+    pat: PatId,
+    function: String,
+}
+
+pub(crate) fn timer_tc_unit(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+
+    // Variables are scoped to the clause.
+    for (_clause_id, clause) in def_fb.clauses() {
+        let mut bindings: Vec<Binding> = Vec::new();
+        let mut comparisons: Vec<(ExprId, Var, i128)> = Vec::new();
+        for expr in &clause.exprs {
+            def_fb.fold_expr(
+                Strategy::TopDown,
+                *expr,
+                (),
+                &mut |(), ctx| match ctx.expr {
+                    Expr::Match { lhs, rhs } => {
+                        if let (Pat::Tuple { pats }, Some(function)) =
+                            (&def_fb[lhs], timer_tc(sema, &def_fb, rhs))
+                        {
+                            if let [time, _result] = pats.as_slice() {
+                                if let Pat::Var(var) = &def_fb[*time] {
+                                    bindings.push(Binding {
+                                        var: *var,
+                                        pat: *time,
+                                        function,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                    Expr::BinaryOp {
+                        lhs,
+                        rhs,
+                        op: BinaryOp::CompOp(CompOp::Ord { .. }),
+                    } => {
+                        let operands = match (&def_fb[lhs], &def_fb[rhs]) {
+                            (Expr::Var(var), Expr::Literal(Literal::Integer(n)))
+                            | (Expr::Literal(Literal::Integer(n)), Expr::Var(var)) => {
+                                Some((*var, *n))
+                            }
+                            _ => None,
+                        };
+                        if let Some((var, n)) = operands {
+                            comparisons.push((ctx.expr_id, var, n));
+                        }
+                    }
+                    _ => {}
+                },
+                &mut |(), _| (),
+            );
+        }
+
+        for binding in bindings {
+            let var_name = binding.var.as_string(sema.db.upcast());
+            if is_millis_name(&var_name) {
+                if let Some(pat) = body_map
+                    .pat(binding.pat)
+                    .and_then(|ptr| ptr.to_node(&source_file))
+                {
+                    diags.push(make_diagnostic(
+                        pat.syntax().text_range(),
+                        format!(
+                            "`{var_name}` is named as milliseconds, but `{}` returns microseconds",
+                            binding.function
+                        ),
+                    ));
+                }
+            }
+            for (expr, _var, n) in comparisons
+                .iter()
+                .filter(|(_, var, n)| *var == binding.var && 0 < *n && *n < MICROS_PER_SECOND)
+            {
+                if let Some(range) = def_fb.range_for_expr(sema.db, *expr) {
+                    diags.push(make_diagnostic(
+                        range,
+                        format!(
+                            "`{var_name}` is in microseconds, as returned by `{}`, is `{n}` meant as milliseconds?",
+                            binding.function
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// The name of the function called by `expr`, if it is `timer:tc/1,2,3`.
+/// `timer:tc/4` is given the unit of the result, so it is not included.
+fn timer_tc(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    expr: ExprId,
+) -> Option<String> {
+    match &def_fb[expr] {
+        Expr::Call {
+            target: CallTarget::Remote { module, name },
+            args,
+        } => {
+            let module = def_fb.as_atom_name(sema.db, module)?;
+            let name = def_fb.as_atom_name(sema.db, name)?;
+            let is_tc = module.as_str() == "timer" && name.as_str() == "tc";
+            (is_tc && (1..=3).contains(&args.len())).then(|| format!("timer:tc/{}", args.len()))
+        }
+        Expr::MacroCall { expansion, .. } => timer_tc(sema, def_fb, *expansion),
+        _ => None,
+    }
+}
+
+/// `Ms`, `TimeMs`, `ElapsedMS`, `Millis`, `DurationMilliseconds`, ...
+fn is_millis_name(name: &str) -> bool {
+    name.ends_with("Ms") || name.ends_with("MS") || name.to_lowercase().contains("milli")
+}
+
+fn make_diagnostic(range: TextRange, message: String) -> Diagnostic {
+    Diagnostic::new(DiagnosticCode::TimerTcUnit, message, range).severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
+    use crate::tests::check_diagnostics_with_config;
+
+    #[track_caller]
+    fn check_diagnostics(fixture: &str) {
+        let config = DiagnosticsConfig::default()
+            .enable(DiagnosticCode::TimerTcUnit)
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(config, fixture)
+    }
+
+    #[test]
+    fn micros_taken_for_millis() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(F) ->
+    {TimeMs, Res} = timer:tc(F),
+%%   ^^^^^^ weak: `TimeMs` is named as milliseconds, but `timer:tc/1` returns microseconds
+    {TimeMs, Res}.
+
+bar(F) ->
+    {Time, _} = timer:tc(F, []),
+    case Time > 1000 of
+%%       ^^^^^^^^^^^ weak: `Time` is in microseconds, as returned by `timer:tc/2`, is `1000` meant as milliseconds?
+        true -> slow;
+        false -> fast
+    end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn micros_used_as_micros_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(F) ->
+    {Micros, _} = timer:tc(F),
+    Micros > 5000000.
+
+bar(F) ->
+    {Time, Res} = timer:tc(F),
+    Ms = Time div 1000,
+    {Ms, Res}.
+            "#,
+        );
+    }
+}
@@ -0,0 +1,273 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Lint: unchecked_io_result
+//!
+//! Return a hint if the result of a function returning `{ok, Handle}` or
+//! `{error, _}`, e.g. `file:open/2`, is bound to a variable which is then
+//! passed as the handle to a function of the same family, e.g.
+//! `F = file:open(Name, [read]), file:read_line(F)`.
+//!
+//! No fix is offered: matching on `{ok, F}` would turn the error into a
+//! crash, and whether that is wanted is up to the caller.
+//!
+
+use elp_ide_db::elp_base_db::FileId;
+use elp_syntax::AstNode;
+use elp_syntax::TextRange;
+use fxhash::FxHashSet;
+use hir::CallTarget;
+use hir::Expr;
+use hir::ExprId;
+use hir::FunctionDef;
+use hir::InFunctionBody;
+use hir::Pat;
+use hir::PatId;
+use hir::Semantic;
+use hir::Strategy;
+use hir::Var;
+
+use super::Diagnostic;
+use super::Severity;
+use crate::diagnostics::DiagnosticCode;
+
+/// Functions returning `{ok, Handle}` or `{error, Reason}`.
+const IO_FUNCTIONS: &[(&str, &str, usize)] = &[
+    ("file", "open", 2),
+    ("gen_tcp", "connect", 3),
+    ("gen_tcp", "connect", 4),
+    ("gen_tcp", "listen", 2),
+    ("gen_tcp", "accept", 1),
+    ("gen_tcp", "accept", 2),
+    ("gen_udp", "open", 1),
+    ("gen_udp", "open", 2),
+    ("ssl", "connect", 3),
+    ("ssl", "connect", 4),
+    ("ssl", "listen", 2),
+    ("ssl", "handshake", 1),
+    ("ssl", "handshake", 2),
+];
+
+/// The modules whose functions take the handles returned by those of a
+/// module of `IO_FUNCTIONS`, as their first argument.
+const HANDLE_MODULES: &[(&str, &[&str])] = &[
+    ("file", &["file", "io"]),
+    ("gen_tcp", &["gen_tcp", "inet"]),
+    ("gen_udp", &["gen_udp", "inet"]),
+    ("ssl", &["ssl"]),
+];
+
+/// `Var = io_function(...)`, with the function.
+struct Binding {
+    var: Var,
+    pat: PatId,
+    module: String,
+    function: String,
+}
+
+pub(crate) fn unchecked_io_result(diags: &mut Vec<Diagnostic>, sema: &Semantic, file_id: FileId) {
+    sema.def_map(file_id)
+        .get_functions()
+        .iter()
+        .for_each(|(_arity, def)| {
+            if def.file.file_id == file_id {
+                process_function(diags, sema, def)
+            }
+        });
+}
+
+fn process_function(diags: &mut Vec<Diagnostic>, sema: &Semantic, def: &FunctionDef) {
+    let def_fb = def.in_function_body(sema.db, def);
+    let body_map = def_fb.get_body_map(sema.db);
+    let source_file = sema.parse(def.file.file_id);
+
+    // Variables are scoped to the clause.
+    for (_clause_id, clause) in def_fb.clauses() {
+        let mut bindings: Vec<Binding> = Vec::new();
+        // The variables passed as first argument of a remote call, with
+        // the module called.
+        let mut handle_args: FxHashSet<(Var, String)> = FxHashSet::default();
+        for expr in &clause.exprs {
+            def_fb.fold_expr(
+                Strategy::TopDown,
+                *expr,
+                (),
+                &mut |(), ctx| match ctx.expr {
+                    Expr::Match { lhs, rhs } => {
+                        if let (Pat::Var(var), Some((module, function))) =
+                            (&def_fb[lhs], io_function(sema, &def_fb, rhs))
+                        {
+                            bindings.push(Binding {
+                                var: *var,
+                                pat: lhs,
+                                module,
+                                function,
+                            });
+                        }
+                    }
+                    Expr::Call {
+                        target: CallTarget::Remote { module, .. },
+                        args,
+                    } => {
+                        let first = args.first().map(|arg| &def_fb[*arg]);
+                        if let (Some(module), Some(Expr::Var(var))) =
+                            (def_fb.as_atom_name(sema.db, &module), first)
+                        {
+                            handle_args.insert((*var, module.as_str().to_string()));
+                        }
+                    }
+                    _ => {}
+                },
+                &mut |(), _| (),
+            );
+        }
+
+        for binding in bindings {
+            let used_as_handle = HANDLE_MODULES
+                .iter()
+                .filter(|(module, _)| *module == binding.module)
+                .flat_map(|(_, users)| users.iter())
+                .any(|user| handle_args.contains(&(binding.var, user.to_string())));
+            if !used_as_handle {
+                continue;
+            }
+            let range = match body_map
+                .pat(binding.pat)
+                .and_then(|ptr| ptr.to_node(&source_file))
+            {
+                Some(pat) => pat.syntax().text_range(),
+                None => continue,
+            };
+            let var_name = binding.var.as_string(sema.db.upcast());
+            diags.push(make_diagnostic(range, &var_name, &binding.function));
+        }
+    }
+}
+
+/// The module and the name of the function called by `expr`, if it is in
+/// `IO_FUNCTIONS`.
+fn io_function(
+    sema: &Semantic,
+    def_fb: &InFunctionBody<&FunctionDef>,
+    expr: ExprId,
+) -> Option<(String, String)> {
+    match &def_fb[expr] {
+        Expr::Call {
+            target: CallTarget::Remote { module, name },
+            args,
+        } => {
+            let module = def_fb.as_atom_name(sema.db, module)?;
+            let name = def_fb.as_atom_name(sema.db, name)?;
+            IO_FUNCTIONS
+                .iter()
+                .any(|(m, f, arity)| {
+                    module.as_str() == *m && name.as_str() == *f && args.len() == *arity
+                })
+                .then(|| {
+                    let function = format!("{}:{}/{}", module.as_str(), name.as_str(), args.len());
+                    (module.as_str().to_string(), function)
+                })
+        }
+        Expr::MacroCall { expansion, .. } => io_function(sema, def_fb, *expansion),
+        _ => None,
+    }
+}
+
+fn make_diagnostic(range: TextRange, var: &str, function: &str) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCode::UncheckedIoResult,
+        format!(
+            "`{function}` returns `{{ok, _}}` or `{{error, _}}`, but `{var}` is used as the result"
+        ),
+        range,
+    )
+    .severity(Severity::WeakWarning)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_diagnostics;
+
+    #[test]
+    fn result_used_as_handle() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/2]).
+
+foo(Name) ->
+    F = file:open(Name, [read]),
+%%  ^ 💡 weak: `file:open/2` returns `{ok, _}` or `{error, _}`, but `F` is used as the result
+    file:read_line(F).
+
+bar(Host, Port) ->
+    Sock = gen_tcp:connect(Host, Port, []),
+%%  ^^^^ 💡 weak: `gen_tcp:connect/3` returns `{ok, _}` or `{error, _}`, but `Sock` is used as the result
+    gen_tcp:send(Sock, <<"hello">>).
+            "#,
+        );
+    }
+
+    #[test]
+    fn result_checked_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(Name) ->
+    {ok, F} = file:open(Name, [read]),
+    file:read_line(F).
+
+bar(Name) ->
+    Res = file:read_file(Name),
+    case Res of
+        {ok, Bin} -> Bin;
+        {error, _} -> <<>>
+    end.
+            "#,
+        );
+    }
+
+    #[test]
+    fn result_used_as_io_device() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1]).
+
+foo(Name) ->
+    F = file:open(Name, [write]),
+%%  ^ 💡 weak: `file:open/2` returns `{ok, _}` or `{error, _}`, but `F` is used as the result
+    io:format(F, "hello~n", []).
+            "#,
+        );
+    }
+
+    #[test]
+    fn result_not_used_as_handle_not_reported() {
+        check_diagnostics(
+            r#"
+-module(main).
+-export([foo/1, bar/1]).
+
+foo(Name) ->
+    Res = file:open(Name, [read]),
+    log(Res),
+    lists:member(Res, [ok]).
+
+bar(Name) ->
+    F = file:open(Name, [read]),
+    file:write_file("copy", F).
+
+log(_) -> ok.
+            "#,
+        );
+    }
+}
@@ -9,63 +9,43 @@
 
 // Diagnostic: unused-macro
 //
-// Return a warning if a macro defined in an .erl file has no references to it
+// Return a warning if a macro defined in an .erl file has no references to it.
+// Optionally, also for a macro defined in an .hrl file included by a single
+// module, directly or through other headers.
 
 use elp_ide_assists::Assist;
 use elp_ide_db::elp_base_db::FileId;
 use elp_ide_db::source_change::SourceChange;
-use elp_ide_db::SymbolDefinition;
-use elp_syntax::AstNode;
-use elp_syntax::SyntaxKind;
+use elp_ide_db::DiagnosticsDatabase;
 use elp_syntax::TextRange;
-use elp_syntax::TextSize;
-use hir::Semantic;
 use text_edit::TextEdit;
 
 use crate::diagnostics::DiagnosticCode;
 use crate::fix;
 use crate::Diagnostic;
 
+/// The unused macros are computed by a salsa query, so they are only
+/// searched for again when something they depend on changes.
 pub(crate) fn unused_macro(
     acc: &mut Vec<Diagnostic>,
-    sema: &Semantic,
+    db: &dyn DiagnosticsDatabase,
     file_id: FileId,
     ext: Option<&str>,
-) -> Option<()> {
-    if Some("erl") == ext {
-        let def_map = sema.def_map(file_id);
-        for (name, def) in def_map.get_macros() {
-            // Only run the check for macros defined in the local module,
-            // not in the included files.
-            if def.file.file_id == file_id {
-                if !SymbolDefinition::Define(def.clone())
-                    .usages(&sema)
-                    .at_least_one()
-                {
-                    let source = def.source(sema.db.upcast());
-                    let macro_syntax = source.syntax();
-                    // If after the macro there's a new line, drop it
-                    let next_token = macro_syntax.last_token()?.next_token()?;
-                    let macro_range = if next_token.kind() == SyntaxKind::WHITESPACE
-                        && next_token.text().starts_with("\n")
-                    {
-                        let start = macro_syntax.text_range().start();
-                        let end = macro_syntax.text_range().end() + TextSize::from(1);
-                        // Temporary for T148094436
-                        let _pctx =
-                            stdx::panic_context::enter(format!("\ndiagnostics::unused_macro"));
-                        TextRange::new(start, end)
-                    } else {
-                        macro_syntax.text_range()
-                    };
-                    let name_range = source.name()?.syntax().text_range();
-                    let d = make_diagnostic(file_id, macro_range, name_range, &name.to_string());
-                    acc.push(d);
-                }
-            }
-        }
+    in_headers: bool,
+) {
+    let unused = match ext {
+        Some("erl") => db.unused_macros(file_id),
+        Some("hrl") if in_headers => db.unused_header_macros(file_id),
+        _ => return,
+    };
+    for unused in unused.iter() {
+        acc.push(make_diagnostic(
+            file_id,
+            unused.macro_range,
+            unused.name_range,
+            &unused.name,
+        ));
     }
-    Some(())
 }
 
 fn make_diagnostic(
@@ -97,9 +77,20 @@ fn delete_unused_macro(file_id: FileId, range: TextRange, name: &str) -> Assist
 #[cfg(test)]
 mod tests {
 
+    use crate::diagnostics::DiagnosticCode;
+    use crate::diagnostics::DiagnosticsConfig;
     use crate::tests::check_diagnostics;
+    use crate::tests::check_diagnostics_with_config;
     use crate::tests::check_fix;
 
+    #[track_caller]
+    fn check_diagnostics_in_headers(fixture: &str) {
+        let config = DiagnosticsConfig::default()
+            .with_unused_header_macros()
+            .disable(DiagnosticCode::MissingCompileWarnMissingSpec);
+        check_diagnostics_with_config(config, fixture)
+    }
+
     #[test]
     fn test_unused_macro() {
         check_diagnostics(
@@ -190,4 +181,115 @@ main() ->
         "#,
         );
     }
+
+    #[test]
+    fn test_unused_macro_ignore_comment() {
+        check_diagnostics(
+            r#"
+-module(main).
+% elp:ignore unused-macro
+-define(MEANING_OF_LIFE, 42).
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_unused_macro_ignore_comment_blank_line() {
+        check_diagnostics(
+            r#"
+-module(main).
+% elp:ignore unused-macro
+
+-define(MEANING_OF_LIFE, 42).
+    %%  ^^^^^^^^^^^^^^^ 💡 warning: Unused macro (MEANING_OF_LIFE)
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_unused_macro_unrelated_comment() {
+        check_diagnostics(
+            r#"
+-module(main).
+% The answer
+-define(MEANING_OF_LIFE, 42).
+    %%  ^^^^^^^^^^^^^^^ 💡 warning: Unused macro (MEANING_OF_LIFE)
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_unused_macro_hrl_single_includer() {
+        check_diagnostics_in_headers(
+            r#"
+//- /src/foo.hrl
+-define(USED, used).
+-define(UNUSED, unused).
+     %% ^^^^^^ 💡 warning: Unused macro (UNUSED)
+//- /src/foo.erl
+-module(foo).
+-include("foo.hrl").
+main() ->
+  ?USED.
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_unused_macro_hrl_many_includers() {
+        check_diagnostics_in_headers(
+            r#"
+//- /src/foo.hrl
+-define(USED, used).
+-define(UNUSED, unused).
+//- /src/foo.erl
+-module(foo).
+-include("foo.hrl").
+main() ->
+  ?USED.
+//- /src/bar.erl
+-module(bar).
+-include("foo.hrl").
+main() ->
+  ?USED.
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_unused_macro_hrl_transitive_includer() {
+        check_diagnostics_in_headers(
+            r#"
+//- /src/inner.hrl
+-define(USED, used).
+-define(UNUSED, unused).
+     %% ^^^^^^ 💡 warning: Unused macro (UNUSED)
+//- /src/outer.hrl
+-include("inner.hrl").
+//- /src/foo.erl
+-module(foo).
+-include("outer.hrl").
+main() ->
+  ?USED.
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_unused_macro_hrl_redefined_in_includer() {
+        check_diagnostics_in_headers(
+            r#"
+//- /src/foo.hrl
+-define(USED, used).
+-define(REDEFINED, header).
+//- /src/foo.erl
+-module(foo).
+-include("foo.hrl").
+-undef(REDEFINED).
+-define(REDEFINED, module).
+main() ->
+  {?USED, ?REDEFINED}.
+            "#,
+        );
+    }
 }
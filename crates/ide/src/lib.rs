@@ -13,6 +13,7 @@ use std::sync::Arc;
 use anyhow::Result;
 use call_hierarchy::CallItem;
 use diagnostics::Diagnostic;
+use diagnostics::DiagnosticCode;
 use diagnostics::DiagnosticsConfig;
 use elp_ide_assists::Assist;
 use elp_ide_assists::AssistConfig;
@@ -47,6 +48,7 @@ use elp_ide_db::ErlAstDatabase;
 use elp_ide_db::Includes;
 use elp_ide_db::LineIndex;
 use elp_ide_db::LineIndexDatabase;
+use elp_ide_db::ModuleDiff;
 use elp_ide_db::RootDatabase;
 use elp_project_model::AppName;
 use elp_project_model::AppType;
@@ -60,9 +62,12 @@ use handlers::references;
 use hir::db::MinDefDatabase;
 use hir::DefMap;
 use hir::File;
+use hir::InFile;
 use hir::Module;
 use hir::Semantic;
 use navigation_target::ToNav;
+use rayon::iter::IntoParallelIterator;
+use rayon::iter::ParallelIterator;
 
 mod annotations;
 mod call_hierarchy;
@@ -201,6 +206,22 @@ impl Analysis {
         self.with_db(|db| diagnostics::diagnostics(db, config, file_id, include_generated))
     }
 
+    /// Computes the fixes for the given file that are safe to apply
+    /// without review, e.g. on save.
+    pub fn safe_fixes(&self, file_id: FileId) -> Cancellable<Vec<Assist>> {
+        self.with_db(|db| diagnostics::get_safe_fixes(db, file_id))
+    }
+
+    /// Applies all the fixes of the diagnostic with the given code to the
+    /// file, returning the new text if anything changed.
+    pub fn apply_fixes_for_code(
+        &self,
+        file_id: FileId,
+        code: DiagnosticCode,
+    ) -> Cancellable<Option<String>> {
+        self.with_db(|db| diagnostics::apply_fixes_for_code(db, file_id, code))
+    }
+
     /// Computes the set of eqwalizer diagnostics for the given file.
     pub fn eqwalizer_diagnostics(
         &self,
@@ -218,6 +239,24 @@ impl Analysis {
         self.with_db(|db| db.eqwalizer_stats(project_id, file_id))
     }
 
+    pub fn project_eqwalizer_stats(
+        &self,
+        project_id: ProjectId,
+    ) -> Cancellable<Arc<EqwalizerStats>> {
+        self.with_db(|db| db.project_eqwalizer_stats(project_id))
+    }
+
+    /// Compares the raw and the ELP-converted eqWAlizer ASTs of a module.
+    pub fn eqwalizer_ast_diff(
+        &self,
+        project_id: ProjectId,
+        module: &str,
+    ) -> Cancellable<Result<ModuleDiff, String>> {
+        self.with_db(|db| {
+            elp_ide_db::eqwalizer_ast_diff(db, project_id, module).map_err(|err| err.to_string())
+        })
+    }
+
     /// Computes the set of EDoc diagnostics for the given file.
     pub fn edoc_diagnostics(&self, file_id: FileId) -> Cancellable<Vec<(FileId, Vec<Diagnostic>)>> {
         self.with_db(|db| diagnostics::edoc_diagnostics(db, file_id))
@@ -231,11 +270,53 @@ impl Analysis {
         self.with_db(|db| diagnostics::erlang_service_diagnostics(db, file_id))
     }
 
+    /// Computes the unused definitions of the given module, see
+    /// `diagnostics::unused_definitions`.
+    pub fn unused_definitions(&self, file_id: FileId) -> Cancellable<Vec<(FileId, Diagnostic)>> {
+        self.with_db(|db| diagnostics::unused_definitions(db, file_id))
+    }
+
+    /// Computes the unused definitions of all the modules of the
+    /// project, except dependencies, in parallel. They are sorted by file
+    /// and position, and reported once even if in a header included by
+    /// several modules.
+    pub fn project_unused_definitions(
+        &self,
+        project_id: ProjectId,
+    ) -> Cancellable<Vec<(FileId, Diagnostic)>> {
+        let module_index = self.module_index(project_id)?;
+        let mut file_ids = Vec::new();
+        for (_name, _source, file_id) in module_index.iter_own() {
+            if self.file_app_type(file_id)? != Some(AppType::Dep) {
+                file_ids.push(file_id);
+            }
+        }
+        let per_module: Vec<Vec<(FileId, Diagnostic)>> = file_ids
+            .into_par_iter()
+            .map_with(self.clone(), |analysis, file_id| {
+                analysis.unused_definitions(file_id)
+            })
+            .collect::<Cancellable<_>>()?;
+        let mut res: Vec<(FileId, Diagnostic)> = per_module.into_iter().flatten().collect();
+        res.sort_by_key(|(file_id, d)| {
+            (*file_id, d.range.start(), d.range.end(), d.code.as_code())
+        });
+        res.dedup_by(|(a_file, a), (b_file, b)| {
+            a_file == b_file && a.range == b.range && a.code == b.code
+        });
+        Ok(res)
+    }
+
     /// Low-level access to eqwalizer
     pub fn eqwalizer(&self) -> &Eqwalizer {
         self.db.eqwalizer()
     }
 
+    /// Low-level access to the eqwalizer used for the project
+    pub fn project_eqwalizer(&self, project_id: ProjectId) -> &Eqwalizer {
+        self.db.project_eqwalizer(project_id)
+    }
+
     /// eqwalizer is enabled if:
     /// - the app (the module belongs to) has `.eqwalizer` marker in the roof
     /// - or the module has `-typing([eqwalizer]).` pragma
@@ -434,6 +515,17 @@ impl Analysis {
         self.with_db(|db| db.resolved_includes(file_id))
     }
 
+    /// Returns the files directly included by the given file, that could
+    /// be resolved.
+    pub fn included_file_ids(&self, file_id: FileId) -> Cancellable<Vec<FileId>> {
+        self.with_db(|db| {
+            db.file_form_list(file_id)
+                .includes()
+                .filter_map(|(idx, _include)| db.resolve_include(InFile::new(file_id, idx)))
+                .collect()
+        })
+    }
+
     /// Returns the edit required to rename the thing at the position to the new
     /// name.
     pub fn rename(
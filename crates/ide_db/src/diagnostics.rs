@@ -0,0 +1,209 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Diagnostics computed as salsa queries rather than plain functions, so
+//! the result for a file is reused as long as what it was computed from,
+//! e.g. the def map and the usages found, is unchanged. The `ide` crate
+//! turns the results into diagnostics.
+
+use std::iter;
+use std::sync::Arc;
+
+use elp_base_db::salsa;
+use elp_base_db::FileId;
+use elp_base_db::SourceDatabase;
+use elp_base_db::Upcast;
+use elp_syntax::AstNode;
+use elp_syntax::SyntaxKind;
+use elp_syntax::TextRange;
+use elp_syntax::TextSize;
+use fxhash::FxHashSet;
+use hir::db::MinDefDatabase;
+use hir::DefineDef;
+use hir::File;
+use hir::FileKind;
+use hir::MacroName;
+use hir::Semantic;
+
+use crate::search::SearchScope;
+use crate::SymbolDefinition;
+
+#[salsa::query_group(DiagnosticsDatabaseStorage)]
+pub trait DiagnosticsDatabase:
+    MinDefDatabase + SourceDatabase + Upcast<dyn MinDefDatabase>
+{
+    /// The macros defined in the file, not in the files it includes,
+    /// that are never used.
+    fn unused_macros(&self, file_id: FileId) -> Arc<Vec<UnusedMacro>>;
+
+    /// The macros defined in a header that are never used, when the
+    /// header has a single includer. Empty otherwise, since a header
+    /// included widely can't be checked from one module.
+    fn unused_header_macros(&self, file_id: FileId) -> Arc<Vec<UnusedMacro>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusedMacro {
+    pub name: String,
+    /// The name in the definition, where the diagnostic is reported.
+    pub name_range: TextRange,
+    /// The whole definition, including the newline after it if any.
+    pub macro_range: TextRange,
+}
+
+fn unused_macros(db: &dyn DiagnosticsDatabase, file_id: FileId) -> Arc<Vec<UnusedMacro>> {
+    let sema = Semantic { db: db.upcast() };
+    let def_map = sema.def_map(file_id);
+    let mut res = Vec::new();
+    for (name, def) in def_map.get_macros() {
+        // Only run the check for macros defined in the local module,
+        // not in the included files.
+        if def.file.file_id != file_id
+            || SymbolDefinition::Define(def.clone())
+                .usages(&sema)
+                .at_least_one()
+        {
+            continue;
+        }
+        res.extend(unused_macro(&sema, name, def));
+    }
+    Arc::new(res)
+}
+
+fn unused_header_macros(db: &dyn DiagnosticsDatabase, file_id: FileId) -> Arc<Vec<UnusedMacro>> {
+    let sema = Semantic { db: db.upcast() };
+    let includer = match single_includer(&sema, file_id) {
+        Some(includer) => includer,
+        None => return Arc::new(Vec::new()),
+    };
+    // The def map of the includer covers everything it includes,
+    // directly or not, so it is the scope the header is expanded in.
+    let includer_def_map = sema.def_map(includer);
+    let scope =
+        SearchScope::files(iter::once(includer).chain(includer_def_map.get_included_files()));
+    let mut res = Vec::new();
+    for (name, def) in sema.def_map(file_id).get_macros() {
+        // A macro redefined in the includer is not the one its usages
+        // refer to, so it is left alone.
+        if def.file.file_id != file_id
+            || includer_def_map.get_macros().get(name) != Some(def)
+            || SymbolDefinition::Define(def.clone())
+                .usages(&sema)
+                .set_scope(&scope)
+                .at_least_one()
+        {
+            continue;
+        }
+        res.extend(unused_macro(&sema, name, def));
+    }
+    Arc::new(res)
+}
+
+/// The module including the header, when there is exactly one, either
+/// directly or through other headers.
+fn single_includer(sema: &Semantic, file_id: FileId) -> Option<FileId> {
+    let mut modules = FxHashSet::default();
+    let mut seen = FxHashSet::from_iter([file_id]);
+    let mut pending = vec![file_id];
+    while let Some(header) = pending.pop() {
+        let includers = SymbolDefinition::Header(File { file_id: header })
+            .usages(sema)
+            .all();
+        for (includer, _) in includers.iter() {
+            if !seen.insert(includer) {
+                continue;
+            }
+            match (File { file_id: includer }).kind(sema.db.upcast()) {
+                FileKind::Module => {
+                    modules.insert(includer);
+                }
+                FileKind::Header => pending.push(includer),
+                FileKind::Other => {}
+            }
+        }
+    }
+    match modules.into_iter().collect::<Vec<_>>().as_slice() {
+        [includer] => Some(*includer),
+        _ => None,
+    }
+}
+
+fn unused_macro(sema: &Semantic, name: &MacroName, def: &DefineDef) -> Option<UnusedMacro> {
+    let source = def.source(sema.db.upcast());
+    let macro_syntax = source.syntax();
+    // If after the macro there's a new line, drop it
+    let macro_range = match macro_syntax
+        .last_token()
+        .and_then(|token| token.next_token())
+    {
+        Some(next_token)
+            if next_token.kind() == SyntaxKind::WHITESPACE
+                && next_token.text().starts_with('\n') =>
+        {
+            let range = macro_syntax.text_range();
+            TextRange::new(range.start(), range.end() + TextSize::from(1))
+        }
+        _ => macro_syntax.text_range(),
+    };
+    let name_range = source.name()?.syntax().text_range();
+    Some(UnusedMacro {
+        name: name.to_string(),
+        name_range,
+        macro_range,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use elp_base_db::fixture::WithFixture;
+    use elp_base_db::SourceDatabaseExt;
+
+    use super::*;
+    use crate::RootDatabase;
+
+    #[test]
+    fn unused_macros_not_recomputed_for_unrelated_edit() {
+        let (mut db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+-define(UNUSED, 1).
+-define(USED, 2).
+foo() -> ?USED.
+//- /src/b.erl
+-module(b).
+bar() -> ok.
+"#,
+        );
+        let (a, b) = (file_ids[0], file_ids[1]);
+
+        let before = db.unused_macros(a);
+        assert_eq!(
+            before.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(),
+            vec!["UNUSED"]
+        );
+
+        db.set_file_text(
+            b,
+            Arc::new(
+                r#"
+-module(b).
+bar() -> ok.
+baz() -> ok.
+"#
+                .to_string(),
+            ),
+        );
+
+        let after = db.unused_macros(a);
+        assert!(Arc::ptr_eq(&before, &after));
+    }
+}
@@ -23,6 +23,7 @@ use elp_eqwalizer::ast::Error;
 use elp_eqwalizer::ipc::IpcHandle;
 use elp_eqwalizer::EqwalizerDiagnostics;
 use elp_eqwalizer::EqwalizerDiagnosticsDatabase;
+use elp_eqwalizer::EqwalizerError;
 use elp_eqwalizer::EqwalizerStats;
 use elp_syntax::ast;
 use parking_lot::Mutex;
@@ -50,8 +51,13 @@ impl EqwalizerLoader for crate::RootDatabase {
             .iter()
             .map(|&f| module_index.module_for_file(f).unwrap().as_str())
             .collect();
-        self.eqwalizer
-            .typecheck(build_info_path.as_ref(), self, project_id, module_names)
+        self.project_eqwalizer(project_id).typecheck(
+            build_info_path.as_ref(),
+            self,
+            project_id,
+            module_names,
+            None,
+        )
     }
 }
 
@@ -73,6 +79,8 @@ pub trait EqwalizerDatabase:
         project_id: ProjectId,
         file_id: FileId,
     ) -> Option<Arc<EqwalizerStats>>;
+    /// The sum of the stats of all opted-in modules of the project.
+    fn project_eqwalizer_stats(&self, project_id: ProjectId) -> Arc<EqwalizerStats>;
     fn has_eqwalizer_app_marker(&self, source_root_id: SourceRootId) -> bool;
     fn has_eqwalizer_module_marker(&self, file_id: FileId) -> bool;
     fn has_eqwalizer_ignore_marker(&self, file_id: FileId) -> bool;
@@ -90,9 +98,9 @@ fn eqwalizer_diagnostics(
     } else {
         //
         log::error!("EqWAlizing in a fixture project");
-        Arc::new(EqwalizerDiagnostics::Error(
+        Arc::new(EqwalizerDiagnostics::Error(EqwalizerError::Internal(
             "EqWAlizing in a fixture project".to_string(),
-        ))
+        )))
     }
 }
 
@@ -106,6 +114,19 @@ fn eqwalizer_stats(
     db.compute_eqwalizer_stats(project_id, ModuleName::new(module_name))
 }
 
+fn project_eqwalizer_stats(
+    db: &dyn EqwalizerDatabase,
+    project_id: ProjectId,
+) -> Arc<EqwalizerStats> {
+    let module_index = db.module_index(project_id);
+    let stats = module_index
+        .iter_own()
+        .filter(|(_name, _source, file_id)| db.is_eqwalizer_enabled(*file_id, false))
+        .filter_map(|(_name, _source, file_id)| db.eqwalizer_stats(project_id, file_id))
+        .fold(EqwalizerStats::default(), |acc, stats| acc.combine(&stats));
+    Arc::new(stats)
+}
+
 fn is_eqwalizer_enabled(
     db: &dyn EqwalizerDatabase,
     file_id: FileId,
@@ -286,10 +307,137 @@ fn is_ignore_or_fixme_atom(expr: ast::Expr) -> bool {
 #[cfg(test)]
 mod tests {
     use elp_base_db::fixture::WithFixture;
+    use elp_base_db::SourceDatabaseExt;
+    use elp_eqwalizer::ipc::EqWAlizerASTFormat;
+    use elp_eqwalizer::replay_typecheck;
+    use elp_eqwalizer::replay_typecheck_map;
+    use elp_eqwalizer::replay_typecheck_streaming;
+    use elp_eqwalizer::session::Session;
+    use elp_eqwalizer::session::SessionEntry;
+    use elp_eqwalizer::Eqwalizer;
+    use elp_syntax::TextRange;
+    use expect_test::expect;
 
     use super::*;
     use crate::RootDatabase;
 
+    #[test]
+    fn stats_not_recomputed_for_unrelated_edit() {
+        let (mut db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+-typing([eqwalizer]).
+foo() ->
+    % eqwalizer:fixme
+    ok.
+//- /src/b.erl
+-module(b).
+-typing([eqwalizer]).
+bar() ->
+    % eqwalizer:ignore
+    ok.
+"#,
+        );
+        let (a, b) = (file_ids[0], file_ids[1]);
+        let project_id = db.app_data(db.file_source_root(a)).unwrap().project_id;
+        db.ensure_erlang_service(project_id).unwrap();
+
+        let b_before = db.eqwalizer_stats(project_id, b).unwrap();
+        expect![[r#"
+            EqwalizerStats {
+                ignores: 1,
+                fixmes: 0,
+                nowarn: 0,
+                suppressions: [
+                    EqwalizerSuppression {
+                        kind: Ignore,
+                        module: "b",
+                        range: Some(
+                            47..65,
+                        ),
+                    },
+                ],
+            }
+        "#]]
+        .assert_debug_eq(&b_before);
+        let project_before = db.project_eqwalizer_stats(project_id);
+        assert_eq!(
+            (
+                project_before.ignores,
+                project_before.fixmes,
+                project_before.nowarn
+            ),
+            (1, 1, 0)
+        );
+
+        db.set_file_text(
+            a,
+            Arc::new(
+                r#"
+-module(a).
+-typing([eqwalizer]).
+foo() ->
+    % eqwalizer:fixme
+    ok.
+baz() ->
+    % eqwalizer:fixme
+    ok.
+"#
+                .to_string(),
+            ),
+        );
+
+        let b_after = db.eqwalizer_stats(project_id, b).unwrap();
+        assert!(Arc::ptr_eq(&b_before, &b_after));
+        let project_after = db.project_eqwalizer_stats(project_id);
+        assert_eq!(
+            (
+                project_after.ignores,
+                project_after.fixmes,
+                project_after.nowarn
+            ),
+            (1, 2, 0)
+        );
+        let mut modules: Vec<&str> = project_after
+            .suppressions
+            .iter()
+            .map(|suppression| suppression.module.as_str())
+            .collect();
+        modules.sort();
+        assert_eq!(modules, vec!["a", "a", "b"]);
+    }
+
+    #[test]
+    fn fold_modules_skips_excluded_modules() {
+        let (db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+-typing([eqwalizer]).
+//- /src/b.erl
+-module(b).
+"#,
+        );
+        let project_id = db
+            .app_data(db.file_source_root(file_ids[0]))
+            .unwrap()
+            .project_id;
+        db.ensure_erlang_service(project_id).unwrap();
+
+        let modules = elp_eqwalizer::fold_modules(
+            &db,
+            project_id,
+            |file_id| db.is_eqwalizer_enabled(file_id, false),
+            Vec::new(),
+            |mut acc, module, _ast| {
+                acc.push(module.as_str().to_string());
+                acc
+            },
+        );
+        assert_eq!(modules, vec!["a".to_string()]);
+    }
+
     #[test]
     fn test_has_eqwalizer_module_marker() {
         let (db, file_id) = RootDatabase::with_single_file(
@@ -365,4 +513,284 @@ mod tests {
 
         assert!(db.has_eqwalizer_ignore_marker(file_id));
     }
+
+    fn replay_session(entries: Vec<SessionEntry>) -> Session {
+        Session {
+            entries: entries.into(),
+        }
+    }
+
+    fn received(line: &str) -> SessionEntry {
+        SessionEntry::Received(format!("{}\n", line))
+    }
+
+    fn sent(msg: &str) -> SessionEntry {
+        SessionEntry::Sent(msg.to_string())
+    }
+
+    /// A diagnostic of eqWAlizer, as JSON, with the given range.
+    fn diagnostic(message: &str, start: u32, end: u32) -> String {
+        format!(
+            concat!(
+                r#"{{"range":{{"start":{},"end":{}}},"message":"{}","uri":"","code":"c","#,
+                r#""expressionOrNull":null,"explanationOrNull":null}}"#
+            ),
+            start, end, message
+        )
+    }
+
+    fn diagnostic_messages(diagnostics: &EqwalizerDiagnostics) -> Vec<(String, String)> {
+        let mut messages = match diagnostics {
+            EqwalizerDiagnostics::Diagnostics(by_module) => by_module
+                .iter()
+                .flat_map(|(module, diags)| {
+                    diags
+                        .iter()
+                        .map(move |diag| (module.clone(), diag.message.clone()))
+                })
+                .collect(),
+            _ => panic!("expected diagnostics, got {:?}", diagnostics),
+        };
+        messages.sort();
+        messages
+    }
+
+    #[test]
+    fn replay_module_without_ast() {
+        let (db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+foo( ->
+"#,
+        );
+        let project_id = db
+            .app_data(db.file_source_root(file_ids[0]))
+            .unwrap()
+            .project_id;
+        db.ensure_erlang_service(project_id).unwrap();
+        let session = replay_session(vec![
+            received(r#"{"tag":"GetAstBytes","content":{"module":"a","format":"RawForms"}}"#),
+            sent(r#"{"tag":"CannotCompleteRequest"}"#),
+        ]);
+
+        assert_eq!(
+            replay_typecheck(session, &db, project_id),
+            EqwalizerDiagnostics::NoAst {
+                module: "a".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn replay_crash() {
+        let (db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+"#,
+        );
+        let project_id = db
+            .app_data(db.file_source_root(file_ids[0]))
+            .unwrap()
+            .project_id;
+        // eqWAlizer died after starting on `a`
+        let session = replay_session(vec![received(
+            r#"{"tag":"EqwalizingStart","content":{"module":"a"}}"#,
+        )]);
+
+        assert!(matches!(
+            replay_typecheck(session, &db, project_id),
+            EqwalizerDiagnostics::Error(EqwalizerError::Killed(_))
+        ));
+    }
+
+    #[test]
+    fn replay_dependencies_are_not_answered() {
+        let (db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+-export([foo/0]).
+foo() -> b:bar().
+//- /src/b.erl
+-module(b).
+-export([bar/0]).
+bar() -> ok.
+"#,
+        );
+        let project_id = db
+            .app_data(db.file_source_root(file_ids[0]))
+            .unwrap()
+            .project_id;
+        db.ensure_erlang_service(project_id).unwrap();
+        let done = r#"{"tag":"Done","content":{"diagnostics":{}}}"#;
+        let session = replay_session(vec![
+            received(r#"{"tag":"EnteringModule","content":{"module":"a"}}"#),
+            sent(r#"{"tag":"ELPEnteringModule"}"#),
+            received(r#"{"tag":"Dependencies","content":{"modules":["b"]}}"#),
+            received(done),
+            sent(r#"{"tag":"ELPExitingModule"}"#),
+            received(done),
+        ]);
+        assert_eq!(
+            replay_typecheck_streaming(session, &db, project_id, |_, _| ()),
+            EqwalizerDiagnostics::default()
+        );
+    }
+
+    #[test]
+    fn diagnostics_in_range() {
+        let (db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+foo() -> ok.
+bar() -> ok.
+"#,
+        );
+        let project_id = db
+            .app_data(db.file_source_root(file_ids[0]))
+            .unwrap()
+            .project_id;
+        let done = format!(
+            r#"{{"tag":"Done","content":{{"diagnostics":{{"a":[{},{}]}}}}}}"#,
+            diagnostic("foo", 12, 15),
+            diagnostic("bar", 25, 28)
+        );
+        let session = replay_session(vec![
+            sent(r#"{"tag":"ELPEnteringModule"}"#),
+            received(&done),
+        ]);
+        db.set_module_ipc_handle(
+            ModuleName::new("a"),
+            Arc::new(Mutex::new(IpcHandle::replay(session))),
+        );
+
+        // The range of `bar`
+        let range = TextRange::new(25.into(), 38.into());
+        let in_range = db.module_diagnostics_in_range(project_id, "a".to_string(), range);
+        assert_eq!(
+            diagnostic_messages(&in_range),
+            vec![("a".to_string(), "bar".to_string())]
+        );
+    }
+
+    #[test]
+    fn replay_map_keeps_modules_apart() {
+        let (db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+//- /src/b.erl
+-module(b).
+"#,
+        );
+        let project_id = db
+            .app_data(db.file_source_root(file_ids[0]))
+            .unwrap()
+            .project_id;
+        let done_a = format!(
+            r#"{{"tag":"Done","content":{{"diagnostics":{{"a":[{}]}}}}}}"#,
+            diagnostic("in a", 0, 1)
+        );
+        // eqWAlizer died after checking `a`
+        let session = replay_session(vec![
+            received(r#"{"tag":"EnteringModule","content":{"module":"a"}}"#),
+            sent(r#"{"tag":"ELPEnteringModule"}"#),
+            received(&done_a),
+            sent(r#"{"tag":"ELPExitingModule"}"#),
+        ]);
+
+        let results = replay_typecheck_map(session, &db, project_id, vec!["a", "b"]);
+        assert_eq!(
+            diagnostic_messages(&results["a"]),
+            vec![("a".to_string(), "in a".to_string())]
+        );
+        assert!(matches!(
+            results["b"],
+            EqwalizerDiagnostics::Error(EqwalizerError::Killed(_))
+        ));
+    }
+
+    #[test]
+    fn replay_streaming_reports_each_module() {
+        let (db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+//- /src/b.erl
+-module(b).
+"#,
+        );
+        let project_id = db
+            .app_data(db.file_source_root(file_ids[0]))
+            .unwrap()
+            .project_id;
+        let done = |module: &str| {
+            format!(
+                r#"{{"tag":"Done","content":{{"diagnostics":{{"{}":[{}]}}}}}}"#,
+                module,
+                diagnostic(&format!("in {}", module), 0, 1)
+            )
+        };
+        let mut entries = vec![];
+        for module in ["a", "b"] {
+            entries.push(received(&format!(
+                r#"{{"tag":"EnteringModule","content":{{"module":"{}"}}}}"#,
+                module
+            )));
+            entries.push(sent(r#"{"tag":"ELPEnteringModule"}"#));
+            entries.push(received(&done(module)));
+            entries.push(sent(r#"{"tag":"ELPExitingModule"}"#));
+        }
+        entries.push(received(r#"{"tag":"Done","content":{"diagnostics":{}}}"#));
+
+        let mut streamed = vec![];
+        let diagnostics = replay_typecheck_streaming(
+            replay_session(entries),
+            &db,
+            project_id,
+            |module, diags| {
+                streamed.push((module, diags.len()));
+            },
+        );
+        assert_eq!(streamed, vec![("a".to_string(), 1), ("b".to_string(), 1)]);
+        assert_eq!(
+            diagnostic_messages(&diagnostics),
+            vec![
+                ("a".to_string(), "in a".to_string()),
+                ("b".to_string(), "in b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn export_ast_is_what_eqwalizer_receives() {
+        let (db, file_ids) = RootDatabase::with_many_files(
+            r#"
+//- /src/a.erl
+-module(a).
+foo() -> ok.
+"#,
+        );
+        let project_id = db
+            .app_data(db.file_source_root(file_ids[0]))
+            .unwrap()
+            .project_id;
+        db.ensure_erlang_service(project_id).unwrap();
+        let eqwalizer = Eqwalizer::default();
+
+        let exported = eqwalizer
+            .export_ast(&db, project_id, "a", EqWAlizerASTFormat::RawForms)
+            .unwrap();
+        let sent = db
+            .get_erl_ast_bytes(project_id, ModuleName::new("a"))
+            .unwrap();
+        assert_eq!(exported, *sent);
+        assert_eq!(
+            eqwalizer.export_ast(&db, project_id, "nope", EqWAlizerASTFormat::RawForms),
+            Err(Error::ModuleNotFound("nope".to_string()))
+        );
+    }
 }
@@ -8,6 +8,7 @@
  */
 
 use std::fmt;
+use std::iter;
 use std::panic::AssertUnwindSafe;
 use std::panic::RefUnwindSafe;
 use std::path::PathBuf;
@@ -42,6 +43,7 @@ use serde::Serialize;
 
 mod apply_change;
 mod defs;
+mod diagnostics;
 pub mod docs;
 pub mod eqwalizer;
 mod erl_ast;
@@ -60,12 +62,22 @@ pub use defs::ReferenceClass;
 pub use defs::ReferenceType;
 pub use defs::SymbolClass;
 pub use defs::SymbolDefinition;
+pub use diagnostics::DiagnosticsDatabase;
+pub use diagnostics::UnusedMacro;
 pub use elp_base_db;
 pub use elp_base_db::impl_intern_key;
+pub use elp_eqwalizer::ast::diff::diff_module as eqwalizer_ast_diff;
+pub use elp_eqwalizer::ast::diff::FormDiff;
+pub use elp_eqwalizer::ast::diff::ModuleDiff;
 pub use elp_eqwalizer::Eqwalizer;
+pub use elp_eqwalizer::EqwalizerBuilder;
 pub use elp_eqwalizer::EqwalizerDiagnostic;
 pub use elp_eqwalizer::EqwalizerDiagnostics;
+pub use elp_eqwalizer::EqwalizerError;
+pub use elp_eqwalizer::EqwalizerSeverity;
 pub use elp_eqwalizer::EqwalizerStats;
+pub use elp_eqwalizer::EqwalizerSuppression;
+pub use elp_eqwalizer::FixmeKind;
 pub use elp_erlang_service as erlang_service;
 pub use eqwalizer::EqwalizerDatabase;
 pub use erl_ast::ErlAstDatabase;
@@ -91,6 +103,7 @@ pub trait EqwalizerProgressReporter: Send + Sync + RefUnwindSafe {
 
 #[salsa::database(
     LineIndexDatabaseStorage,
+    diagnostics::DiagnosticsDatabaseStorage,
     docs::DocDatabaseStorage,
     elp_base_db::SourceDatabaseExtStorage,
     elp_base_db::SourceDatabaseStorage,
@@ -105,6 +118,8 @@ pub struct RootDatabase {
     storage: salsa::Storage<Self>,
     erlang_services: Arc<AssertUnwindSafe<RwLock<FxHashMap<ProjectId, Connection>>>>,
     eqwalizer: Eqwalizer,
+    /// Projects pinned to an eqWAlizer other than the default one.
+    project_eqwalizers: FxHashMap<ProjectId, Eqwalizer>,
     eqwalizer_progress_reporter: EqwalizerProgressReporterBox,
     ipc_handles: Arc<AssertUnwindSafe<RwLock<FxHashMap<String, Arc<Mutex<IpcHandle>>>>>>,
 }
@@ -115,6 +130,7 @@ impl Default for RootDatabase {
             storage: salsa::Storage::default(),
             erlang_services: Arc::default(),
             eqwalizer: Eqwalizer::default(),
+            project_eqwalizers: FxHashMap::default(),
             eqwalizer_progress_reporter: EqwalizerProgressReporterBox::default(),
             ipc_handles: Arc::default(),
         };
@@ -161,6 +177,7 @@ impl salsa::ParallelDatabase for RootDatabase {
             storage: self.storage.snapshot(),
             erlang_services: self.erlang_services.clone(),
             eqwalizer: self.eqwalizer.clone(),
+            project_eqwalizers: self.project_eqwalizers.clone(),
             eqwalizer_progress_reporter: self.eqwalizer_progress_reporter.clone(),
             ipc_handles: self.ipc_handles.clone(),
         })
@@ -216,8 +233,55 @@ impl RootDatabase {
         &self.eqwalizer
     }
 
+    /// The eqWAlizer to typecheck the project with.
+    pub fn project_eqwalizer(&self, project_id: ProjectId) -> &Eqwalizer {
+        self.project_eqwalizers
+            .get(&project_id)
+            .unwrap_or(&self.eqwalizer)
+    }
+
+    /// Use the eqWAlizer the project is pinned to in its `.elp.toml`,
+    /// if any, for the project. A pinned eqWAlizer that cannot be set up
+    /// fails the typechecks of the project, not its loading.
+    pub fn ensure_eqwalizer(&mut self, project_id: ProjectId) {
+        let project_data = self.project_data(project_id);
+        match &project_data.eqwalizer_config.path {
+            Some(path) => {
+                let path = project_data.root_dir.join(path);
+                let mut builder = EqwalizerBuilder::default()
+                    .path(path.into())
+                    .shell(self.eqwalizer.shell)
+                    .verbose(self.eqwalizer.verbose)
+                    .deterministic(self.eqwalizer.deterministic);
+                if let Some(timeout) = self.eqwalizer.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                self.project_eqwalizers.insert(project_id, builder.build());
+            }
+            None => {
+                self.project_eqwalizers.remove(&project_id);
+            }
+        }
+    }
+
+    fn eqwalizers_mut(&mut self) -> impl Iterator<Item = &mut Eqwalizer> {
+        iter::once(&mut self.eqwalizer).chain(self.project_eqwalizers.values_mut())
+    }
+
     pub fn in_shell(&mut self) -> () {
-        self.eqwalizer.shell = true
+        self.eqwalizers_mut()
+            .for_each(|eqwalizer| eqwalizer.shell = true)
+    }
+
+    pub fn set_eqwalizer_verbose(&mut self, verbose: bool) {
+        self.eqwalizers_mut()
+            .for_each(|eqwalizer| eqwalizer.verbose = verbose)
+    }
+
+    /// For tests, see `Eqwalizer::deterministic`.
+    pub fn set_eqwalizer_deterministic(&mut self, deterministic: bool) {
+        self.eqwalizers_mut()
+            .for_each(|eqwalizer| eqwalizer.deterministic = deterministic)
     }
 
     pub fn resolved_includes(&self, file_id: FileId) -> Option<Includes> {
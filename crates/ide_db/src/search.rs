@@ -99,7 +99,7 @@ impl SearchScope {
         }
     }
 
-    fn files(files: impl Iterator<Item = FileId>) -> SearchScope {
+    pub(crate) fn files(files: impl Iterator<Item = FileId>) -> SearchScope {
         SearchScope {
             entries: files.map(|file_id| (file_id, None)).collect(),
         }
@@ -58,6 +58,7 @@ pub const ELP_CONFIG_FILE: &str = ".elp.toml";
 //
 // [eqwalizer]
 // enable_all = true
+// path = "tools/eqwalizer.jar"
 //```
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Deserialize)]
 pub struct ElpConfig {
@@ -166,6 +167,10 @@ impl BuckConfig {
 pub struct EqwalizerConfig {
     #[serde(default)]
     pub enable_all: bool,
+    /// The eqWAlizer executable or jar to use for this project, relative
+    /// to the project root, instead of the one ELP comes with.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
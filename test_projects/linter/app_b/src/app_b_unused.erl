@@ -0,0 +1,6 @@
+-module(app_b_unused).
+-export([foo/0]).
+-define(UNUSED, 1).
+
+foo() ->
+    ok.